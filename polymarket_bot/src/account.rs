@@ -0,0 +1,151 @@
+//! The account's resting orders and positions, kept current from the
+//! Polymarket user channel. Position accounting itself lives in
+//! [`crate::positions`].
+//!
+//! A trade is reported several times as it settles; each fill is counted
+//! into the position once, when first seen, and taken back out if the trade
+//! ends up `FAILED`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::polymarket::user_ws::{Fill, OrderEvent, OrderEventType, TradeStatus, UserEvent};
+use crate::positions::Positions;
+use crate::strategy::Side;
+
+#[derive(Debug, Clone, Default)]
+pub struct Account {
+    /// API key the user channel reports as `owner` for our orders.
+    owner: String,
+    orders: HashMap<String, OrderEvent>,
+    positions: Positions,
+    /// `(trade id, order id)` of fills counted into `positions`.
+    counted: HashSet<(String, String)>,
+}
+
+/// What one user-channel event changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountUpdate {
+    /// Fills newly counted into positions.
+    pub fills: Vec<Fill>,
+    /// Fills taken back out because their trade failed.
+    pub reverted: Vec<Fill>,
+}
+
+impl Account {
+    pub fn new(owner: impl Into<String>) -> Self {
+        Self { owner: owner.into(), ..Self::default() }
+    }
+
+    pub fn apply(&mut self, event: &UserEvent) -> AccountUpdate {
+        match event {
+            UserEvent::Order(order) => {
+                self.apply_order(order);
+                AccountUpdate::default()
+            }
+            UserEvent::Trade(trade) => {
+                let mut update = AccountUpdate::default();
+                for fill in trade.fills(&self.owner) {
+                    let key = (fill.trade_id.clone(), fill.order_id.clone());
+                    match (fill.status, self.counted.contains(&key)) {
+                        (TradeStatus::Failed, true) => {
+                            self.counted.remove(&key);
+                            self.positions.apply(&fill.market, &fill.asset_id, opposite(fill.side), fill.price, fill.size);
+                            self.positions.charge_fee(&fill.asset_id, -fill.fee);
+                            update.reverted.push(fill);
+                        }
+                        (TradeStatus::Failed, false) | (_, true) => {}
+                        (_, false) => {
+                            self.counted.insert(key);
+                            self.positions.apply(&fill.market, &fill.asset_id, fill.side, fill.price, fill.size);
+                            self.positions.charge_fee(&fill.asset_id, fill.fee);
+                            update.fills.push(fill);
+                        }
+                    }
+                }
+                update
+            }
+            UserEvent::Unknown { .. } | UserEvent::Malformed { .. } => AccountUpdate::default(),
+        }
+    }
+
+    fn apply_order(&mut self, order: &OrderEvent) {
+        if order.kind == OrderEventType::Cancellation || order.remaining() <= 0.0 {
+            self.orders.remove(&order.id);
+        } else {
+            self.orders.insert(order.id.clone(), order.clone());
+        }
+    }
+
+    /// Orders still resting on the book.
+    pub fn open_orders(&self) -> impl Iterator<Item = &OrderEvent> {
+        self.orders.values()
+    }
+
+    /// Net shares of `asset_id` (negative when short).
+    pub fn position(&self, asset_id: &str) -> f64 {
+        self.positions.shares(asset_id)
+    }
+
+    pub fn positions(&self) -> &Positions {
+        &self.positions
+    }
+
+    /// Settle `asset_id` at `payout` per share once its market resolves.
+    pub fn redeem(&mut self, asset_id: &str, payout: f64) {
+        self.positions.redeem(asset_id, payout);
+    }
+}
+
+/// A failed trade is undone by trading the same size back at the same price.
+fn opposite(side: Side) -> Side {
+    match side {
+        Side::Buy => Side::Sell,
+        Side::Sell => Side::Buy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polymarket::user_ws::parse_message;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/polymarket/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    #[test]
+    fn tracks_resting_orders() {
+        let mut account = Account::new("maker-key");
+        let events = parse_message(&fixture("user_order.json")).unwrap();
+        account.apply(&events[0]);
+        account.apply(&events[1]);
+        let open: Vec<&OrderEvent> = account.open_orders().collect();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].size_matched, 4.0);
+
+        let UserEvent::Order(mut cancelled) = events[1].clone() else { unreachable!() };
+        cancelled.kind = OrderEventType::Cancellation;
+        account.apply(&UserEvent::Order(cancelled));
+        assert_eq!(account.open_orders().count(), 0);
+    }
+
+    #[test]
+    fn counts_each_fill_once_and_reverts_failed_trades() {
+        let mut account = Account::new("maker-key");
+        let events = parse_message(&fixture("user_trade.json")).unwrap();
+        let UserEvent::Trade(trade) = &events[0] else { unreachable!() };
+        let (yes, no) = (trade.maker_orders[0].asset_id.clone(), trade.maker_orders[1].asset_id.clone());
+
+        assert_eq!(account.apply(&events[0]).fills.len(), 2);
+        let mut confirmed = trade.clone();
+        confirmed.status = TradeStatus::Confirmed;
+        assert!(account.apply(&UserEvent::Trade(confirmed)).fills.is_empty());
+        assert_eq!((account.position(&yes), account.position(&no)), (-6.0, 4.0));
+
+        let mut failed = trade.clone();
+        failed.status = TradeStatus::Failed;
+        assert_eq!(account.apply(&UserEvent::Trade(failed)).reverted.len(), 2);
+        assert_eq!((account.position(&yes), account.position(&no)), (0.0, 0.0));
+    }
+}
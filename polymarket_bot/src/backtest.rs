@@ -0,0 +1,271 @@
+//! Backtests: how a configuration would have fared on captured feeds.
+//!
+//! A backtest is a [`Replay`] with the `backtest` latencies and taker fee,
+//! scored per market: PnL marked to the final books, the share of filled
+//! orders that were worth more than they cost, and the deepest fall of PnL
+//! from its running peak, sampled after every frame.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::config::Settings;
+use crate::engine::{Engine, Verdict};
+use crate::polymarket::order::OrderError;
+use crate::positions::Pnl;
+use crate::recorder::CapturedFrame;
+use crate::replay::Replay;
+use crate::strategy::Side;
+
+/// How one market, or all of them together, did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarketReport {
+    /// Signals the strategy raised.
+    pub signals: u64,
+    /// Orders sent to the exchange.
+    pub orders: u64,
+    /// Orders that filled, at least in part.
+    pub filled: u64,
+    /// Filled orders worth more than they cost, fees included, at the final
+    /// mid. Orders on a token without a final book count as misses.
+    pub hits: u64,
+    /// Taker fees paid, already part of the realized PnL.
+    pub fees: f64,
+    pub pnl: Pnl,
+    /// Largest fall of total PnL from its running peak.
+    pub max_drawdown: f64,
+}
+
+impl MarketReport {
+    /// `hits / filled`, if anything filled.
+    pub fn hit_rate(&self) -> Option<f64> {
+        (self.filled > 0).then(|| self.hits as f64 / self.filled as f64)
+    }
+
+    fn add(&mut self, other: &MarketReport) {
+        self.signals += other.signals;
+        self.orders += other.orders;
+        self.filled += other.filled;
+        self.hits += other.hits;
+        self.fees += other.fees;
+        self.pnl.realized += other.pnl.realized;
+        self.pnl.unrealized += other.pnl.unrealized;
+        self.pnl.exposure += other.pnl.exposure;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BacktestReport {
+    /// Frames replayed.
+    pub frames: u64,
+    /// Every configured market, keyed on market id.
+    pub markets: BTreeMap<String, MarketReport>,
+    pub total: MarketReport,
+    /// Change of total PnL over each minute of capture time that had frames,
+    /// oldest first.
+    pub minute_pnl: Vec<f64>,
+}
+
+/// Running peak of a PnL series and the deepest fall from it.
+#[derive(Debug, Default)]
+struct Drawdown {
+    peak: f64,
+    max: f64,
+}
+
+impl Drawdown {
+    fn update(&mut self, pnl: f64) {
+        self.peak = self.peak.max(pnl);
+        self.max = self.max.max(self.peak - pnl);
+    }
+}
+
+/// Total PnL changes per wall-clock minute.
+#[derive(Debug, Default)]
+struct MinutePnl {
+    changes: Vec<f64>,
+    minute: Option<i64>,
+    /// Total PnL when the current minute started.
+    start: f64,
+    last: f64,
+}
+
+impl MinutePnl {
+    fn update(&mut self, now_ms: i64, pnl: f64) {
+        let minute = now_ms.div_euclid(60_000);
+        if self.minute.is_some_and(|m| m != minute) {
+            self.changes.push(self.last - self.start);
+            self.start = self.last;
+        }
+        self.minute = Some(minute);
+        self.last = pnl;
+    }
+
+    fn finish(mut self) -> Vec<f64> {
+        if self.minute.is_some() {
+            self.changes.push(self.last - self.start);
+        }
+        self.changes
+    }
+}
+
+/// Replay `frames` against the markets in `settings` and score the result.
+pub async fn run(settings: &Settings, frames: &[CapturedFrame]) -> Result<BacktestReport, OrderError> {
+    let mut drawdowns: HashMap<String, Drawdown> = HashMap::new();
+    let mut total_drawdown = Drawdown::default();
+    let mut minute_pnl = MinutePnl::default();
+    let mut replay = Replay::new(settings)?;
+    let replayed = replay
+        .run_observed(frames, |engine, now_ms| {
+            let positions = engine.account().positions();
+            let mut total = 0.0;
+            for (market, pnl) in positions.market_pnl(engine.books()) {
+                drawdowns.entry(market.to_string()).or_default().update(pnl.total());
+                total += pnl.total();
+            }
+            total_drawdown.update(total);
+            minute_pnl.update(now_ms, total);
+        })
+        .await;
+
+    let mut markets: BTreeMap<String, MarketReport> =
+        settings.markets.iter().map(|m| (m.market_id.clone(), MarketReport::default())).collect();
+    let market_of: HashMap<&str, &str> = settings
+        .markets
+        .iter()
+        .flat_map(|m| [(m.yes_token_id.as_str(), m.market_id.as_str()), (m.no_token_id.as_str(), m.market_id.as_str())])
+        .collect();
+
+    for decision in &replayed.decisions {
+        let report = markets.entry(decision.signal.market_id.clone()).or_default();
+        report.signals += 1;
+        if let Verdict::Submitted { .. } = decision.verdict {
+            report.orders += 1;
+        }
+    }
+    for (market, value) in order_values(replay.engine(), &market_of) {
+        let report = markets.entry(market.to_string()).or_default();
+        report.filled += 1;
+        if value.is_some_and(|v| v > 0.0) {
+            report.hits += 1;
+        }
+    }
+    for fill in &replayed.fills {
+        if let Some(market) = market_of.get(fill.asset_id.as_str()) {
+            markets.entry(market.to_string()).or_default().fees += fill.fee;
+        }
+    }
+    for (market, pnl) in &replayed.pnl {
+        markets.entry(market.clone()).or_default().pnl = *pnl;
+    }
+    for (market, drawdown) in drawdowns {
+        markets.entry(market).or_default().max_drawdown = drawdown.max;
+    }
+
+    let mut total = MarketReport { max_drawdown: total_drawdown.max, ..MarketReport::default() };
+    for report in markets.values() {
+        total.add(report);
+    }
+    Ok(BacktestReport { frames: replayed.frames, markets, total, minute_pnl: minute_pnl.finish() })
+}
+
+/// Market of every filled order and what it was worth at the final mid of
+/// its token, net of fees; `None` without a final mid.
+fn order_values<'a>(engine: &Engine, market_of: &HashMap<&str, &'a str>) -> Vec<(&'a str, Option<f64>)> {
+    // Fills per local order id, in id order for a stable result.
+    let mut orders: BTreeMap<u64, Vec<_>> = BTreeMap::new();
+    for (id, fill) in engine.oms().fills() {
+        orders.entry(*id).or_default().push(fill);
+    }
+    orders
+        .into_values()
+        .filter_map(|fills| {
+            let market = *market_of.get(fills[0].asset_id.as_str())?;
+            let mark = engine.books().get(&fills[0].asset_id).and_then(|b| b.mid());
+            let value = mark.map(|mark| {
+                fills
+                    .iter()
+                    .map(|f| {
+                        let edge = match f.side {
+                            Side::Buy => mark - f.price,
+                            Side::Sell => f.price - mark,
+                        };
+                        edge * f.size - f.fee
+                    })
+                    .sum()
+            });
+            Some((market, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::read_capture;
+    use std::path::Path;
+
+    fn settings(backtest: &str) -> Settings {
+        let yaml = format!(
+            r#"
+spot_venue: binance
+markets:
+  - market_id: "0xabc"
+    symbol: "btc/usdt"
+    yes_token_id: "1111"
+    no_token_id: "2222"
+    threshold_pct: 0.005
+    order_type: fok
+risk:
+  max_notional_per_trade: 10.0
+  self_slippage_buffer_pct: 0.0
+backtest:
+{}
+"#,
+            backtest
+        );
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    async fn backtest(backtest: &str) -> BacktestReport {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay/capture.jsonl");
+        run(&settings(backtest), &read_capture(&path).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn scores_pnl_hits_and_drawdown_per_market() {
+        let report = backtest("  fee_rate_bps: 0").await;
+        let market = &report.markets["0xabc"];
+        assert_eq!((market.signals, market.orders, market.filled, market.hits), (2, 2, 2, 1));
+        assert_eq!(market.hit_rate(), Some(0.5));
+        assert_eq!(market.fees, 0.0);
+        // YES bought at 0.5 marks at 0.625, NO bought at 0.25 marks at 0.225.
+        assert!((market.pnl.total() - 1.5).abs() < 1e-9, "{:?}", market.pnl);
+        // Right after the NO fill: 2.5 on YES, then -1.0 on NO.
+        assert!((market.max_drawdown - 1.0).abs() < 1e-9, "{}", market.max_drawdown);
+        assert_eq!(report.total, *market);
+        // The whole capture falls within one minute.
+        assert_eq!(report.minute_pnl.len(), 1);
+        assert!((report.minute_pnl[0] - 1.5).abs() < 1e-9, "{:?}", report.minute_pnl);
+    }
+
+    #[tokio::test]
+    async fn charges_taker_fees() {
+        let report = backtest("  fee_rate_bps: 1000").await;
+        let market = &report.markets["0xabc"];
+        // 10% of min(p, 1 - p) per share: 20 * 0.5 * 0.1 + 40 * 0.25 * 0.1.
+        assert!((market.fees - 2.0).abs() < 1e-9, "{}", market.fees);
+        assert!((market.pnl.total() - (1.5 - 2.0)).abs() < 1e-9, "{:?}", market.pnl);
+        // YES still clears its 1.0 fee.
+        assert_eq!(market.hits, 1);
+    }
+
+    #[tokio::test]
+    async fn fills_against_the_book_at_arrival() {
+        // The NO order is decided at 4s but reaches the exchange after the
+        // 5s frame; the YES order arrives after the 3s book moved its ask
+        // to 0.65, above its 0.5 limit, and the FOK is killed.
+        let report = backtest("  decision_to_exchange_ms: 1500\n  exchange_to_ack_ms: 200").await;
+        let market = &report.markets["0xabc"];
+        assert_eq!((market.signals, market.orders, market.filled), (2, 2, 1));
+        assert!((market.pnl.total() - 40.0 * -0.025).abs() < 1e-9, "{:?}", market.pnl);
+    }
+}
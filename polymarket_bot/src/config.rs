@@ -0,0 +1,862 @@
+//! Typed configuration for the latency bot.
+//!
+//! Mirrors `latency_bot.config` on the Python side so both implementations can
+//! share `config.example.yaml`. Values of the form `${VAR}` (or `${VAR:-default}`)
+//! are expanded from the environment before the file is deserialized, and every
+//! validation error names the YAML key it refers to.
+
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::polymarket::eip712::Address;
+
+pub const CONFIG_ENV_VAR: &str = "LATENCY_BOT_CONFIG";
+const DEFAULT_CONFIG_PATH: &str = "config.yaml";
+
+/// A string that is never printed by `Debug`.
+#[derive(Clone, Deserialize, Default)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(**********)")
+    }
+}
+
+/// Exchange used as the spot price source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpotVenue {
+    Binance,
+    Kraken,
+    Coinbase,
+}
+
+/// How orders are signed, i.e. who holds the funds (`signatureType` on the order).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureType {
+    /// The private key's own address holds the funds.
+    #[default]
+    Eoa,
+    /// A Polymarket proxy wallet (email/Magic accounts) holds the funds.
+    PolyProxy,
+    /// A Gnosis Safe proxy (browser-wallet accounts) holds the funds.
+    PolyGnosisSafe,
+}
+
+/// Time in force of the orders a market sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderType {
+    /// Good till cancelled: rests until filled or cancelled.
+    #[default]
+    Gtc,
+    /// Good till date: rests until `order_ttl_secs` after submission.
+    Gtd,
+    /// Fill or kill: fills completely and immediately, or not at all.
+    Fok,
+    /// Fill and kill: fills what it can immediately, the rest is cancelled.
+    Fak,
+}
+
+/// Per-market threshold configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketConfig {
+    /// Condition id of the Polymarket market (`0x…`), as the CLOB and the user
+    /// channel name it.
+    pub market_id: String,
+    /// Spot pair to monitor, e.g. `xbt/usdt`.
+    pub symbol: String,
+    pub yes_token_id: String,
+    pub no_token_id: String,
+    /// Spot venue for this market's symbol; falls back to the top-level `spot_venue`.
+    #[serde(default)]
+    pub spot_venue: Option<SpotVenue>,
+    /// Whether YES pays out when the asset finishes higher.
+    #[serde(default = "default_true")]
+    pub yes_is_upside: bool,
+    /// Relative move (0.02 = 2%) that triggers a trade.
+    #[serde(default = "default_threshold_pct")]
+    pub threshold_pct: f64,
+    /// Oldest reference price a move is measured against; an older one is
+    /// replaced by the next tick. Unset keeps it until a trade re-anchors it.
+    #[serde(default)]
+    pub reference_window_ms: Option<u64>,
+    /// Maximum quote currency held and on order in this market.
+    #[serde(default = "default_max_position")]
+    pub max_position: f64,
+    #[serde(default)]
+    pub order_type: OrderType,
+    /// Reject the order instead of letting it take liquidity (gtc/gtd only).
+    #[serde(default)]
+    pub post_only: bool,
+    /// Lifetime of gtd orders.
+    #[serde(default)]
+    pub order_ttl_secs: Option<u64>,
+}
+
+/// Risk management limits.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskConfig {
+    /// Maximum notional per trade (USDC).
+    #[serde(default = "default_max_notional_per_trade")]
+    pub max_notional_per_trade: f64,
+    /// Orders accepted in any rolling minute, across all markets.
+    #[serde(default = "default_max_trades_per_minute")]
+    pub max_trades_per_minute: u32,
+    /// Buffer applied to Polymarket odds when quoting to mitigate self-slippage.
+    #[serde(default = "default_self_slippage_buffer_pct")]
+    pub self_slippage_buffer_pct: f64,
+    /// Orders allowed open on the CLOB at once, across all markets.
+    #[serde(default = "default_max_open_orders")]
+    pub max_open_orders: usize,
+    /// Furthest an order's price may sit from the token's book mid.
+    #[serde(default = "default_price_band")]
+    pub price_band: f64,
+    /// Minimum time between two orders on the same token.
+    #[serde(default = "default_token_cooldown_ms")]
+    pub token_cooldown_ms: i64,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            max_notional_per_trade: default_max_notional_per_trade(),
+            max_trades_per_minute: default_max_trades_per_minute(),
+            self_slippage_buffer_pct: default_self_slippage_buffer_pct(),
+            max_open_orders: default_max_open_orders(),
+            price_band: default_price_band(),
+            token_cooldown_ms: default_token_cooldown_ms(),
+        }
+    }
+}
+
+/// When the kill switch halts trading.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KillSwitchConfig {
+    /// Loss (USDC) since the start of the UTC day that trips the switch.
+    #[serde(default)]
+    pub max_daily_loss: Option<f64>,
+    /// Order rejections in a row that trip the switch.
+    #[serde(default = "default_max_consecutive_rejections")]
+    pub max_consecutive_rejections: u32,
+    /// How long the Polymarket or a spot feed may stay disconnected or silent;
+    /// longer than the keepalive interval, which is all an idle market sends.
+    #[serde(default = "default_feed_timeout_secs")]
+    pub feed_timeout_secs: u64,
+    /// Where the tripped state is kept across restarts.
+    #[serde(default = "default_kill_switch_state_file")]
+    pub state_file: PathBuf,
+}
+
+impl Default for KillSwitchConfig {
+    fn default() -> Self {
+        Self {
+            max_daily_loss: None,
+            max_consecutive_rejections: default_max_consecutive_rejections(),
+            feed_timeout_secs: default_feed_timeout_secs(),
+            state_file: default_kill_switch_state_file(),
+        }
+    }
+}
+
+/// Raw market-data capture.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecorderConfig {
+    /// Record while trading; `polymarket_bot record` records regardless.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Where capture files are written.
+    #[serde(default = "default_capture_directory")]
+    pub directory: PathBuf,
+    /// Size at which a capture file is closed and the next one started.
+    #[serde(default = "default_max_file_mb")]
+    pub max_file_mb: u64,
+    /// Capture files to keep, newest first; all of them if unset.
+    #[serde(default)]
+    pub max_files: Option<usize>,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: default_capture_directory(),
+            max_file_mb: default_max_file_mb(),
+            max_files: None,
+        }
+    }
+}
+
+/// The simulated exchange of `replay` and `backtest`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BacktestConfig {
+    /// From the decision to the order reaching the exchange.
+    #[serde(default)]
+    pub decision_to_exchange_ms: u64,
+    /// From the exchange matching an order to the bot hearing about it.
+    #[serde(default)]
+    pub exchange_to_ack_ms: u64,
+    /// Taker fee base rate charged on every token.
+    #[serde(default)]
+    pub fee_rate_bps: u32,
+}
+
+/// Strategy parameters tried by `sweep`. Every parameter with values is
+/// swept; the others keep their configured value.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SweepConfig {
+    /// Applied to every market.
+    #[serde(default)]
+    pub threshold_pct: Vec<f64>,
+    /// Applied to every market.
+    #[serde(default)]
+    pub max_position: Vec<f64>,
+    /// Applied to every market.
+    #[serde(default)]
+    pub reference_window_ms: Vec<u64>,
+    #[serde(default)]
+    pub max_notional_per_trade: Vec<f64>,
+    #[serde(default)]
+    pub max_trades_per_minute: Vec<u32>,
+    #[serde(default)]
+    pub token_cooldown_ms: Vec<i64>,
+    /// Try this many combinations drawn at random instead of the whole grid.
+    #[serde(default)]
+    pub samples: Option<usize>,
+    /// Seed of the random draw, so a sample can be repeated.
+    #[serde(default)]
+    pub seed: u64,
+}
+
+/// Runtime configuration for the bot.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    #[serde(default = "default_spot_venue")]
+    pub spot_venue: SpotVenue,
+    #[serde(default = "default_binance_ws_url")]
+    pub binance_ws_url: String,
+    #[serde(default = "default_kraken_ws_url")]
+    pub kraken_ws_url: String,
+    /// Kraken instrument feeding every market on Kraken, e.g. `XBT/USDT`.
+    #[serde(default = "default_kraken_pair")]
+    pub kraken_pair: String,
+    #[serde(default = "default_coinbase_ws_url")]
+    pub coinbase_ws_url: String,
+    #[serde(default = "default_polymarket_ws_url")]
+    pub polymarket_ws_url: String,
+    #[serde(default = "default_polymarket_api_url")]
+    pub polymarket_api_url: String,
+    #[serde(default)]
+    pub polygon_rpc_url: Option<String>,
+    #[serde(default = "default_polygon_chain_id")]
+    pub polygon_chain_id: u64,
+    #[serde(default = "default_metrics_port")]
+    pub metrics_port: u16,
+    /// Fill orders against the local books instead of sending them to the CLOB.
+    #[serde(default)]
+    pub paper_trading: bool,
+    #[serde(default)]
+    pub private_key: Option<Secret>,
+    #[serde(default)]
+    pub signature_type: SignatureType,
+    /// Address holding the funds; required unless `signature_type` is `eoa`.
+    #[serde(default)]
+    pub funder_address: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<Secret>,
+    #[serde(default)]
+    pub api_secret: Option<Secret>,
+    #[serde(default)]
+    pub api_passphrase: Option<Secret>,
+    pub markets: Vec<MarketConfig>,
+    #[serde(default)]
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub kill_switch: KillSwitchConfig,
+    #[serde(default)]
+    pub recorder: RecorderConfig,
+    #[serde(default)]
+    pub backtest: BacktestConfig,
+    #[serde(default)]
+    pub sweep: SweepConfig,
+}
+
+fn default_true() -> bool {
+    true
+}
+fn default_threshold_pct() -> f64 {
+    0.02
+}
+fn default_max_position() -> f64 {
+    500.0
+}
+fn default_max_notional_per_trade() -> f64 {
+    100.0
+}
+fn default_max_trades_per_minute() -> u32 {
+    60
+}
+fn default_self_slippage_buffer_pct() -> f64 {
+    0.001
+}
+fn default_max_open_orders() -> usize {
+    10
+}
+fn default_price_band() -> f64 {
+    0.05
+}
+fn default_token_cooldown_ms() -> i64 {
+    1_000
+}
+fn default_max_consecutive_rejections() -> u32 {
+    5
+}
+fn default_feed_timeout_secs() -> u64 {
+    30
+}
+fn default_kill_switch_state_file() -> PathBuf {
+    PathBuf::from("kill_switch.json")
+}
+fn default_capture_directory() -> PathBuf {
+    PathBuf::from("captures")
+}
+fn default_max_file_mb() -> u64 {
+    256
+}
+fn default_log_level() -> String {
+    "INFO".to_string()
+}
+fn default_spot_venue() -> SpotVenue {
+    SpotVenue::Binance
+}
+fn default_binance_ws_url() -> String {
+    "wss://stream.binance.us:9443/ws".to_string()
+}
+fn default_kraken_ws_url() -> String {
+    "wss://ws.kraken.com".to_string()
+}
+fn default_kraken_pair() -> String {
+    "XBT/USDT".to_string()
+}
+fn default_coinbase_ws_url() -> String {
+    "wss://ws-feed.exchange.coinbase.com".to_string()
+}
+fn default_polymarket_ws_url() -> String {
+    "wss://ws-subscriptions-clob.polymarket.com/ws".to_string()
+}
+fn default_polymarket_api_url() -> String {
+    "https://clob.polymarket.com".to_string()
+}
+fn default_polygon_chain_id() -> u64 {
+    137
+}
+fn default_metrics_port() -> u16 {
+    9100
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    NotFound(PathBuf),
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, serde_yaml::Error),
+    /// A value failed expansion or validation; the first field is the YAML key.
+    Invalid { key: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NotFound(path) => write!(
+                f,
+                "configuration file {} not found; create it before running the bot",
+                path.display()
+            ),
+            ConfigError::Io(path, err) => write!(f, "failed to read {}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => {
+                write!(f, "invalid configuration in {}: {}", path.display(), err)
+            }
+            ConfigError::Invalid { key, reason } => write!(f, "invalid configuration: {}: {}", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(key: impl Into<String>, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid { key: key.into(), reason: reason.into() }
+}
+
+/// Resolve the configuration path: explicit CLI value, then `LATENCY_BOT_CONFIG`,
+/// then `config.yaml` in the working directory.
+pub fn resolve_path(cli: Option<&Path>) -> PathBuf {
+    if let Some(path) = cli {
+        return path.to_path_buf();
+    }
+    match std::env::var(CONFIG_ENV_VAR) {
+        Ok(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from(DEFAULT_CONFIG_PATH),
+    }
+}
+
+/// Load, expand and validate the settings file at `path`.
+pub fn load_settings(path: &Path) -> Result<Settings, ConfigError> {
+    if !path.exists() {
+        return Err(ConfigError::NotFound(path.to_path_buf()));
+    }
+    let raw = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+    let settings = parse_settings(&raw).map_err(|e| match e {
+        ConfigError::Parse(_, err) => ConfigError::Parse(path.to_path_buf(), err),
+        other => other,
+    })?;
+    Ok(settings)
+}
+
+/// Parse settings from YAML text. Exposed separately so callers and tests do not
+/// need a file on disk.
+pub fn parse_settings(raw: &str) -> Result<Settings, ConfigError> {
+    let mut value: Value = serde_yaml::from_str(raw).map_err(|e| ConfigError::Parse(PathBuf::new(), e))?;
+    expand_env(&mut value, "")?;
+    let mut settings: Settings = serde_path_to_error::deserialize(value).map_err(|e| {
+        let parent = e.path().to_string();
+        let reason = e.into_inner().to_string();
+        // A missing field is reported at its parent; name the field itself.
+        let missing = reason.strip_prefix("missing field `").and_then(|rest| rest.split('`').next());
+        let key = match (parent.as_str(), missing) {
+            (".", Some(field)) => field.to_string(),
+            (_, Some(field)) => format!("{}.{}", parent, field),
+            (".", None) => "<root>".to_string(),
+            (_, None) => parent,
+        };
+        invalid(key, reason)
+    })?;
+    settings.drop_empty_secrets();
+    settings.validate()?;
+    Ok(settings)
+}
+
+fn expand_env(value: &mut Value, path: &str) -> Result<(), ConfigError> {
+    match value {
+        Value::Mapping(map) => {
+            for (key, child) in map.iter_mut() {
+                let key = key.as_str().unwrap_or("?");
+                let child_path = if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+                expand_env(child, &child_path)?;
+            }
+        }
+        Value::Sequence(items) => {
+            for (i, child) in items.iter_mut().enumerate() {
+                expand_env(child, &format!("{}[{}]", path, i))?;
+            }
+        }
+        Value::String(s) if s.contains("${") => {
+            *s = expand_str(s).map_err(|reason| invalid(path, reason))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` placeholders. An unset variable
+/// without a default is an error rather than being silently left in place.
+fn expand_str(input: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find('}').ok_or_else(|| format!("unterminated placeholder in {:?}", input))?;
+        let expr = &after[..end];
+        let (name, default) = match expr.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expr, None),
+        };
+        if name.is_empty() {
+            return Err(format!("empty placeholder in {:?}", input));
+        }
+        match std::env::var(name) {
+            Ok(v) => out.push_str(&v),
+            Err(_) => match default {
+                Some(d) => out.push_str(d),
+                None => return Err(format!("environment variable `{}` is not set", name)),
+            },
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+impl Settings {
+    /// Spot venue feeding `market`'s symbol.
+    pub fn venue_for(&self, market: &MarketConfig) -> SpotVenue {
+        market.spot_venue.unwrap_or(self.spot_venue)
+    }
+
+    fn drop_empty_secrets(&mut self) {
+        for secret in [&mut self.private_key, &mut self.api_key, &mut self.api_secret, &mut self.api_passphrase] {
+            if secret.as_ref().is_some_and(Secret::is_empty) {
+                *secret = None;
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        const LEVELS: [&str; 5] = ["DEBUG", "INFO", "WARNING", "ERROR", "CRITICAL"];
+        if !LEVELS.contains(&self.log_level.as_str()) {
+            return Err(invalid("log_level", format!("must be one of {}, got {:?}", LEVELS.join("|"), self.log_level)));
+        }
+        for (key, url) in [
+            ("binance_ws_url", &self.binance_ws_url),
+            ("kraken_ws_url", &self.kraken_ws_url),
+            ("coinbase_ws_url", &self.coinbase_ws_url),
+            ("polymarket_ws_url", &self.polymarket_ws_url),
+            ("polymarket_api_url", &self.polymarket_api_url),
+        ] {
+            url::Url::parse(url).map_err(|e| invalid(key, format!("invalid URL {:?}: {}", url, e)))?;
+        }
+        if self.polygon_chain_id < 1 {
+            return Err(invalid("polygon_chain_id", "must be >= 1"));
+        }
+        let credentials = [
+            ("api_key", &self.api_key),
+            ("api_secret", &self.api_secret),
+            ("api_passphrase", &self.api_passphrase),
+        ];
+        if credentials.iter().any(|(_, v)| v.is_some()) {
+            if let Some((key, _)) = credentials.iter().find(|(_, v)| v.is_none()) {
+                return Err(invalid(*key, "api_key, api_secret and api_passphrase must be set together"));
+            }
+            if self.private_key.is_none() {
+                return Err(invalid("private_key", "required to use API credentials"));
+            }
+        }
+        match (&self.funder_address, self.signature_type) {
+            (Some(address), _) => {
+                address
+                    .parse::<Address>()
+                    .map_err(|e| invalid("funder_address", e.to_string()))?;
+            }
+            (None, SignatureType::Eoa) => {}
+            (None, _) => return Err(invalid("funder_address", "required when signature_type is not eoa")),
+        }
+        if self.metrics_port < 1 {
+            return Err(invalid("metrics_port", "must be between 1 and 65535"));
+        }
+        if self.markets.is_empty() {
+            return Err(invalid("markets", "at least one market is required"));
+        }
+        let mut symbol_venues: HashMap<String, SpotVenue> = HashMap::new();
+        let mut kraken_symbol: Option<&str> = None;
+        for (i, market) in self.markets.iter().enumerate() {
+            let path = format!("markets[{}]", i);
+            market.validate(&path)?;
+            let venue = self.venue_for(market);
+            if venue == SpotVenue::Kraken {
+                match kraken_symbol {
+                    Some(symbol) if !symbol.eq_ignore_ascii_case(&market.symbol) => {
+                        return Err(invalid(
+                            format!("{}.symbol", path),
+                            format!("kraken only follows kraken_pair ({}), which feeds {}", self.kraken_pair, symbol),
+                        ));
+                    }
+                    _ => kraken_symbol = Some(&market.symbol),
+                }
+            }
+            match symbol_venues.insert(market.symbol.to_lowercase(), venue) {
+                Some(previous) if previous != venue => {
+                    return Err(invalid(
+                        format!("{}.spot_venue", path),
+                        format!("{} is already fed from {:?} by another market", market.symbol, previous),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        self.risk.validate("risk")?;
+        self.kill_switch.validate("kill_switch")?;
+        self.recorder.validate("recorder")?;
+        self.backtest.validate("backtest")?;
+        self.sweep.validate("sweep")
+    }
+}
+
+impl MarketConfig {
+    fn validate(&self, path: &str) -> Result<(), ConfigError> {
+        for (key, value) in [
+            ("market_id", &self.market_id),
+            ("symbol", &self.symbol),
+            ("yes_token_id", &self.yes_token_id),
+            ("no_token_id", &self.no_token_id),
+        ] {
+            if value.trim().is_empty() {
+                return Err(invalid(format!("{}.{}", path, key), "must not be empty"));
+            }
+        }
+        let hex = self.market_id.strip_prefix("0x").unwrap_or_default();
+        if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid(
+                format!("{}.market_id", path),
+                format!("must be the market's condition id (0x...), got {:?}", self.market_id),
+            ));
+        }
+        if self.yes_token_id == self.no_token_id {
+            return Err(invalid(format!("{}.no_token_id", path), "must differ from yes_token_id"));
+        }
+        non_negative(&format!("{}.threshold_pct", path), self.threshold_pct)?;
+        non_negative(&format!("{}.max_position", path), self.max_position)?;
+        if self.reference_window_ms == Some(0) {
+            return Err(invalid(format!("{}.reference_window_ms", path), "must be >= 1"));
+        }
+        match (self.order_type, self.order_ttl_secs) {
+            (OrderType::Gtd, None | Some(0)) => {
+                return Err(invalid(format!("{}.order_ttl_secs", path), "must be >= 1 for gtd orders"))
+            }
+            (OrderType::Gtc | OrderType::Fok | OrderType::Fak, Some(_)) => {
+                return Err(invalid(format!("{}.order_ttl_secs", path), "only applies to gtd orders"))
+            }
+            _ => {}
+        }
+        if self.post_only && matches!(self.order_type, OrderType::Fok | OrderType::Fak) {
+            return Err(invalid(format!("{}.post_only", path), "only applies to gtc and gtd orders"));
+        }
+        Ok(())
+    }
+}
+
+impl RiskConfig {
+    fn validate(&self, path: &str) -> Result<(), ConfigError> {
+        non_negative(&format!("{}.max_notional_per_trade", path), self.max_notional_per_trade)?;
+        if self.max_trades_per_minute < 1 {
+            return Err(invalid(format!("{}.max_trades_per_minute", path), "must be >= 1"));
+        }
+        non_negative(&format!("{}.self_slippage_buffer_pct", path), self.self_slippage_buffer_pct)?;
+        if self.max_open_orders < 1 {
+            return Err(invalid(format!("{}.max_open_orders", path), "must be >= 1"));
+        }
+        if !(self.price_band > 0.0 && self.price_band <= 1.0) {
+            return Err(invalid(format!("{}.price_band", path), format!("must be in (0, 1], got {}", self.price_band)));
+        }
+        if self.token_cooldown_ms < 0 {
+            return Err(invalid(format!("{}.token_cooldown_ms", path), "must be >= 0"));
+        }
+        Ok(())
+    }
+}
+
+impl KillSwitchConfig {
+    fn validate(&self, path: &str) -> Result<(), ConfigError> {
+        if let Some(loss) = self.max_daily_loss {
+            non_negative(&format!("{}.max_daily_loss", path), loss)?;
+        }
+        if self.max_consecutive_rejections < 1 {
+            return Err(invalid(format!("{}.max_consecutive_rejections", path), "must be >= 1"));
+        }
+        // An idle market channel only answers the keepalive pings.
+        let keepalive_secs = crate::polymarket::keepalive().interval.as_secs();
+        if self.feed_timeout_secs <= keepalive_secs {
+            return Err(invalid(
+                format!("{}.feed_timeout_secs", path),
+                format!("must be above the {}s keepalive interval", keepalive_secs),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl RecorderConfig {
+    fn validate(&self, path: &str) -> Result<(), ConfigError> {
+        if self.max_file_mb < 1 {
+            return Err(invalid(format!("{}.max_file_mb", path), "must be >= 1"));
+        }
+        if self.max_files == Some(0) {
+            return Err(invalid(format!("{}.max_files", path), "must be >= 1"));
+        }
+        Ok(())
+    }
+}
+
+impl BacktestConfig {
+    fn validate(&self, path: &str) -> Result<(), ConfigError> {
+        if self.fee_rate_bps > 10_000 {
+            return Err(invalid(format!("{}.fee_rate_bps", path), "must be <= 10000"));
+        }
+        Ok(())
+    }
+}
+
+impl SweepConfig {
+    fn validate(&self, path: &str) -> Result<(), ConfigError> {
+        for (key, values) in [
+            ("threshold_pct", &self.threshold_pct),
+            ("max_position", &self.max_position),
+            ("max_notional_per_trade", &self.max_notional_per_trade),
+        ] {
+            for value in values {
+                non_negative(&format!("{}.{}", path, key), *value)?;
+            }
+        }
+        if self.reference_window_ms.contains(&0) {
+            return Err(invalid(format!("{}.reference_window_ms", path), "values must be >= 1"));
+        }
+        if self.max_trades_per_minute.contains(&0) {
+            return Err(invalid(format!("{}.max_trades_per_minute", path), "values must be >= 1"));
+        }
+        if self.token_cooldown_ms.iter().any(|ms| *ms < 0) {
+            return Err(invalid(format!("{}.token_cooldown_ms", path), "values must be >= 0"));
+        }
+        if self.samples == Some(0) {
+            return Err(invalid(format!("{}.samples", path), "must be >= 1"));
+        }
+        Ok(())
+    }
+}
+
+fn non_negative(key: &str, value: f64) -> Result<(), ConfigError> {
+    if !value.is_finite() || value < 0.0 {
+        return Err(invalid(key, format!("must be a finite number >= 0, got {}", value)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKET: &str = r#"
+markets:
+  - market_id: "0xabc"
+    symbol: "btc/usdt"
+    yes_token_id: "1111"
+    no_token_id: "2222"
+"#;
+
+    fn error(yaml: &str) -> (String, String) {
+        match parse_settings(yaml) {
+            Err(ConfigError::Invalid { key, reason }) => (key, reason),
+            other => panic!("expected a validation error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn parses_a_minimal_config_with_defaults() {
+        let settings = parse_settings(MARKET).unwrap();
+        assert_eq!(settings.spot_venue, SpotVenue::Binance);
+        assert_eq!(settings.markets[0].threshold_pct, default_threshold_pct());
+        assert!(settings.private_key.is_none());
+    }
+
+    #[test]
+    fn parses_the_example_without_secrets_in_the_environment() {
+        // Every secret in the example has a default, so only live trading needs them.
+        let example = include_str!("../../config.example.yaml");
+        let settings = parse_settings(example).unwrap();
+        assert!(!settings.paper_trading);
+    }
+
+    #[test]
+    fn names_an_unknown_venue() {
+        let (key, reason) = error(&format!("spot_venue: bitstamp\n{}", MARKET));
+        assert_eq!(key, "spot_venue");
+        assert!(reason.contains("bitstamp"), "{}", reason);
+
+        let (key, _) = error(&format!("{}    spot_venue: bitstamp\n", MARKET));
+        assert_eq!(key, "markets[0].spot_venue");
+    }
+
+    #[test]
+    fn needs_a_condition_id_for_the_market() {
+        let (key, reason) = error(&MARKET.replace("0xabc", "btc-2025-01-15-up-down"));
+        assert_eq!(key, "markets[0].market_id");
+        assert!(reason.contains("condition id"), "{}", reason);
+    }
+
+    #[test]
+    fn names_a_negative_threshold() {
+        let (key, reason) = error(&format!("{}    threshold_pct: -0.01\n", MARKET));
+        assert_eq!(key, "markets[0].threshold_pct");
+        assert!(reason.contains("-0.01"), "{}", reason);
+    }
+
+    #[test]
+    fn needs_a_feed_timeout_above_the_keepalive_interval() {
+        let (key, reason) = error(&format!("{}kill_switch:\n  feed_timeout_secs: 10\n", MARKET));
+        assert_eq!(key, "kill_switch.feed_timeout_secs");
+        assert!(reason.contains("10s keepalive"), "{}", reason);
+    }
+
+    #[test]
+    fn names_an_empty_reference_window() {
+        let (key, _) = error(&format!("{}    reference_window_ms: 0\n", MARKET));
+        assert_eq!(key, "markets[0].reference_window_ms");
+        let (key, _) = error(&format!("{}sweep:\n  reference_window_ms: [1000, 0]\n", MARKET));
+        assert_eq!(key, "sweep.reference_window_ms");
+    }
+
+    #[test]
+    fn names_a_missing_token_id() {
+        let (key, reason) = error(&MARKET.replace("    yes_token_id: \"1111\"\n", ""));
+        assert_eq!(key, "markets[0].yes_token_id");
+        assert!(reason.contains("missing"), "{}", reason);
+
+        let (key, _) = error(&MARKET.replace("\"2222\"", "\"  \""));
+        assert_eq!(key, "markets[0].no_token_id");
+    }
+
+    #[test]
+    fn feeds_one_symbol_from_kraken_pair() {
+        let second = r#"  - market_id: "0xdef"
+    symbol: "eth/usdt"
+    yes_token_id: "3333"
+    no_token_id: "4444"
+"#;
+        let two = format!("spot_venue: kraken\n{}{}", MARKET, second);
+        let (key, reason) = error(&two);
+        assert_eq!(key, "markets[1].symbol");
+        assert!(reason.contains("XBT/USDT"), "{}", reason);
+
+        // The same symbol twice is fine, as is a second symbol on another venue.
+        assert!(parse_settings(&two.replace("eth/usdt", "BTC/USDT")).is_ok());
+        assert!(parse_settings(&two.replace("eth/usdt\"\n", "eth/usdt\"\n    spot_venue: binance\n")).is_ok());
+    }
+
+    #[test]
+    fn names_a_missing_environment_variable() {
+        let yaml = format!("{}\nprivate_key: \"${{LATENCY_BOT_TEST_UNSET_KEY}}\"\n", MARKET);
+        let (key, reason) = error(&yaml);
+        assert_eq!(key, "private_key");
+        assert!(reason.contains("LATENCY_BOT_TEST_UNSET_KEY"), "{}", reason);
+
+        // A default makes it optional.
+        let yaml = format!("{}\nprivate_key: \"${{LATENCY_BOT_TEST_UNSET_KEY:-}}\"\n", MARKET);
+        assert!(parse_settings(&yaml).unwrap().private_key.is_none());
+    }
+}
@@ -0,0 +1,547 @@
+//! Trading decisions, independent of where their inputs come from.
+//!
+//! [`Engine`] owns the local books, the strategy, the pre-trade risk checks,
+//! the OMS and the account, and turns market-data frames and user-channel
+//! events into orders. Submitting them is left to the caller: the live loop
+//! in `main` sends them in the background, a [`crate::replay`] awaits them on
+//! a paper exchange. Nothing in here reads the clock; every input carries its
+//! time, so the same inputs always lead to the same decisions.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::account::Account;
+use crate::config::{MarketConfig, OrderType, Settings};
+use crate::execution::paper::{PaperGateway, PAPER_OWNER};
+use crate::execution::ExecutionGateway;
+use crate::feeds::SpotTick;
+use crate::metrics::Metrics;
+use crate::oms::{Oms, OmsError, OrderState};
+use crate::polymarket::book::{BookStore, ResyncReason};
+use crate::polymarket::clob::{ClobError, Market, OrderResponse, TokenParams};
+use crate::polymarket::market_ws::{self, BookEvent, MarketEvent};
+use crate::polymarket::order::{OrderArgs, OrderBuilder, SignedOrder};
+use crate::polymarket::user_ws::UserEvent;
+use crate::risk::{RiskContext, RiskEngine, RiskRejection};
+use crate::strategy::{LatencyStrategy, TradeSignal};
+
+/// Seconds the CLOB adds to every GTD expiration as a security margin.
+const GTD_EXPIRATION_MARGIN_SECS: u64 = 60;
+
+/// Outcome of a `POST /order` for the OMS order with this id.
+pub type Submission = (u64, Result<OrderResponse, ClobError>);
+
+/// A signed order for [`ExecutionGateway::post_order`], tracked in the OMS as `id`.
+#[derive(Debug, Clone)]
+pub struct OrderSubmission {
+    pub id: u64,
+    pub order: SignedOrder,
+    pub order_type: OrderType,
+    pub post_only: bool,
+}
+
+/// What became of one strategy signal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Rejected(RiskRejection),
+    /// No signing key or no trading parameters for the token; only logged.
+    Logged,
+    /// The order could not be built or signed.
+    Invalid(String),
+    /// Signed, but the gateway cannot take orders.
+    Unsubmitted { id: u64 },
+    Submitted { id: u64 },
+}
+
+impl Verdict {
+    /// Short stable name, e.g. for counting verdicts.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Verdict::Rejected(_) => "rejected",
+            Verdict::Logged => "logged",
+            Verdict::Invalid(_) => "invalid",
+            Verdict::Unsubmitted { .. } => "unsubmitted",
+            Verdict::Submitted { .. } => "submitted",
+        }
+    }
+}
+
+/// A strategy signal and what became of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub at_ms: i64,
+    pub signal: TradeSignal,
+    pub verdict: Verdict,
+}
+
+/// Decisions taken on one spot tick, and the orders to send for them.
+#[derive(Debug, Default)]
+pub struct TickOutcome {
+    pub decisions: Vec<Decision>,
+    pub submissions: Vec<OrderSubmission>,
+}
+
+pub struct Engine {
+    markets: HashMap<String, MarketConfig>,
+    /// Every configured outcome token.
+    token_ids: Vec<String>,
+    strategy: LatencyStrategy,
+    risk: RiskEngine,
+    books: BookStore,
+    oms: Oms,
+    account: Account,
+    token_params: HashMap<String, TokenParams>,
+    orders: Option<OrderBuilder>,
+    gateway: Arc<dyn ExecutionGateway>,
+    /// Shown every book change when trading on paper.
+    paper: Option<PaperGateway>,
+    metrics: Metrics,
+}
+
+impl Engine {
+    /// An engine for the account with API key `owner`, trading through `gateway`.
+    pub fn new(settings: &Settings, gateway: Arc<dyn ExecutionGateway>, owner: impl Into<String>) -> Self {
+        Self {
+            markets: settings.markets.iter().map(|m| (m.market_id.clone(), m.clone())).collect(),
+            token_ids: settings.markets.iter().flat_map(|m| [m.yes_token_id.clone(), m.no_token_id.clone()]).collect(),
+            strategy: LatencyStrategy::new(settings),
+            risk: RiskEngine::new(settings),
+            books: BookStore::new(),
+            oms: Oms::new(),
+            account: Account::new(owner),
+            token_params: HashMap::new(),
+            orders: None,
+            gateway,
+            paper: None,
+            metrics: Metrics::new(),
+        }
+    }
+
+    /// An engine trading on `paper`, which it keeps in step with the local books.
+    pub fn paper(settings: &Settings, paper: PaperGateway) -> Self {
+        let mut engine = Self::new(settings, Arc::new(paper.clone()), PAPER_OWNER);
+        engine.paper = Some(paper);
+        engine
+    }
+
+    /// Sign orders with `orders`; without a builder signals are only logged.
+    pub fn with_orders(mut self, orders: Option<OrderBuilder>) -> Self {
+        self.orders = orders;
+        self
+    }
+
+    /// Trading parameters per token; tokens without them are not traded.
+    pub fn with_token_params(mut self, token_params: HashMap<String, TokenParams>) -> Self {
+        self.token_params = token_params;
+        self
+    }
+
+    /// Publish risk rejections, book resyncs and PnL to `metrics`.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn books(&self) -> &BookStore {
+        &self.books
+    }
+
+    pub fn oms(&self) -> &Oms {
+        &self.oms
+    }
+
+    pub fn oms_mut(&mut self) -> &mut Oms {
+        &mut self.oms
+    }
+
+    pub fn account(&self) -> &Account {
+        &self.account
+    }
+
+    pub fn risk(&self) -> &RiskEngine {
+        &self.risk
+    }
+
+    pub fn gateway(&self) -> &Arc<dyn ExecutionGateway> {
+        &self.gateway
+    }
+
+    /// Total PnL of the account, marked against the local books.
+    pub fn total_pnl(&self) -> f64 {
+        self.account.positions().total_pnl(&self.books).total()
+    }
+
+    /// Run `tick` through the strategy and the risk checks. Accepted signals
+    /// are signed and tracked in the OMS; the orders to send are returned with
+    /// every decision taken. While trading is `halted` the strategy still
+    /// follows the ticks and its signals are dropped, so trading resumes
+    /// against current reference prices rather than on moves made meanwhile.
+    pub fn on_spot_tick(&mut self, tick: &SpotTick, halted: bool) -> TickOutcome {
+        let now_ms = tick.received_at_ms;
+        let mut outcome = TickOutcome::default();
+        let signals = self.strategy.on_spot_tick(tick, &self.books, &self.oms);
+        if halted {
+            return outcome;
+        }
+        for signal in signals {
+            println!(
+                "Edge detected on {} via {}! {} move of {:.4}%",
+                signal.market_id,
+                tick.venue,
+                signal.direction,
+                signal.delta * 100.0
+            );
+            let Some(market) = self.markets.get(&signal.market_id).cloned() else {
+                continue;
+            };
+            let ctx = RiskContext { oms: &self.oms, positions: self.account.positions(), books: &self.books };
+            let verdict = match self.risk.check(&signal, &market, &ctx, now_ms) {
+                Ok(()) => self.dispatch(&market, &signal, now_ms, &mut outcome.submissions),
+                Err(rejection) => {
+                    eprintln!("Risk rejected {} order on {}: {}", signal.side, signal.token_id, rejection);
+                    self.metrics.inc("risk_rejections_total", &[("reason", rejection.kind())]);
+                    Verdict::Rejected(rejection)
+                }
+            };
+            outcome.decisions.push(Decision { at_ms: now_ms, signal, verdict });
+        }
+        outcome
+    }
+
+    /// Build and sign the order for `signal` with `market`'s time in force and
+    /// track it in the OMS; queue it on `submissions` when the gateway can trade.
+    fn dispatch(
+        &mut self,
+        market: &MarketConfig,
+        signal: &TradeSignal,
+        now_ms: i64,
+        submissions: &mut Vec<OrderSubmission>,
+    ) -> Verdict {
+        let (Some(orders), Some(params)) = (&self.orders, self.token_params.get(&signal.token_id)) else {
+            println!(
+                "Would place {} {} order for {:.2} @ {:.4} on token {} ({}) via {}",
+                market.order_type.as_str(),
+                signal.side,
+                signal.size,
+                signal.price,
+                signal.token_id,
+                signal.market_id,
+                self.gateway.endpoint()
+            );
+            return Verdict::Logged;
+        };
+        let expiration = match (market.order_type, market.order_ttl_secs) {
+            (OrderType::Gtd, Some(ttl)) => (now_ms / 1000) as u64 + GTD_EXPIRATION_MARGIN_SECS + ttl,
+            _ => 0,
+        };
+        let id = self.oms.create(signal, market.order_type, expiration, now_ms);
+        let args = OrderArgs {
+            token_id: signal.token_id.clone(),
+            side: signal.side,
+            price: signal.price,
+            size: signal.size,
+            fee_rate_bps: params.fee_rate_bps,
+            order_type: market.order_type,
+            expiration,
+            nonce: 0,
+        };
+        let order = match orders.build_signed(&args, params.tick_size, params.neg_risk) {
+            Ok(order) => order,
+            Err(e) => {
+                eprintln!("Cannot build order #{} for {}: {}", id, signal.token_id, e);
+                log_oms_error(self.oms.reject(id, &e.to_string(), now_ms));
+                return Verdict::Invalid(e.to_string());
+            }
+        };
+        log_oms_error(self.oms.signed(id, &order.order_id(), now_ms));
+        if !self.gateway.is_authenticated() {
+            println!(
+                "Signed {} {} order #{} ({}) for {:.2} @ {:.4} on token {} ({}); no API credentials, not submitted",
+                market.order_type.as_str(),
+                signal.side,
+                id,
+                order.order_id(),
+                signal.size,
+                signal.price,
+                signal.token_id,
+                signal.market_id
+            );
+            log_oms_error(self.oms.transition(id, OrderState::Cancelled, now_ms));
+            return Verdict::Unsubmitted { id };
+        }
+        log_oms_error(self.oms.transition(id, OrderState::Sent, now_ms));
+        submissions.push(OrderSubmission { id, order, order_type: market.order_type, post_only: market.post_only });
+        Verdict::Submitted { id }
+    }
+
+    /// Fold the outcome of a submission into the OMS; the order's state if
+    /// the outcome is known.
+    pub fn on_submission(&mut self, (id, result): Submission, now_ms: i64) -> Option<OrderState> {
+        match result {
+            Ok(response) => match self.oms.on_response(id, &response, now_ms) {
+                Ok(state) => {
+                    println!("Order #{}: {} -> {}", id, response, state);
+                    Some(state)
+                }
+                Err(e) => {
+                    eprintln!("Order #{}: {}", id, e);
+                    None
+                }
+            },
+            // The CLOB refused it, or it never left.
+            Err(e @ (ClobError::Status { .. } | ClobError::Auth { .. })) => {
+                eprintln!("Order #{} rejected: {}", id, e);
+                log_oms_error(self.oms.reject(id, &e.to_string(), now_ms));
+                Some(OrderState::Rejected)
+            }
+            // It may have reached the CLOB; the user channel will tell.
+            Err(e) => {
+                eprintln!("Order #{} outcome unknown: {}", id, e);
+                None
+            }
+        }
+    }
+
+    /// Apply a market-channel frame received at `received_at_ms`. Returns the
+    /// books that diverged and need a REST snapshot.
+    pub fn on_market_frame(&mut self, text: &str, received_at_ms: i64) -> Vec<(String, ResyncReason)> {
+        let events = match market_ws::parse_message(text) {
+            Ok(events) => events,
+            Err(raw) => {
+                eprintln!("Unexpected Polymarket frame: {}", raw);
+                return Vec::new();
+            }
+        };
+        let mut resync = Vec::new();
+        for event in events {
+            let outcome = self.books.apply(&event, received_at_ms);
+            let diverged = outcome.resync.iter().map(|(token_id, _)| token_id);
+            self.mirror_books(outcome.updated.iter().chain(diverged));
+            resync.extend(outcome.resync);
+            self.on_market_event(event);
+        }
+        resync
+    }
+
+    /// Deltas may have been missed; serve no book until the resubscribe snapshots.
+    pub fn on_market_disconnected(&mut self) {
+        self.books.invalidate_all();
+        self.mirror_books(&self.token_ids);
+    }
+
+    /// Rebuild a diverged book from a REST snapshot.
+    pub fn apply_resync(&mut self, snapshot: &BookEvent, received_at_ms: i64) {
+        self.books.apply_resync(snapshot, received_at_ms);
+        self.mirror_books([&snapshot.asset_id]);
+        let resyncs = self.books.get_mut(&snapshot.asset_id).resyncs;
+        println!("Resynced book for {} (resync #{})", snapshot.asset_id, resyncs);
+        self.metrics.inc("book_resyncs_total", &[("token", &snapshot.asset_id)]);
+    }
+
+    fn on_market_event(&mut self, event: MarketEvent) {
+        match event {
+            MarketEvent::TickSizeChange(change) => {
+                println!("Tick size for {} changed {} -> {}", change.asset_id, change.old_tick_size, change.new_tick_size);
+                if let Some(params) = self.token_params.get_mut(&change.asset_id) {
+                    params.tick_size = change.new_tick_size;
+                }
+            }
+            MarketEvent::Unknown { event_type } => eprintln!("Ignoring unknown Polymarket event {:?}", event_type),
+            MarketEvent::Malformed { event_type, error } => {
+                eprintln!("Malformed Polymarket {} event: {}", event_type, error)
+            }
+            // Book snapshots and deltas were applied to the local books already.
+            MarketEvent::Book(_) | MarketEvent::PriceChange(_) | MarketEvent::LastTradePrice(_) => {}
+        }
+    }
+
+    /// Show the paper exchange the current state of these books.
+    fn mirror_books<'a>(&self, token_ids: impl IntoIterator<Item = &'a String>) {
+        if let Some(paper) = &self.paper {
+            for token_id in token_ids {
+                paper.on_book(token_id, self.books.get(token_id));
+            }
+        }
+    }
+
+    /// Apply an order update or trade of the account.
+    pub fn on_user_event(&mut self, event: UserEvent, now_ms: i64) {
+        match &event {
+            UserEvent::Order(order) => println!(
+                "Order {} {:?}: {} {}/{} @ {} on {}",
+                order.id, order.kind, order.side, order.size_matched, order.original_size, order.price, order.asset_id
+            ),
+            UserEvent::Trade(trade) => {
+                println!("Trade {} {:?} ({} {} @ {})", trade.id, trade.status, trade.side, trade.size, trade.price)
+            }
+            UserEvent::Unknown { event_type } => eprintln!("Ignoring unknown user channel event {:?}", event_type),
+            UserEvent::Malformed { event_type, error } => {
+                eprintln!("Malformed user channel {} event: {}", event_type, error)
+            }
+        }
+        if let UserEvent::Order(order) = &event {
+            if let Some(state) = self.oms.on_order_event(order, now_ms) {
+                println!("Order {} is now {}", order.id, state);
+            }
+        }
+        let update = self.account.apply(&event);
+        for fill in &update.fills {
+            if let Some(state) = self.oms.on_fill(fill, now_ms) {
+                println!("Order {} is now {}", fill.order_id, state);
+            }
+            println!(
+                "Filled {} {} @ {} on {} (order {}); position now {}",
+                fill.side,
+                fill.size,
+                fill.price,
+                fill.asset_id,
+                fill.order_id,
+                self.account.position(&fill.asset_id)
+            );
+        }
+        for fill in &update.reverted {
+            eprintln!(
+                "Trade {} failed; reverted {} {} on {}; position now {}",
+                fill.trade_id,
+                fill.side,
+                fill.size,
+                fill.asset_id,
+                self.account.position(&fill.asset_id)
+            );
+        }
+    }
+
+    /// Redeem every token of a resolved market: 1 per winning share, 0 otherwise.
+    pub fn on_resolution(&mut self, market: &Market) {
+        for token in &market.tokens {
+            let shares = self.account.position(&token.token_id);
+            if shares == 0.0 {
+                continue;
+            }
+            let payout = if token.winner { 1.0 } else { 0.0 };
+            self.account.redeem(&token.token_id, payout);
+            println!("Market {} resolved: redeemed {} {} shares at {}", market.condition_id, shares, token.outcome, payout);
+        }
+    }
+
+    /// Publish per-market and total PnL to the metrics, and log it if `log`.
+    pub fn report_pnl(&self, log: bool) {
+        let positions = self.account.positions();
+        for (asset_id, position) in positions.iter() {
+            let labels = [("market", position.market.as_str()), ("token", asset_id.as_str())];
+            self.metrics.set("polymarket_position_shares", &labels, position.shares);
+            self.metrics.set("polymarket_position_avg_cost", &labels, position.avg_cost);
+        }
+        for (market, pnl) in positions.market_pnl(&self.books) {
+            let labels = [("market", market)];
+            self.metrics.set("polymarket_realized_pnl", &labels, pnl.realized);
+            self.metrics.set("polymarket_unrealized_pnl", &labels, pnl.unrealized);
+            self.metrics.set("polymarket_exposure", &labels, pnl.exposure);
+            if log {
+                println!(
+                    "PnL {}: realized {:.4}, unrealized {:.4}, exposure {:.4}",
+                    market, pnl.realized, pnl.unrealized, pnl.exposure
+                );
+            }
+        }
+        let total = positions.total_pnl(&self.books);
+        self.metrics.set("polymarket_realized_pnl_total", &[], total.realized);
+        self.metrics.set("polymarket_unrealized_pnl_total", &[], total.unrealized);
+        if log && positions.iter().next().is_some() {
+            println!(
+                "PnL total: {:.4} (realized {:.4}, unrealized {:.4})",
+                total.total(),
+                total.realized,
+                total.unrealized
+            );
+        }
+    }
+}
+
+pub fn log_oms_error(result: Result<(), OmsError>) {
+    if let Err(e) = result {
+        eprintln!("OMS: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> Engine {
+        let yaml = r#"
+markets:
+  - market_id: "0xabc"
+    symbol: "btc/usdt"
+    yes_token_id: "1111"
+    no_token_id: "2222"
+    threshold_pct: 0.005
+risk:
+  max_notional_per_trade: 10.0
+  self_slippage_buffer_pct: 0.0
+"#;
+        let settings: Settings = serde_yaml::from_str(yaml).unwrap();
+        let mut engine = Engine::paper(&settings, PaperGateway::new().0);
+        let book = |token: &str| {
+            format!(
+                r#"{{"market":"0xabc","asset_id":"{}","timestamp":"1","hash":"0","event_type":"book",
+                "bids":[{{"price":"0.45","size":"100"}}],"asks":[{{"price":"0.5","size":"100"}}]}}"#,
+                token
+            )
+        };
+        let frame = format!("[{},{}]", book("1111"), book("2222"));
+        assert!(engine.on_market_frame(&frame, 1).is_empty());
+        engine
+    }
+
+    fn tick(last: f64, received_at_ms: i64) -> SpotTick {
+        SpotTick {
+            venue: "binance",
+            symbol: "btc/usdt".to_string(),
+            bid: None,
+            ask: None,
+            last,
+            exchange_ts_ms: None,
+            sequence: None,
+            received_at_ms,
+        }
+    }
+
+    #[test]
+    fn counts_resyncs_per_token_in_the_metrics() {
+        let metrics = Metrics::new();
+        let mut engine = engine().with_metrics(metrics.clone());
+        let snapshot = |token: &str| BookEvent {
+            asset_id: token.to_string(),
+            market: "0xabc".to_string(),
+            bids: vec![market_ws::PriceLevel { price: 0.4, size: 10.0 }],
+            asks: vec![market_ws::PriceLevel { price: 0.6, size: 10.0 }],
+            timestamp: 2,
+            hash: None,
+        };
+        engine.apply_resync(&snapshot("1111"), 2);
+        engine.apply_resync(&snapshot("1111"), 3);
+        engine.apply_resync(&snapshot("2222"), 4);
+
+        for (token, resyncs) in engine.books().resync_counts() {
+            assert_eq!(metrics.get("book_resyncs_total", &[("token", token)]), Some(resyncs as f64), "{}", token);
+        }
+        assert_eq!(metrics.get("book_resyncs_total", &[("token", "1111")]), Some(2.0));
+        assert!(metrics.render().contains("# TYPE book_resyncs_total counter"));
+    }
+
+    #[test]
+    fn follows_ticks_while_halted_without_trading() {
+        let mut engine = engine();
+        assert!(engine.on_spot_tick(&tick(100.0, 1_000), false).decisions.is_empty());
+        let outcome = engine.on_spot_tick(&tick(101.0, 2_000), true);
+        assert!(outcome.decisions.is_empty() && outcome.submissions.is_empty());
+
+        // The move made while halted is not traded once trading resumes...
+        assert!(engine.on_spot_tick(&tick(101.0, 3_000), false).decisions.is_empty());
+        // ...but the next one is.
+        let outcome = engine.on_spot_tick(&tick(102.5, 4_000), false);
+        assert_eq!(outcome.decisions.len(), 1);
+        assert_eq!(outcome.decisions[0].signal.token_id, "1111");
+    }
+}
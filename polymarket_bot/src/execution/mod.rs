@@ -0,0 +1,95 @@
+//! Where orders are sent.
+//!
+//! [`ExecutionGateway`] is the order-entry half of the CLOB API. The live
+//! [`ClobClient`] implements it, and so does [`paper::PaperGateway`], which
+//! fills orders against the local books instead, so the bot runs the same
+//! way with or without real funds at stake.
+
+pub mod paper;
+
+use futures_util::future::BoxFuture;
+
+use crate::config::OrderType;
+use crate::polymarket::clob::{CancelResponse, ClobClient, ClobError, OpenOrder, OrderResponse};
+use crate::polymarket::order::SignedOrder;
+
+/// Order entry, as offered by the CLOB.
+pub trait ExecutionGateway: Send + Sync {
+    /// Where orders go, for logs.
+    fn endpoint(&self) -> &str;
+
+    /// Whether orders can be submitted (the CLOB needs API credentials).
+    fn is_authenticated(&self) -> bool;
+
+    fn post_order<'a>(
+        &'a self,
+        order: &'a SignedOrder,
+        order_type: OrderType,
+        post_only: bool,
+    ) -> BoxFuture<'a, Result<OrderResponse, ClobError>>;
+
+    fn cancel_order<'a>(&'a self, order_id: &'a str) -> BoxFuture<'a, Result<CancelResponse, ClobError>>;
+
+    fn cancel_orders<'a>(&'a self, order_ids: &'a [String]) -> BoxFuture<'a, Result<CancelResponse, ClobError>>;
+
+    fn cancel_market_orders<'a>(
+        &'a self,
+        market: &'a str,
+        asset_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<CancelResponse, ClobError>>;
+
+    fn cancel_all(&self) -> BoxFuture<'_, Result<CancelResponse, ClobError>>;
+
+    fn open_orders<'a>(
+        &'a self,
+        market: Option<&'a str>,
+        asset_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<OpenOrder>, ClobError>>;
+}
+
+impl ExecutionGateway for ClobClient {
+    fn endpoint(&self) -> &str {
+        self.host()
+    }
+
+    fn is_authenticated(&self) -> bool {
+        ClobClient::is_authenticated(self)
+    }
+
+    fn post_order<'a>(
+        &'a self,
+        order: &'a SignedOrder,
+        order_type: OrderType,
+        post_only: bool,
+    ) -> BoxFuture<'a, Result<OrderResponse, ClobError>> {
+        Box::pin(ClobClient::post_order(self, order, order_type, post_only))
+    }
+
+    fn cancel_order<'a>(&'a self, order_id: &'a str) -> BoxFuture<'a, Result<CancelResponse, ClobError>> {
+        Box::pin(ClobClient::cancel_order(self, order_id))
+    }
+
+    fn cancel_orders<'a>(&'a self, order_ids: &'a [String]) -> BoxFuture<'a, Result<CancelResponse, ClobError>> {
+        Box::pin(ClobClient::cancel_orders(self, order_ids))
+    }
+
+    fn cancel_market_orders<'a>(
+        &'a self,
+        market: &'a str,
+        asset_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<CancelResponse, ClobError>> {
+        Box::pin(ClobClient::cancel_market_orders(self, market, asset_id))
+    }
+
+    fn cancel_all(&self) -> BoxFuture<'_, Result<CancelResponse, ClobError>> {
+        Box::pin(ClobClient::cancel_all(self))
+    }
+
+    fn open_orders<'a>(
+        &'a self,
+        market: Option<&'a str>,
+        asset_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<OpenOrder>, ClobError>> {
+        Box::pin(ClobClient::open_orders(self, market, asset_id))
+    }
+}
@@ -0,0 +1,648 @@
+//! Paper trading: orders fill against the locally reconstructed books.
+//!
+//! A marketable order walks the opposite side of the book up to its limit
+//! price, one trade per level, and pays the taker fee at the order's
+//! `fee_rate_bps`. What a GTC/GTD order does not fill on arrival rests at its
+//! price and fills, fee-free as a maker, once a later book update crosses it;
+//! queue position is not modelled. Size the paper account took stays taken
+//! until the exchange shows that level shrinking below it, so the same
+//! liquidity is never filled twice.
+//!
+//! Results are reported the way the user channel reports them: every trade
+//! and order change is sent as a [`UserEvent`] on the channel returned by
+//! [`PaperGateway::new`], so the OMS, positions and PnL are fed exactly as in
+//! live trading.
+
+use futures_util::future::BoxFuture;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+use super::ExecutionGateway;
+use crate::config::OrderType;
+use crate::polymarket::book::OrderBook;
+use crate::polymarket::clob::{CancelResponse, ClobError, OpenOrder, OrderResponse, PlacementStatus};
+use crate::polymarket::market_ws::PriceLevel;
+use crate::polymarket::order::SignedOrder;
+use crate::polymarket::user_ws::{MakerOrder, OrderEvent, OrderEventType, TradeEvent, TradeStatus, UserEvent};
+use crate::strategy::Side;
+
+/// API key the paper account's orders and trades carry.
+pub const PAPER_OWNER: &str = "paper";
+
+/// Owner of the simulated orders on the other side of resting paper orders.
+const MARKET_OWNER: &str = "market";
+
+/// Share amounts below this count as nothing.
+const SIZE_EPSILON: f64 = 1e-9;
+
+/// Milliseconds since the epoch, from the wall clock or a replay.
+pub type Clock = Arc<dyn Fn() -> i64 + Send + Sync>;
+
+fn price_key(price: f64) -> u64 {
+    (price * 1_000_000.0).round() as u64
+}
+
+fn opposite(side: Side) -> Side {
+    match side {
+        Side::Buy => Side::Sell,
+        Side::Sell => Side::Buy,
+    }
+}
+
+/// One token's book as the paper account sees it.
+#[derive(Debug, Default)]
+struct PaperBook {
+    market: String,
+    /// Levels less what the paper account took, best first.
+    bids: Vec<PriceLevel>,
+    asks: Vec<PriceLevel>,
+    /// Size taken per book side and price level.
+    taken: HashMap<(Side, u64), f64>,
+}
+
+impl PaperBook {
+    fn update(&mut self, book: &OrderBook) {
+        self.market = book.market.clone();
+        let mut taken = HashMap::new();
+        for side in [Side::Buy, Side::Sell] {
+            let levels = book
+                .depth(side, usize::MAX)
+                .into_iter()
+                .filter_map(|level| {
+                    let key = (side, price_key(level.price));
+                    let gone = self.taken.get(&key).copied().unwrap_or(0.0).min(level.size);
+                    if gone > 0.0 {
+                        taken.insert(key, gone);
+                    }
+                    let size = level.size - gone;
+                    (size > SIZE_EPSILON).then_some(PriceLevel { price: level.price, size })
+                })
+                .collect();
+            match side {
+                Side::Buy => self.bids = levels,
+                Side::Sell => self.asks = levels,
+            }
+        }
+        self.taken = taken;
+    }
+
+    /// Levels an order on `side` trades against, best first.
+    fn contra(&mut self, side: Side) -> &mut Vec<PriceLevel> {
+        match side {
+            Side::Buy => &mut self.asks,
+            Side::Sell => &mut self.bids,
+        }
+    }
+
+    fn crosses(side: Side, level_price: f64, limit: f64) -> bool {
+        match side {
+            Side::Buy => level_price <= limit + SIZE_EPSILON,
+            Side::Sell => level_price >= limit - SIZE_EPSILON,
+        }
+    }
+
+    /// Shares an order on `side` could take at `limit` or better.
+    fn available(&mut self, side: Side, limit: f64) -> f64 {
+        self.contra(side).iter().take_while(|l| Self::crosses(side, l.price, limit)).map(|l| l.size).sum()
+    }
+
+    /// Take up to `size` shares at `limit` or better; `(price, size)` per level.
+    fn take(&mut self, side: Side, limit: f64, size: f64) -> Vec<(f64, f64)> {
+        let mut fills = Vec::new();
+        let mut remaining = size;
+        let levels = self.contra(side);
+        while remaining > SIZE_EPSILON {
+            let Some(level) = levels.first_mut().filter(|l| Self::crosses(side, l.price, limit)) else { break };
+            let filled = level.size.min(remaining);
+            level.size -= filled;
+            remaining -= filled;
+            fills.push((level.price, filled));
+            if level.size <= SIZE_EPSILON {
+                levels.remove(0);
+            }
+        }
+        for &(price, filled) in &fills {
+            *self.taken.entry((opposite(side), price_key(price))).or_default() += filled;
+        }
+        fills
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RestingOrder {
+    id: String,
+    market: String,
+    asset_id: String,
+    side: Side,
+    price: f64,
+    original_size: f64,
+    size_matched: f64,
+    order_type: OrderType,
+    /// Seconds since the epoch; 0 for none.
+    expiration: u64,
+    created_at: i64,
+}
+
+impl RestingOrder {
+    fn remaining(&self) -> f64 {
+        self.original_size - self.size_matched
+    }
+
+    fn event(&self, kind: OrderEventType, timestamp: i64) -> UserEvent {
+        UserEvent::Order(OrderEvent {
+            id: self.id.clone(),
+            market: self.market.clone(),
+            asset_id: self.asset_id.clone(),
+            side: self.side,
+            price: self.price,
+            original_size: self.original_size,
+            size_matched: self.size_matched,
+            outcome: String::new(),
+            owner: PAPER_OWNER.to_string(),
+            kind,
+            associate_trades: Vec::new(),
+            timestamp,
+        })
+    }
+}
+
+struct PaperExchange {
+    clock: Clock,
+    books: HashMap<String, PaperBook>,
+    resting: BTreeMap<String, RestingOrder>,
+    events: mpsc::UnboundedSender<UserEvent>,
+    trades: u64,
+}
+
+impl PaperExchange {
+    fn now_secs(&self) -> i64 {
+        (self.clock)() / 1000
+    }
+
+    fn emit(&self, event: UserEvent) {
+        // Nobody listening only means nobody keeps score.
+        let _ = self.events.send(event);
+    }
+
+    fn next_trade_id(&mut self) -> String {
+        self.trades += 1;
+        format!("paper-trade-{}", self.trades)
+    }
+
+    fn expire(&mut self) {
+        let now = self.now_secs();
+        let expired: Vec<String> = self
+            .resting
+            .values()
+            .filter(|o| o.expiration > 0 && o.expiration as i64 <= now)
+            .map(|o| o.id.clone())
+            .collect();
+        for id in expired {
+            let order = self.resting.remove(&id).expect("listed above");
+            self.emit(order.event(OrderEventType::Cancellation, now));
+        }
+    }
+
+    fn post_order(&mut self, signed: &SignedOrder, order_type: OrderType, post_only: bool) -> OrderResponse {
+        self.expire();
+        let order_id = signed.order_id();
+        let order = &signed.order;
+        let mut response = OrderResponse {
+            success: false,
+            order_id: order_id.clone(),
+            status: None,
+            making_amount: None,
+            taking_amount: None,
+            error_msg: String::new(),
+            transaction_hashes: Vec::new(),
+        };
+        let reject = |mut response: OrderResponse, error: String| {
+            response.error_msg = error;
+            response
+        };
+        let (shares, collateral) = match order.side {
+            Side::Buy => (order.taker_amount, order.maker_amount),
+            Side::Sell => (order.maker_amount, order.taker_amount),
+        };
+        if shares == 0 {
+            return reject(response, "order has no size".to_string());
+        }
+        let size = shares as f64 / 1e6;
+        // Amounts are exact to the tick, at most 4 decimals.
+        let price = (collateral as f64 / shares as f64 * 10_000.0).round() / 10_000.0;
+        let now = self.now_secs();
+        if order.expiration > 0 && order.expiration as i64 <= now {
+            return reject(response, format!("order expired at {}", order.expiration));
+        }
+        let Some(book) = self.books.get_mut(&order.token_id) else {
+            return reject(response, format!("no order book for token {}", order.token_id));
+        };
+        let available = book.available(order.side, price);
+        if post_only && available > SIZE_EPSILON {
+            return reject(response, "post-only order crosses the book".to_string());
+        }
+        if order_type == OrderType::Fok && available + SIZE_EPSILON < size {
+            return reject(response, format!("FOK order could not be fully filled ({} of {} available)", available, size));
+        }
+        if order_type == OrderType::Fak && available <= SIZE_EPSILON {
+            return reject(response, "no match for FAK order".to_string());
+        }
+
+        let market = book.market.clone();
+        let fills = book.take(order.side, price, size);
+        let fee_rate_bps = Some(order.fee_rate_bps as f64);
+        for &(level_price, filled) in &fills {
+            let trade = TradeEvent {
+                id: self.next_trade_id(),
+                market: market.clone(),
+                asset_id: order.token_id.clone(),
+                side: order.side,
+                price: level_price,
+                size: filled,
+                status: TradeStatus::Confirmed,
+                taker_order_id: order_id.clone(),
+                owner: PAPER_OWNER.to_string(),
+                maker_orders: Vec::new(),
+                fee_rate_bps,
+                timestamp: now,
+            };
+            self.emit(UserEvent::Trade(trade));
+        }
+        let matched: f64 = fills.iter().map(|(_, s)| s).sum();
+        let cost: f64 = fills.iter().map(|(p, s)| p * s).sum();
+        if matched > SIZE_EPSILON {
+            response.status = Some(PlacementStatus::Matched);
+            (response.making_amount, response.taking_amount) = match order.side {
+                Side::Buy => (Some(cost), Some(matched)),
+                Side::Sell => (Some(matched), Some(cost)),
+            };
+        } else {
+            response.status = Some(PlacementStatus::Live);
+        }
+        response.success = true;
+
+        if !order_type.is_marketable() && size - matched > SIZE_EPSILON {
+            let resting = RestingOrder {
+                id: order_id.clone(),
+                market,
+                asset_id: order.token_id.clone(),
+                side: order.side,
+                price,
+                original_size: size,
+                size_matched: matched,
+                order_type,
+                expiration: order.expiration,
+                created_at: now,
+            };
+            self.emit(resting.event(OrderEventType::Placement, now));
+            self.resting.insert(order_id, resting);
+        }
+        response
+    }
+
+    /// Fill resting orders on `asset_id` that its book now crosses, best
+    /// priced first.
+    fn match_resting(&mut self, asset_id: &str) {
+        let mut ids: Vec<(String, Side, f64)> = self
+            .resting
+            .values()
+            .filter(|o| o.asset_id == asset_id)
+            .map(|o| (o.id.clone(), o.side, o.price))
+            .collect();
+        ids.sort_by(|a, b| match a.1 {
+            Side::Buy => b.2.total_cmp(&a.2),
+            Side::Sell => a.2.total_cmp(&b.2),
+        });
+        for (id, _, _) in ids {
+            let order = &self.resting[&id];
+            let (side, price, remaining) = (order.side, order.price, order.remaining());
+            let Some(book) = self.books.get_mut(asset_id) else { return };
+            let filled: f64 = book.take(side, price, remaining).iter().map(|(_, s)| s).sum();
+            if filled <= SIZE_EPSILON {
+                continue;
+            }
+            let order = self.resting.get_mut(&id).expect("listed above");
+            order.size_matched += filled;
+            let order = order.clone();
+            let maker = MakerOrder {
+                order_id: order.id.clone(),
+                asset_id: order.asset_id.clone(),
+                matched_amount: filled,
+                price,
+                outcome: String::new(),
+                owner: PAPER_OWNER.to_string(),
+                side: Some(side),
+            };
+            let now = self.now_secs();
+            let trade = TradeEvent {
+                id: self.next_trade_id(),
+                market: order.market.clone(),
+                asset_id: asset_id.to_string(),
+                side: opposite(side),
+                price,
+                size: filled,
+                status: TradeStatus::Confirmed,
+                taker_order_id: String::new(),
+                owner: MARKET_OWNER.to_string(),
+                maker_orders: vec![maker],
+                fee_rate_bps: None,
+                timestamp: now,
+            };
+            self.emit(UserEvent::Trade(trade));
+            self.emit(order.event(OrderEventType::Update, now));
+            if order.remaining() <= SIZE_EPSILON {
+                self.resting.remove(&id);
+            }
+        }
+    }
+
+    fn cancel(&mut self, matches: impl Fn(&RestingOrder) -> bool) -> CancelResponse {
+        self.expire();
+        let ids: Vec<String> = self.resting.values().filter(|o| matches(o)).map(|o| o.id.clone()).collect();
+        let now = self.now_secs();
+        let mut response = CancelResponse::default();
+        for id in ids {
+            let order = self.resting.remove(&id).expect("listed above");
+            self.emit(order.event(OrderEventType::Cancellation, now));
+            response.canceled.push(id);
+        }
+        response
+    }
+
+    fn cancel_ids(&mut self, order_ids: &[String]) -> CancelResponse {
+        let mut response = self.cancel(|o| order_ids.contains(&o.id));
+        for id in order_ids.iter().filter(|id| !response.canceled.contains(id)) {
+            response.not_canceled.insert(id.clone(), "order not found or already closed".to_string());
+        }
+        response
+    }
+
+    fn open_orders(&mut self, market: Option<&str>, asset_id: Option<&str>) -> Vec<OpenOrder> {
+        self.expire();
+        self.resting
+            .values()
+            .filter(|o| market.is_none_or(|m| o.market == m) && asset_id.is_none_or(|a| o.asset_id == a))
+            .map(|o| OpenOrder {
+                id: o.id.clone(),
+                status: "LIVE".to_string(),
+                market: o.market.clone(),
+                asset_id: o.asset_id.clone(),
+                side: o.side,
+                original_size: o.original_size,
+                size_matched: o.size_matched,
+                price: o.price,
+                outcome: String::new(),
+                order_type: o.order_type.as_str().to_string(),
+                expiration: (o.expiration > 0).then_some(o.expiration as i64),
+                created_at: Some(o.created_at),
+            })
+            .collect()
+    }
+}
+
+/// Simulated CLOB order entry; clones share one paper account.
+#[derive(Clone)]
+pub struct PaperGateway {
+    exchange: Arc<Mutex<PaperExchange>>,
+}
+
+impl PaperGateway {
+    /// A paper account on the wall clock, and the channel its trades and
+    /// order changes are reported on.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<UserEvent>) {
+        let (events, rx) = mpsc::unbounded_channel();
+        let exchange = PaperExchange {
+            clock: Arc::new(|| chrono::Utc::now().timestamp_millis()),
+            books: HashMap::new(),
+            resting: BTreeMap::new(),
+            events,
+            trades: 0,
+        };
+        (Self { exchange: Arc::new(Mutex::new(exchange)) }, rx)
+    }
+
+    /// Tell time with `clock` instead of the wall clock, e.g. during a replay.
+    pub fn with_clock(self, clock: Clock) -> Self {
+        self.exchange.lock().unwrap().clock = clock;
+        self
+    }
+
+    /// Mirror the local book of `asset_id` (`None` once it is no longer
+    /// trustworthy) and fill resting orders it now crosses.
+    pub fn on_book(&self, asset_id: &str, book: Option<&OrderBook>) {
+        let mut exchange = self.exchange.lock().unwrap();
+        exchange.expire();
+        match book {
+            Some(book) => {
+                exchange.books.entry(asset_id.to_string()).or_default().update(book);
+                exchange.match_resting(asset_id);
+            }
+            None => {
+                exchange.books.remove(asset_id);
+            }
+        }
+    }
+
+    fn with_exchange<T: Send + 'static>(&self, f: impl FnOnce(&mut PaperExchange) -> T) -> BoxFuture<'_, T> {
+        let result = f(&mut self.exchange.lock().unwrap());
+        Box::pin(std::future::ready(result))
+    }
+}
+
+impl ExecutionGateway for PaperGateway {
+    fn endpoint(&self) -> &str {
+        "paper"
+    }
+
+    fn is_authenticated(&self) -> bool {
+        true
+    }
+
+    fn post_order<'a>(
+        &'a self,
+        order: &'a SignedOrder,
+        order_type: OrderType,
+        post_only: bool,
+    ) -> BoxFuture<'a, Result<OrderResponse, ClobError>> {
+        self.with_exchange(|e| Ok(e.post_order(order, order_type, post_only)))
+    }
+
+    fn cancel_order<'a>(&'a self, order_id: &'a str) -> BoxFuture<'a, Result<CancelResponse, ClobError>> {
+        self.with_exchange(|e| Ok(e.cancel_ids(&[order_id.to_string()])))
+    }
+
+    fn cancel_orders<'a>(&'a self, order_ids: &'a [String]) -> BoxFuture<'a, Result<CancelResponse, ClobError>> {
+        self.with_exchange(|e| Ok(e.cancel_ids(order_ids)))
+    }
+
+    fn cancel_market_orders<'a>(
+        &'a self,
+        market: &'a str,
+        asset_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<CancelResponse, ClobError>> {
+        self.with_exchange(|e| Ok(e.cancel(|o| o.market == market && asset_id.is_none_or(|a| o.asset_id == a))))
+    }
+
+    fn cancel_all(&self) -> BoxFuture<'_, Result<CancelResponse, ClobError>> {
+        self.with_exchange(|e| Ok(e.cancel(|_| true)))
+    }
+
+    fn open_orders<'a>(
+        &'a self,
+        market: Option<&'a str>,
+        asset_id: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<OpenOrder>, ClobError>> {
+        self.with_exchange(|e| Ok(e.open_orders(market, asset_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::Account;
+    use crate::config::SignatureType;
+    use crate::polymarket::eip712::Wallet;
+    use crate::polymarket::market_ws::BookEvent;
+    use crate::polymarket::order::{OrderArgs, OrderBuilder};
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    const TOKEN: &str = "1234";
+    const HARDHAT_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn book(asks: &[(f64, f64)], bids: &[(f64, f64)]) -> OrderBook {
+        let levels = |levels: &[(f64, f64)]| levels.iter().map(|&(price, size)| PriceLevel { price, size }).collect();
+        let snapshot = BookEvent {
+            asset_id: TOKEN.to_string(),
+            market: "0xabc".to_string(),
+            bids: levels(bids),
+            asks: levels(asks),
+            timestamp: 1,
+            hash: None,
+        };
+        let mut book = OrderBook::new(TOKEN);
+        book.apply_snapshot(&snapshot, 1);
+        book
+    }
+
+    fn order(side: Side, price: f64, size: f64, order_type: OrderType, expiration: u64) -> SignedOrder {
+        let builder = OrderBuilder::new(Wallet::from_hex(HARDHAT_KEY).unwrap(), 137, SignatureType::Eoa, None).unwrap();
+        let args = OrderArgs {
+            token_id: TOKEN.to_string(),
+            side,
+            price,
+            size,
+            fee_rate_bps: 100,
+            order_type,
+            expiration,
+            nonce: 0,
+        };
+        builder.build_signed(&args, 0.01, false).unwrap()
+    }
+
+    fn drain(rx: &mut mpsc::UnboundedReceiver<UserEvent>, account: &mut Account) -> Vec<UserEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            account.apply(&event);
+            events.push(event);
+        }
+        events
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[tokio::test]
+    async fn walks_levels_and_charges_taker_fees() {
+        let (paper, mut rx) = PaperGateway::new();
+        let mut account = Account::new(PAPER_OWNER);
+        paper.on_book(TOKEN, Some(&book(&[(0.50, 10.0), (0.51, 10.0), (0.53, 100.0)], &[(0.48, 50.0)])));
+
+        let fak = order(Side::Buy, 0.52, 30.0, OrderType::Fak, 0);
+        let response = paper.post_order(&fak, OrderType::Fak, false).await.unwrap();
+        assert_eq!(response.status, Some(PlacementStatus::Matched));
+        assert_close(response.making_amount.unwrap(), 10.1);
+        assert_eq!(response.taking_amount, Some(20.0));
+        // The rest of a FAK order is killed, not rested.
+        assert_eq!(drain(&mut rx, &mut account).len(), 2);
+        assert!(paper.open_orders(None, None).await.unwrap().is_empty());
+
+        let position = account.positions().get(TOKEN).unwrap();
+        assert_close(position.shares, 20.0);
+        assert_close(position.avg_cost, 0.505);
+        // 1% of min(p, 1 - p) per share: 10 * 0.005 + 10 * 0.0049.
+        assert_close(position.realized_pnl, -0.099);
+
+        // The taken levels stay taken; what is left is too little for a FOK.
+        let fok = order(Side::Buy, 0.53, 120.0, OrderType::Fok, 0);
+        let response = paper.post_order(&fok, OrderType::Fok, false).await.unwrap();
+        assert!(response.error().unwrap().starts_with("FOK order could not be fully filled"));
+        let sell = order(Side::Sell, 0.48, 20.0, OrderType::Fok, 0);
+        let response = paper.post_order(&sell, OrderType::Fok, false).await.unwrap();
+        assert_eq!((response.making_amount, response.taking_amount), (Some(20.0), Some(9.6)));
+        drain(&mut rx, &mut account);
+        assert_eq!(account.position(TOKEN), 0.0);
+    }
+
+    #[tokio::test]
+    async fn rests_and_fills_as_maker_once_crossed() {
+        let (paper, mut rx) = PaperGateway::new();
+        let mut account = Account::new(PAPER_OWNER);
+        let unbooked = order(Side::Buy, 0.49, 20.0, OrderType::Gtc, 0);
+        let response = paper.post_order(&unbooked, OrderType::Gtc, false).await.unwrap();
+        assert_eq!(response.error(), Some("no order book for token 1234"));
+
+        paper.on_book(TOKEN, Some(&book(&[(0.50, 10.0)], &[(0.48, 10.0)])));
+        let crossing = order(Side::Buy, 0.50, 5.0, OrderType::Gtc, 0);
+        let response = paper.post_order(&crossing, OrderType::Gtc, true).await.unwrap();
+        assert_eq!(response.error(), Some("post-only order crosses the book"));
+
+        let bid = order(Side::Buy, 0.49, 20.0, OrderType::Gtc, 0);
+        let response = paper.post_order(&bid, OrderType::Gtc, true).await.unwrap();
+        assert_eq!(response.status, Some(PlacementStatus::Live));
+        assert_eq!(drain(&mut rx, &mut account).len(), 1);
+
+        // An ask at our price fills us at our price, without fees.
+        paper.on_book(TOKEN, Some(&book(&[(0.49, 5.0)], &[(0.48, 10.0)])));
+        drain(&mut rx, &mut account);
+        assert_eq!(account.position(TOKEN), 5.0);
+        assert_eq!(account.positions().get(TOKEN).unwrap().realized_pnl, 0.0);
+        // The same ask is not filled twice; only what is added to it.
+        paper.on_book(TOKEN, Some(&book(&[(0.49, 5.0)], &[(0.48, 10.0)])));
+        paper.on_book(TOKEN, Some(&book(&[(0.49, 8.0)], &[(0.48, 10.0)])));
+        drain(&mut rx, &mut account);
+        assert_eq!(account.position(TOKEN), 8.0);
+
+        let open = paper.open_orders(Some("0xabc"), None).await.unwrap();
+        assert_eq!((open.len(), open[0].size_matched), (1, 8.0));
+        let cancelled = paper.cancel_all().await.unwrap();
+        assert_eq!(cancelled.canceled, [bid.order_id()]);
+        let missing = paper.cancel_order(&bid.order_id()).await.unwrap();
+        assert_eq!(missing.not_canceled.len(), 1);
+        let events = drain(&mut rx, &mut account);
+        assert!(matches!(&events[..], [UserEvent::Order(o)] if o.kind == OrderEventType::Cancellation));
+        assert_eq!(account.open_orders().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn expires_gtd_orders_on_its_clock() {
+        let now = Arc::new(AtomicI64::new(1_000_000));
+        let clock = now.clone();
+        let (paper, mut rx) = PaperGateway::new();
+        let paper = paper.with_clock(Arc::new(move || clock.load(Ordering::SeqCst)));
+        paper.on_book(TOKEN, Some(&book(&[(0.50, 10.0)], &[(0.48, 10.0)])));
+
+        let gtd = order(Side::Buy, 0.45, 10.0, OrderType::Gtd, 1_060);
+        paper.post_order(&gtd, OrderType::Gtd, false).await.unwrap();
+        assert_eq!(paper.open_orders(None, None).await.unwrap().len(), 1);
+        now.store(1_060_000, Ordering::SeqCst);
+        assert!(paper.open_orders(None, None).await.unwrap().is_empty());
+        let events: Vec<UserEvent> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(matches!(&events[..], [_, UserEvent::Order(o)] if o.kind == OrderEventType::Cancellation));
+
+        let late = order(Side::Buy, 0.45, 10.0, OrderType::Gtd, 1_060);
+        let response = paper.post_order(&late, OrderType::Gtd, false).await.unwrap();
+        assert_eq!(response.error(), Some("order expired at 1060"));
+    }
+}
@@ -0,0 +1,129 @@
+//! Binance (Binance.US) raw `trade` stream connector.
+//!
+//! Subscriptions are sent as a `SUBSCRIBE` request on the bare `/ws` endpoint.
+//! Trade events carry no book, so ticks from this venue only have `last`.
+
+use serde::Deserialize;
+
+use super::{Instruments, SpotFeed, SpotTick};
+
+pub const VENUE: &str = "binance";
+
+#[derive(Deserialize)]
+struct RawTrade {
+    #[serde(rename = "e")]
+    event: String,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "t")]
+    trade_id: u64,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "T")]
+    trade_time_ms: i64,
+}
+
+/// Map a configured pair such as `xbt/usdt` onto a Binance stream name (`btcusdt`).
+pub fn stream_symbol(symbol: &str) -> String {
+    let compact = symbol.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase();
+    match compact.strip_prefix("xbt") {
+        Some(rest) => format!("btc{}", rest),
+        None => compact,
+    }
+}
+
+/// Subscription request for the trade streams of `streams`.
+pub fn subscribe_message(streams: &[String]) -> String {
+    let params: Vec<String> = streams.iter().map(|s| format!("{}@trade", s)).collect();
+    serde_json::json!({ "method": "SUBSCRIBE", "params": params, "id": 1 }).to_string()
+}
+
+/// Parse a `trade` event. The tick symbol is the lower-cased stream name
+/// (`btcusdt`); anything that is not a trade yields `None`.
+pub fn parse_message(text: &str, received_at_ms: i64) -> Option<SpotTick> {
+    let raw: RawTrade = serde_json::from_str(text).ok()?;
+    if raw.event != "trade" {
+        return None;
+    }
+    Some(SpotTick {
+        venue: VENUE,
+        symbol: raw.symbol.to_lowercase(),
+        bid: None,
+        ask: None,
+        last: raw.price.parse().ok()?,
+        exchange_ts_ms: Some(raw.trade_time_ms),
+        sequence: Some(raw.trade_id),
+        received_at_ms,
+    })
+}
+
+pub struct BinanceFeed {
+    url: String,
+    instruments: Instruments,
+}
+
+impl BinanceFeed {
+    pub fn new(url: &str, instruments: Instruments) -> Self {
+        Self { url: url.to_string(), instruments }
+    }
+}
+
+impl SpotFeed for BinanceFeed {
+    fn venue(&self) -> &'static str {
+        VENUE
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    fn subscribe_messages(&self) -> Vec<String> {
+        vec![subscribe_message(&self.instruments.venue_names())]
+    }
+
+    fn parse(&self, text: &str, received_at_ms: i64) -> Option<SpotTick> {
+        self.instruments.resolve(parse_message(text, received_at_ms)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/binance/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    #[test]
+    fn parses_trade_events() {
+        assert_eq!(
+            parse_message(&fixture("trade.json"), 1_704_456_000_200),
+            Some(SpotTick {
+                venue: VENUE,
+                symbol: "btcusdt".to_string(),
+                bid: None,
+                ask: None,
+                last: 43049.74,
+                exchange_ts_ms: Some(1_704_456_000_123),
+                sequence: Some(73_650_521),
+                received_at_ms: 1_704_456_000_200,
+            })
+        );
+        assert_eq!(parse_message(&fixture("subscribe_ack.json"), 0), None);
+    }
+
+    #[test]
+    fn feed_reports_configured_symbol() {
+        let mut instruments = Instruments::default();
+        instruments.insert(stream_symbol("xbt/usdt"), "xbt/usdt".to_string());
+        let feed = BinanceFeed::new("wss://example.invalid/ws", instruments);
+
+        assert_eq!(
+            feed.subscribe_messages(),
+            vec![r#"{"id":1,"method":"SUBSCRIBE","params":["btcusdt@trade"]}"#.to_string()]
+        );
+        let tick = feed.parse(&fixture("trade.json"), 0).unwrap();
+        assert_eq!(tick.symbol, "xbt/usdt");
+    }
+}
@@ -0,0 +1,170 @@
+//! Coinbase Exchange (`ws-feed.exchange.coinbase.com`) ticker connector.
+//!
+//! Every frame is a JSON object tagged with `type`. Ticker frames carry the
+//! venue `sequence` and exchange `time`, both of which are kept on the tick.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{Instruments, SpotFeed, SpotTick};
+
+pub const VENUE: &str = "coinbase";
+
+/// Everything the ticker socket can send us.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoinbaseMessage {
+    Ticker(SpotTick),
+    Subscriptions,
+    Heartbeat,
+    Error { message: String, reason: Option<String> },
+    /// Valid JSON we do not act on.
+    Other,
+}
+
+#[derive(Deserialize)]
+struct RawTicker {
+    product_id: String,
+    sequence: Option<u64>,
+    price: String,
+    best_bid: String,
+    best_ask: String,
+    time: Option<String>,
+}
+
+/// Map a configured pair such as `xbt/usdt` onto a Coinbase product id (`BTC-USDT`).
+pub fn product_id(symbol: &str) -> String {
+    symbol
+        .split(['/', '-'])
+        .map(|part| match part.to_uppercase().as_str() {
+            "XBT" => "BTC".to_string(),
+            other => other.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Subscription request for the ticker channel of `product_ids`.
+pub fn subscribe_message(product_ids: &[String]) -> String {
+    serde_json::json!({
+        "type": "subscribe",
+        "product_ids": product_ids,
+        "channels": ["ticker"],
+    })
+    .to_string()
+}
+
+/// Parse one text frame. Returns `None` for frames that are not valid JSON or
+/// tickers with unparseable prices.
+pub fn parse_message(text: &str, received_at_ms: i64) -> Option<CoinbaseMessage> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    match value.get("type").and_then(Value::as_str) {
+        Some("ticker") => {
+            let raw: RawTicker = serde_json::from_value(value).ok()?;
+            let exchange_ts_ms = raw
+                .time
+                .as_deref()
+                .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.timestamp_millis());
+            Some(CoinbaseMessage::Ticker(SpotTick {
+                venue: VENUE,
+                symbol: raw.product_id,
+                bid: Some(raw.best_bid.parse().ok()?),
+                ask: Some(raw.best_ask.parse().ok()?),
+                last: raw.price.parse().ok()?,
+                exchange_ts_ms,
+                sequence: raw.sequence,
+                received_at_ms,
+            }))
+        }
+        Some("subscriptions") => Some(CoinbaseMessage::Subscriptions),
+        Some("heartbeat") => Some(CoinbaseMessage::Heartbeat),
+        Some("error") => {
+            let field = |name: &str| value.get(name).and_then(Value::as_str).map(str::to_string);
+            Some(CoinbaseMessage::Error { message: field("message").unwrap_or_default(), reason: field("reason") })
+        }
+        _ => Some(CoinbaseMessage::Other),
+    }
+}
+
+pub struct CoinbaseFeed {
+    url: String,
+    instruments: Instruments,
+}
+
+impl CoinbaseFeed {
+    pub fn new(url: &str, instruments: Instruments) -> Self {
+        Self { url: url.to_string(), instruments }
+    }
+}
+
+impl SpotFeed for CoinbaseFeed {
+    fn venue(&self) -> &'static str {
+        VENUE
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    fn subscribe_messages(&self) -> Vec<String> {
+        vec![subscribe_message(&self.instruments.venue_names())]
+    }
+
+    fn parse(&self, text: &str, received_at_ms: i64) -> Option<SpotTick> {
+        match parse_message(text, received_at_ms)? {
+            CoinbaseMessage::Ticker(tick) => self.instruments.resolve(tick),
+            CoinbaseMessage::Error { message, reason } => {
+                eprintln!("Coinbase error: {} ({})", message, reason.unwrap_or_default());
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/coinbase/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    #[test]
+    fn parses_ticker_with_sequence_and_time() {
+        let msg = parse_message(&fixture("ticker.json"), 1_704_456_000_200).unwrap();
+        assert_eq!(
+            msg,
+            CoinbaseMessage::Ticker(SpotTick {
+                venue: VENUE,
+                symbol: "BTC-USD".to_string(),
+                bid: Some(43049.73),
+                ask: Some(43049.74),
+                last: 43049.74,
+                exchange_ts_ms: Some(1_704_456_000_123),
+                sequence: Some(37_475_248_783),
+                received_at_ms: 1_704_456_000_200,
+            })
+        );
+    }
+
+    #[test]
+    fn classifies_control_frames() {
+        assert_eq!(parse_message(&fixture("subscriptions.json"), 0), Some(CoinbaseMessage::Subscriptions));
+        assert_eq!(parse_message(&fixture("heartbeat.json"), 0), Some(CoinbaseMessage::Heartbeat));
+        assert_eq!(
+            parse_message(&fixture("error.json"), 0),
+            Some(CoinbaseMessage::Error {
+                message: "Failed to subscribe".to_string(),
+                reason: Some("BTC-FOO is not a valid product".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn maps_configured_symbols_to_products() {
+        assert_eq!(product_id("xbt/usdt"), "BTC-USDT");
+        assert_eq!(product_id("eth-usd"), "ETH-USD");
+    }
+}
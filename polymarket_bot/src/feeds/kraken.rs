@@ -0,0 +1,178 @@
+//! Kraken public websocket (v1) ticker connector.
+//!
+//! Ticker updates arrive as arrays, `[channel_id, {..}, "ticker", "XBT/USDT"]`,
+//! while heartbeats, system status and subscription acks are JSON objects
+//! tagged with an `event` field.
+
+use serde_json::Value;
+
+use super::{Instruments, SpotFeed, SpotTick};
+
+pub const VENUE: &str = "kraken";
+
+/// Everything the ticker socket can send us.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KrakenMessage {
+    Ticker(SpotTick),
+    Heartbeat,
+    SystemStatus { status: String },
+    SubscriptionStatus { pair: Option<String>, status: String, error: Option<String> },
+    /// Valid JSON we do not act on (other channels, pong, ...).
+    Other,
+}
+
+/// Subscription request for the ticker channel of `pairs`.
+pub fn subscribe_message(pairs: &[String]) -> String {
+    serde_json::json!({
+        "event": "subscribe",
+        "pair": pairs,
+        "subscription": { "name": "ticker" },
+    })
+    .to_string()
+}
+
+/// Parse one text frame. Returns `None` for frames that are not valid JSON or
+/// that look like a ticker but are missing the bid, ask or last price.
+pub fn parse_message(text: &str, received_at_ms: i64) -> Option<KrakenMessage> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    match value {
+        Value::Object(obj) => {
+            let field = |name: &str| obj.get(name).and_then(Value::as_str).map(str::to_string);
+            Some(match obj.get("event").and_then(Value::as_str) {
+                Some("heartbeat") => KrakenMessage::Heartbeat,
+                Some("systemStatus") => KrakenMessage::SystemStatus { status: field("status").unwrap_or_default() },
+                Some("subscriptionStatus") => KrakenMessage::SubscriptionStatus {
+                    pair: field("pair"),
+                    status: field("status").unwrap_or_default(),
+                    error: field("errorMessage"),
+                },
+                _ => KrakenMessage::Other,
+            })
+        }
+        Value::Array(items) => {
+            // [channel_id, payload, channel_name, pair]
+            if items.len() < 4 || items[items.len() - 2].as_str() != Some("ticker") {
+                return Some(KrakenMessage::Other);
+            }
+            let data = items[1].as_object()?;
+            let pair = items[items.len() - 1].as_str()?.to_string();
+            let level = |key: &str| data.get(key)?.get(0)?.as_str()?.parse::<f64>().ok();
+            Some(KrakenMessage::Ticker(SpotTick {
+                venue: VENUE,
+                symbol: pair,
+                bid: Some(level("b")?),
+                ask: Some(level("a")?),
+                last: level("c")?,
+                exchange_ts_ms: None,
+                sequence: None,
+                received_at_ms,
+            }))
+        }
+        _ => Some(KrakenMessage::Other),
+    }
+}
+
+pub struct KrakenFeed {
+    url: String,
+    instruments: Instruments,
+}
+
+impl KrakenFeed {
+    pub fn new(url: &str, instruments: Instruments) -> Self {
+        Self { url: url.to_string(), instruments }
+    }
+}
+
+impl SpotFeed for KrakenFeed {
+    fn venue(&self) -> &'static str {
+        VENUE
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    fn subscribe_messages(&self) -> Vec<String> {
+        vec![subscribe_message(&self.instruments.venue_names())]
+    }
+
+    fn parse(&self, text: &str, received_at_ms: i64) -> Option<SpotTick> {
+        match parse_message(text, received_at_ms)? {
+            KrakenMessage::Ticker(tick) => self.instruments.resolve(tick),
+            KrakenMessage::SubscriptionStatus { pair, status, error } if status == "error" => {
+                eprintln!("Kraken subscription for {:?} failed: {}", pair, error.unwrap_or_default());
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/kraken/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    #[test]
+    fn parses_ticker_arrays() {
+        let msg = parse_message(&fixture("ticker_usdt.json"), 1_700_000_000_123).unwrap();
+        assert_eq!(
+            msg,
+            KrakenMessage::Ticker(SpotTick {
+                venue: VENUE,
+                symbol: "XBT/USDT".to_string(),
+                bid: Some(64249.9),
+                ask: Some(64250.1),
+                last: 64250.0,
+                exchange_ts_ms: None,
+                sequence: None,
+                received_at_ms: 1_700_000_000_123,
+            })
+        );
+
+        let Some(KrakenMessage::Ticker(tick)) = parse_message(&fixture("ticker.json"), 0) else {
+            panic!("expected ticker");
+        };
+        assert_eq!(tick.symbol, "XBT/USD");
+        assert_eq!((tick.bid, tick.ask, tick.last), (Some(5525.1), Some(5525.4), 5525.1));
+    }
+
+    #[test]
+    fn ignores_status_objects_and_other_channels() {
+        assert_eq!(parse_message(&fixture("heartbeat.json"), 0), Some(KrakenMessage::Heartbeat));
+        assert_eq!(
+            parse_message(&fixture("system_status.json"), 0),
+            Some(KrakenMessage::SystemStatus { status: "online".to_string() })
+        );
+        assert_eq!(parse_message(&fixture("spread.json"), 0), Some(KrakenMessage::Other));
+    }
+
+    #[test]
+    fn reports_subscription_status() {
+        assert_eq!(
+            parse_message(&fixture("subscription_status.json"), 0),
+            Some(KrakenMessage::SubscriptionStatus {
+                pair: Some("XBT/USDT".to_string()),
+                status: "subscribed".to_string(),
+                error: None,
+            })
+        );
+        let Some(KrakenMessage::SubscriptionStatus { status, error, .. }) =
+            parse_message(&fixture("subscription_error.json"), 0)
+        else {
+            panic!("expected subscription status");
+        };
+        assert_eq!(status, "error");
+        assert_eq!(error.as_deref(), Some("Currency pair not supported XBT/FOO"));
+    }
+
+    #[test]
+    fn rejects_malformed_frames() {
+        assert_eq!(parse_message("not json", 0), None);
+        assert_eq!(parse_message(r#"[1,{"a":["x",0,"1"]},"ticker","XBT/USD"]"#, 0), None);
+    }
+}
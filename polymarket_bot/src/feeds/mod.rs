@@ -0,0 +1,199 @@
+//! Spot exchange price feeds.
+//!
+//! Every venue implements [`SpotFeed`]: it knows its endpoint, the frames that
+//! subscribe to the configured instruments, and how to turn a text frame into
+//! a [`SpotTick`]. Ticks are reported under the *configured* symbol (e.g.
+//! `xbt/usdt`), so the strategy never sees venue naming.
+
+pub mod binance;
+pub mod coinbase;
+pub mod kraken;
+
+use futures_util::future::BoxFuture;
+use futures_util::{SinkExt, Stream, StreamExt};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::config::{Settings, SpotVenue};
+use crate::recorder::Recorder;
+use crate::supervisor::{self, BackoffPolicy, Keepalive, StreamEvent};
+
+/// Client websocket connection as returned by `connect_async`.
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+pub type FeedError = Box<dyn Error + Send + Sync>;
+
+/// Top-of-book and last trade for one instrument, as produced by every feed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotTick {
+    /// Venue id, e.g. `kraken`.
+    pub venue: &'static str,
+    /// Instrument name. Venue parsers report the venue's own name (`XBT/USDT`,
+    /// `BTC-USD`); ticks coming out of a [`SpotFeed`] carry the configured symbol.
+    pub symbol: String,
+    /// Best bid, when the venue publishes it on this channel.
+    pub bid: Option<f64>,
+    /// Best ask, when the venue publishes it on this channel.
+    pub ask: Option<f64>,
+    pub last: f64,
+    /// Exchange event time in milliseconds since the epoch, when the venue sends one.
+    pub exchange_ts_ms: Option<i64>,
+    /// Venue sequence number (or trade id), when the venue sends one.
+    pub sequence: Option<u64>,
+    /// Local wall-clock receive time in milliseconds since the epoch.
+    pub received_at_ms: i64,
+}
+
+/// A spot price source.
+pub trait SpotFeed: Send + Sync {
+    fn venue(&self) -> &'static str;
+
+    fn endpoint(&self) -> &str;
+
+    /// Frames to send after connecting.
+    fn subscribe_messages(&self) -> Vec<String>;
+
+    /// Parse one text frame into a tick under the configured symbol. Control
+    /// frames, unknown instruments and malformed data yield `None`.
+    fn parse(&self, text: &str, received_at_ms: i64) -> Option<SpotTick>;
+
+    /// Connect to [`endpoint`](Self::endpoint) and send the subscriptions.
+    fn connect(&self) -> BoxFuture<'_, Result<WsStream, FeedError>> {
+        Box::pin(async move {
+            let (mut ws_stream, _) = connect_async(self.endpoint()).await?;
+            for msg in self.subscribe_messages() {
+                ws_stream.send(Message::Text(msg)).await?;
+            }
+            println!("Connected to {} spot feed at {}", self.venue(), self.endpoint());
+            Ok(ws_stream)
+        })
+    }
+}
+
+/// Venue instrument names mapped back to the configured symbols they feed.
+#[derive(Debug, Clone, Default)]
+pub struct Instruments {
+    by_venue_name: HashMap<String, String>,
+}
+
+impl Instruments {
+    pub fn insert(&mut self, venue_name: String, symbol: String) {
+        self.by_venue_name.insert(venue_name, symbol);
+    }
+
+    /// Venue names, sorted so subscription frames are deterministic.
+    pub fn venue_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.by_venue_name.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Rewrite a venue-named tick to the configured symbol.
+    pub fn resolve(&self, mut tick: SpotTick) -> Option<SpotTick> {
+        tick.symbol = self.by_venue_name.get(&tick.symbol)?.clone();
+        Some(tick)
+    }
+}
+
+/// Build one feed per venue referenced by the configured markets.
+pub fn from_settings(settings: &Settings) -> Vec<Arc<dyn SpotFeed>> {
+    let mut by_venue: HashMap<SpotVenue, Instruments> = HashMap::new();
+    for market in &settings.markets {
+        let venue = settings.venue_for(market);
+        let symbol = market.symbol.to_lowercase();
+        let venue_name = match venue {
+            SpotVenue::Binance => binance::stream_symbol(&symbol),
+            SpotVenue::Kraken => settings.kraken_pair.clone(),
+            SpotVenue::Coinbase => coinbase::product_id(&symbol),
+        };
+        by_venue.entry(venue).or_default().insert(venue_name, symbol);
+    }
+    by_venue
+        .into_iter()
+        .map(|(venue, instruments)| -> Arc<dyn SpotFeed> {
+            match venue {
+                SpotVenue::Binance => Arc::new(binance::BinanceFeed::new(&settings.binance_ws_url, instruments)),
+                SpotVenue::Kraken => Arc::new(kraken::KrakenFeed::new(&settings.kraken_ws_url, instruments)),
+                SpotVenue::Coinbase => Arc::new(coinbase::CoinbaseFeed::new(&settings.coinbase_ws_url, instruments)),
+            }
+        })
+        .collect()
+}
+
+/// What the merged spot stream yields.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedEvent {
+    Tick(SpotTick),
+    /// Connection state change of one venue's socket (never a frame).
+    Connection { venue: &'static str, event: StreamEvent },
+}
+
+/// Run every feed under a reconnecting supervisor and merge them into one
+/// normalized stream. Subscriptions are re-sent on every reconnect, sockets
+/// are pinged and dropped when they go silent, and raw frames are captured
+/// when a `recorder` is given.
+pub fn spawn_all(
+    feeds: &[Arc<dyn SpotFeed>],
+    policy: BackoffPolicy,
+    recorder: Option<&Recorder>,
+) -> impl Stream<Item = FeedEvent> + Unpin {
+    let streams = feeds.iter().map(|feed| {
+        let connector = {
+            let feed = Arc::clone(feed);
+            move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+                let feed = Arc::clone(&feed);
+                Box::pin(async move { feed.connect().await })
+            }
+        };
+        let (rx, _task) =
+            supervisor::spawn_recorded(feed.venue(), policy, Keepalive::default(), connector, recorder.cloned());
+        let feed = Arc::clone(feed);
+        futures_util::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|event| (event, rx)) })
+            .filter_map(move |event| {
+                let out = match event {
+                    StreamEvent::Frame(text) => {
+                        feed.parse(&text, chrono::Utc::now().timestamp_millis()).map(FeedEvent::Tick)
+                    }
+                    event => Some(FeedEvent::Connection { venue: feed.venue(), event }),
+                };
+                futures_util::future::ready(out)
+            })
+            .boxed()
+    });
+    futures_util::stream::select_all(streams)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_settings;
+
+    #[test]
+    fn subscribes_each_venue_under_its_own_names() {
+        let yaml = r#"
+kraken_pair: "XBT/USD"
+markets:
+  - market_id: "0xabc"
+    symbol: "btc/usd"
+    spot_venue: kraken
+    yes_token_id: "1111"
+    no_token_id: "2222"
+  - market_id: "0xdef"
+    symbol: "eth/usdt"
+    yes_token_id: "3333"
+    no_token_id: "4444"
+"#;
+        let feeds = from_settings(&parse_settings(yaml).unwrap());
+        let mut subscriptions: Vec<(&str, Vec<String>)> =
+            feeds.iter().map(|feed| (feed.venue(), feed.subscribe_messages())).collect();
+        subscriptions.sort();
+        assert_eq!(subscriptions.len(), 2);
+        assert_eq!(subscriptions[0].0, "binance");
+        assert!(subscriptions[0].1[0].contains("ethusdt@trade"), "{:?}", subscriptions[0]);
+        assert_eq!(subscriptions[1], ("kraken", vec![kraken::subscribe_message(&["XBT/USD".to_string()])]));
+    }
+}
@@ -0,0 +1,338 @@
+//! Global kill switch.
+//!
+//! Trips on a daily loss beyond `max_daily_loss`, on
+//! `max_consecutive_rejections` order rejections in a row, when a feed stays
+//! disconnected or a market-data feed delivers nothing for longer than
+//! `feed_timeout_secs`, or by hand
+//! (`polymarket_bot kill-switch trip`). Once tripped the bot cancels every
+//! order and places no more until the switch is reset. The tripped state is
+//! written to `state_file`, so a restart stays halted, and the running bot
+//! re-reads that file to pick up a manual trip from another process.
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::config::KillSwitchConfig;
+
+/// Why the switch tripped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TripReason {
+    DailyLoss { loss: f64, limit: f64 },
+    ConsecutiveRejections { count: u32 },
+    FeedLoss { feed: String, down_secs: u64 },
+    Manual { reason: String },
+}
+
+impl TripReason {
+    /// Stable label for logs and metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TripReason::DailyLoss { .. } => "daily_loss",
+            TripReason::ConsecutiveRejections { .. } => "consecutive_rejections",
+            TripReason::FeedLoss { .. } => "feed_loss",
+            TripReason::Manual { .. } => "manual",
+        }
+    }
+}
+
+impl fmt::Display for TripReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TripReason::DailyLoss { loss, limit } => write!(f, "daily loss {:.2} reached the {:.2} limit", loss, limit),
+            TripReason::ConsecutiveRejections { count } => write!(f, "{} order rejections in a row", count),
+            TripReason::FeedLoss { feed, down_secs } => write!(f, "no data from {} feed for {}s", feed, down_secs),
+            TripReason::Manual { reason } => write!(f, "manual: {}", reason),
+        }
+    }
+}
+
+/// What is persisted while tripped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tripped {
+    pub reason: TripReason,
+    /// Milliseconds since the epoch.
+    pub tripped_at_ms: i64,
+}
+
+impl fmt::Display for Tripped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match Utc.timestamp_millis_opt(self.tripped_at_ms).single() {
+            Some(at) => write!(f, "{} (tripped {})", self.reason, at.to_rfc3339()),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+/// The state file could not be read or written.
+#[derive(Debug)]
+pub struct StateFileError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for StateFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "kill switch state file {}: {}", self.path.display(), self.reason)
+    }
+}
+
+impl std::error::Error for StateFileError {}
+
+pub struct KillSwitch {
+    config: KillSwitchConfig,
+    tripped: Option<Tripped>,
+    consecutive_rejections: u32,
+    /// UTC day and total PnL when it was first seen, the daily-loss baseline.
+    day_start: Option<(NaiveDate, f64)>,
+    /// Feed name to when it went down; absent while connected.
+    down_since_ms: HashMap<String, i64>,
+    /// Watched market-data feed to when it last delivered data.
+    last_data_ms: HashMap<String, i64>,
+}
+
+impl KillSwitch {
+    /// Load the persisted state, if any.
+    pub fn load(config: &KillSwitchConfig) -> Result<Self, StateFileError> {
+        let mut switch = Self {
+            config: config.clone(),
+            tripped: None,
+            consecutive_rejections: 0,
+            day_start: None,
+            down_since_ms: HashMap::new(),
+            last_data_ms: HashMap::new(),
+        };
+        switch.tripped = switch.read_state()?;
+        Ok(switch)
+    }
+
+    fn state_error(&self, reason: impl fmt::Display) -> StateFileError {
+        StateFileError { path: self.config.state_file.clone(), reason: reason.to_string() }
+    }
+
+    fn read_state(&self) -> Result<Option<Tripped>, StateFileError> {
+        match std::fs::read_to_string(&self.config.state_file) {
+            Ok(text) => serde_json::from_str(&text).map(Some).map_err(|e| self.state_error(e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(self.state_error(e)),
+        }
+    }
+
+    pub fn tripped(&self) -> Option<&Tripped> {
+        self.tripped.as_ref()
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.tripped.is_some()
+    }
+
+    /// Trip and persist. `Ok(false)` if already tripped; an error means the
+    /// switch tripped but the state could not be saved.
+    pub fn trip(&mut self, reason: TripReason, now_ms: i64) -> Result<bool, StateFileError> {
+        if self.tripped.is_some() {
+            return Ok(false);
+        }
+        let tripped = Tripped { reason, tripped_at_ms: now_ms };
+        let json = serde_json::to_string_pretty(&tripped).expect("state serializes");
+        self.tripped = Some(tripped);
+        std::fs::write(&self.config.state_file, json).map_err(|e| self.state_error(e))?;
+        Ok(true)
+    }
+
+    /// Clear the tripped state and its file.
+    pub fn reset(&mut self) -> Result<(), StateFileError> {
+        self.tripped = None;
+        self.consecutive_rejections = 0;
+        match std::fs::remove_file(&self.config.state_file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(self.state_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Pick up a trip another process wrote to the state file; `true` if
+    /// this newly tripped the switch.
+    pub fn poll_state_file(&mut self) -> Result<bool, StateFileError> {
+        if self.tripped.is_some() {
+            return Ok(false);
+        }
+        self.tripped = self.read_state()?;
+        Ok(self.tripped.is_some())
+    }
+
+    pub fn on_order_accepted(&mut self) {
+        self.consecutive_rejections = 0;
+    }
+
+    pub fn on_order_rejected(&mut self) -> Option<TripReason> {
+        self.consecutive_rejections += 1;
+        let count = self.consecutive_rejections;
+        (count >= self.config.max_consecutive_rejections).then_some(TripReason::ConsecutiveRejections { count })
+    }
+
+    /// Compare `total_pnl` with where the UTC day started.
+    pub fn on_pnl(&mut self, total_pnl: f64, now_ms: i64) -> Option<TripReason> {
+        let today = Utc.timestamp_millis_opt(now_ms).single().map(|t| t.date_naive()).unwrap_or_default();
+        let baseline = match self.day_start {
+            Some((day, baseline)) if day == today => baseline,
+            _ => {
+                self.day_start = Some((today, total_pnl));
+                total_pnl
+            }
+        };
+        let loss = baseline - total_pnl;
+        match self.config.max_daily_loss {
+            Some(limit) if loss >= limit => Some(TripReason::DailyLoss { loss, limit }),
+            _ => None,
+        }
+    }
+
+    pub fn feed_up(&mut self, feed: &str) {
+        self.down_since_ms.remove(feed);
+    }
+
+    /// Note that `feed` lost its connection; the first report counts.
+    pub fn feed_down(&mut self, feed: &str, now_ms: i64) {
+        self.down_since_ms.entry(feed.to_string()).or_insert(now_ms);
+    }
+
+    /// Expect `feed` to keep delivering data; silence from `now_ms` on counts
+    /// as a loss even while connected, or while still connecting.
+    pub fn watch_feed(&mut self, feed: &str, now_ms: i64) {
+        self.last_data_ms.insert(feed.to_string(), now_ms);
+    }
+
+    /// Note data from a watched `feed`.
+    pub fn feed_data(&mut self, feed: &str, now_ms: i64) {
+        if let Some(last) = self.last_data_ms.get_mut(feed) {
+            *last = (*last).max(now_ms);
+        }
+    }
+
+    /// The feed down or silent longest, if that is beyond `feed_timeout_secs`.
+    pub fn check_feeds(&self, now_ms: i64) -> Option<TripReason> {
+        let timeout_ms = self.config.feed_timeout_secs as i64 * 1000;
+        self.down_since_ms
+            .iter()
+            .chain(&self.last_data_ms)
+            .filter(|(_, since)| now_ms - **since >= timeout_ms)
+            .min_by_key(|(_, since)| **since)
+            .map(|(feed, since)| TripReason::FeedLoss { feed: feed.clone(), down_secs: ((now_ms - since) / 1000) as u64 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str) -> KillSwitchConfig {
+        let dir = std::env::temp_dir().join(format!("kill-switch-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state_file = dir.join("state.json");
+        let _ = std::fs::remove_file(&state_file);
+        KillSwitchConfig {
+            max_daily_loss: Some(50.0),
+            max_consecutive_rejections: 3,
+            feed_timeout_secs: 10,
+            state_file,
+        }
+    }
+
+    #[test]
+    fn trips_on_rejections_and_survives_restart() {
+        let config = config("rejections");
+        let mut switch = KillSwitch::load(&config).unwrap();
+        assert_eq!(switch.on_order_rejected(), None);
+        switch.on_order_accepted();
+        assert_eq!(switch.on_order_rejected(), None);
+        assert_eq!(switch.on_order_rejected(), None);
+        let reason = switch.on_order_rejected().unwrap();
+        assert_eq!(reason, TripReason::ConsecutiveRejections { count: 3 });
+        assert!(switch.trip(reason.clone(), 4).unwrap());
+        assert!(!switch.trip(reason.clone(), 5).unwrap());
+
+        let mut restarted = KillSwitch::load(&config).unwrap();
+        assert_eq!(restarted.tripped(), Some(&Tripped { reason, tripped_at_ms: 4 }));
+        restarted.reset().unwrap();
+        assert!(!KillSwitch::load(&config).unwrap().is_tripped());
+    }
+
+    #[test]
+    fn trips_on_daily_loss_from_the_day_start() {
+        let mut switch = KillSwitch::load(&config("loss")).unwrap();
+        let day = 1_700_000_000_000;
+        assert_eq!(switch.on_pnl(20.0, day), None);
+        assert_eq!(switch.on_pnl(-25.0, day + 1_000), None);
+        // A new day resets the baseline.
+        assert_eq!(switch.on_pnl(-25.0, day + 86_400_000), None);
+        assert_eq!(switch.on_pnl(-70.0, day + 86_401_000), None);
+        assert_eq!(switch.on_pnl(-75.0, day + 86_402_000), Some(TripReason::DailyLoss { loss: 50.0, limit: 50.0 }));
+    }
+
+    #[test]
+    fn trips_on_feed_loss_and_manual_trigger() {
+        let config = config("feeds");
+        let mut running = KillSwitch::load(&config).unwrap();
+        running.feed_down("polymarket", 0);
+        running.feed_down("polymarket", 5_000);
+        running.feed_up("polymarket");
+        running.feed_down("binance", 1_000);
+        assert_eq!(running.check_feeds(10_000), None);
+        assert_eq!(
+            running.check_feeds(11_000),
+            Some(TripReason::FeedLoss { feed: "binance".to_string(), down_secs: 10 })
+        );
+
+        // Another process trips the switch through the state file.
+        assert!(!running.poll_state_file().unwrap());
+        let mut operator = KillSwitch::load(&config).unwrap();
+        operator.trip(TripReason::Manual { reason: "maintenance".to_string() }, 1).unwrap();
+        assert!(running.poll_state_file().unwrap());
+        assert_eq!(running.tripped().unwrap().reason, TripReason::Manual { reason: "maintenance".to_string() });
+    }
+
+    #[test]
+    fn trips_on_a_connected_but_silent_feed() {
+        let mut switch = KillSwitch::load(&config("silent")).unwrap();
+        switch.watch_feed("binance", 0);
+        switch.watch_feed("polymarket", 0);
+        // Both connect; only binance keeps sending.
+        switch.feed_up("binance");
+        switch.feed_up("polymarket");
+        switch.feed_data("polymarket", 2_000);
+        for now_ms in [4_000, 8_000, 12_000] {
+            switch.feed_data("binance", now_ms);
+        }
+        assert_eq!(switch.check_feeds(11_999), None);
+        assert_eq!(
+            switch.check_feeds(12_000),
+            Some(TripReason::FeedLoss { feed: "polymarket".to_string(), down_secs: 10 })
+        );
+
+        // Data that never starts, e.g. a hung connect, trips as well.
+        let mut switch = KillSwitch::load(&config("never")).unwrap();
+        switch.watch_feed("kraken", 1_000);
+        switch.feed_data("unwatched", 5_000);
+        assert_eq!(
+            switch.check_feeds(11_000),
+            Some(TripReason::FeedLoss { feed: "kraken".to_string(), down_secs: 10 })
+        );
+    }
+
+    #[test]
+    fn keeps_a_connected_but_idle_market_feed_alive_on_keepalive_replies() {
+        let mut switch = KillSwitch::load(&KillSwitchConfig { feed_timeout_secs: 30, ..config("idle") }).unwrap();
+        switch.watch_feed("polymarket", 0);
+        switch.feed_up("polymarket");
+        // No book changes for a minute, only a PONG for every PING.
+        let interval_ms = crate::polymarket::keepalive().interval.as_millis() as i64;
+        for now_ms in (0..=60_000).step_by(1_000) {
+            if now_ms % interval_ms == 0 {
+                switch.feed_data("polymarket", now_ms);
+            }
+            assert_eq!(switch.check_feeds(now_ms), None, "tripped at {}ms", now_ms);
+        }
+    }
+}
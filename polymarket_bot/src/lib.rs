@@ -0,0 +1,20 @@
+//! Library half of the latency bot: everything the `polymarket_bot` binary
+//! wires together lives here so it can be exercised without a network.
+
+pub mod account;
+pub mod backtest;
+pub mod config;
+pub mod engine;
+pub mod execution;
+pub mod feeds;
+pub mod kill_switch;
+pub mod metrics;
+pub mod oms;
+pub mod polymarket;
+pub mod positions;
+pub mod recorder;
+pub mod replay;
+pub mod risk;
+pub mod strategy;
+pub mod supervisor;
+pub mod sweep;
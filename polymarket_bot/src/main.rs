@@ -1,97 +1,934 @@
-use tokio::time::{interval, Duration};
-use futures_util::{StreamExt, SinkExt};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use url::Url;
-use serde_json::Value;
+use clap::{Parser, Subcommand};
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
 
-// Mocking the structures from the screenshot to make it compilable/understandable
-struct ClobClient {
-    host: String,
+use polymarket_bot::backtest;
+use polymarket_bot::config::{self, Settings, SignatureType};
+use polymarket_bot::engine::{Engine, OrderSubmission, Submission, Verdict};
+use polymarket_bot::execution::paper::PaperGateway;
+use polymarket_bot::execution::ExecutionGateway;
+use polymarket_bot::feeds::{self, FeedError, FeedEvent, WsStream};
+use polymarket_bot::kill_switch::{KillSwitch, TripReason, Tripped};
+use polymarket_bot::metrics::{self, Metrics};
+use polymarket_bot::oms::{Oms, OrderState};
+use polymarket_bot::polymarket;
+use polymarket_bot::polymarket::auth::{ApiCredentials, L2Auth};
+use polymarket_bot::polymarket::clob::{ClobClient, ClobError, Market, TokenParams};
+use polymarket_bot::polymarket::eip712::Wallet;
+use polymarket_bot::polymarket::market_ws::{self, BookEvent};
+use polymarket_bot::polymarket::order::{OrderBuilder, OrderError};
+use polymarket_bot::polymarket::user_ws::{self, UserEvent};
+use polymarket_bot::recorder::Recorder;
+use polymarket_bot::replay::{self, Pace, Replay};
+use polymarket_bot::supervisor::{self, Backoff, BackoffPolicy, StreamEvent};
+use polymarket_bot::sweep;
+
+#[derive(Parser)]
+#[command(about = "Polymarket latency arbitrage bot")]
+struct Cli {
+    /// Path to the YAML configuration (defaults to $LATENCY_BOT_CONFIG, then ./config.yaml)
+    #[arg(long, short, global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create CLOB API credentials for the configured private_key (or derive
+    /// the existing ones) and print them for the config file
+    ApiKey {
+        /// Only derive the credentials previously created under --nonce
+        #[arg(long)]
+        derive: bool,
+        #[arg(long, default_value_t = 0)]
+        nonce: u64,
+        /// Also append them as POLYMARKET_API_* variables to this env file (e.g. .env)
+        #[arg(long)]
+        save: Option<PathBuf>,
+    },
+    /// Cancel resting orders: by id, every order in a market, or all of them
+    #[command(group = clap::ArgGroup::new("target").required(true).args(["order_id", "market", "all"]))]
+    Cancel {
+        /// Order id to cancel (repeatable)
+        #[arg(long)]
+        order_id: Vec<String>,
+        /// Cancel every order in this market (condition id)
+        #[arg(long)]
+        market: Option<String>,
+        /// With --market, only cancel orders on this outcome token
+        #[arg(long, requires = "market")]
+        asset_id: Option<String>,
+        /// Cancel every resting order of the account
+        #[arg(long)]
+        all: bool,
+    },
+    /// Inspect, trip or reset the kill switch through its state file
+    KillSwitch {
+        #[command(subcommand)]
+        action: KillSwitchAction,
+    },
+    /// Capture the spot and Polymarket feeds to the recorder directory without trading
+    Record,
+    /// Run captured feeds through the strategy against a paper exchange
+    Replay {
+        /// Capture files, or directories of them, in the order to replay
+        #[arg(required = true)]
+        captures: Vec<PathBuf>,
+        /// Replay this many times faster than recorded
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+        /// Replay without waiting between frames
+        #[arg(long, conflicts_with = "speed")]
+        fast: bool,
+    },
+    /// Score captured feeds per market with the configured latencies and fees
+    Backtest {
+        /// Capture files, or directories of them, in the order to replay
+        #[arg(required = true)]
+        captures: Vec<PathBuf>,
+        /// Override `backtest.decision_to_exchange_ms`
+        #[arg(long)]
+        decision_to_exchange_ms: Option<u64>,
+        /// Override `backtest.exchange_to_ack_ms`
+        #[arg(long)]
+        exchange_to_ack_ms: Option<u64>,
+        /// Override `backtest.fee_rate_bps`
+        #[arg(long, value_parser = clap::value_parser!(u32).range(..=10_000))]
+        fee_rate_bps: Option<u32>,
+    },
+    /// Backtest the strategy parameters in the `sweep` settings and rank them
+    Sweep {
+        /// Capture sessions, each a file or a directory of them
+        #[arg(required = true)]
+        captures: Vec<PathBuf>,
+        /// Backtests to run at once; defaults to the number of CPUs
+        #[arg(long)]
+        jobs: Option<usize>,
+        /// Write the ranked results to this CSV file
+        #[arg(long)]
+        csv: Option<PathBuf>,
+        /// Write the ranked results to this JSON file
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum KillSwitchAction {
+    /// Show whether trading is halted and why
+    Status,
+    /// Halt trading; a running bot cancels its orders within a second
+    Trip {
+        #[arg(long)]
+        reason: String,
+    },
+    /// Allow trading again; a running bot stays halted until restarted
+    Reset,
+}
+
+/// `api-key`: obtain credentials with a wallet-signed request.
+async fn api_key_command(
+    settings: &Settings,
+    derive: bool,
+    nonce: u64,
+    save: Option<&Path>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let key = settings.private_key.as_ref().ok_or("private_key is required to create API credentials")?;
+    let wallet = Wallet::from_hex(key.expose())?;
+    let clob = ClobClient::new(&settings.polymarket_api_url);
+    let chain_id = settings.polygon_chain_id;
+    let credentials = if derive {
+        clob.derive_api_key(&wallet, chain_id, nonce).await?
+    } else {
+        clob.create_or_derive_api_key(&wallet, chain_id, nonce).await?
+    };
+    println!("# API credentials for {} (nonce {})", wallet.address(), nonce);
+    println!("api_key: \"{}\"", credentials.api_key);
+    println!("api_secret: \"{}\"", credentials.secret.expose());
+    println!("api_passphrase: \"{}\"", credentials.passphrase.expose());
+
+    if let Some(path) = save {
+        let mut options = std::fs::OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        writeln!(file, "POLYMARKET_API_KEY={}", credentials.api_key)?;
+        writeln!(file, "POLYMARKET_API_SECRET={}", credentials.secret.expose())?;
+        writeln!(file, "POLYMARKET_API_PASSPHRASE={}", credentials.passphrase.expose())?;
+        println!("Appended credentials to {}", path.display());
+    }
+    Ok(())
+}
+
+/// `cancel`: cancel resting orders with the configured API credentials.
+async fn cancel_command(
+    settings: &Settings,
+    order_ids: &[String],
+    market: Option<&str>,
+    asset_id: Option<&str>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let key = settings.private_key.as_ref().ok_or("private_key is required to cancel orders")?;
+    let credentials = ApiCredentials::from_settings(settings).ok_or("API credentials are required to cancel orders")?;
+    let address = Wallet::from_hex(key.expose())?.address();
+    let clob = ClobClient::new(&settings.polymarket_api_url).with_auth(L2Auth::new(address, credentials));
+    let response = match (order_ids, market) {
+        ([order_id], None) => clob.cancel_order(order_id).await?,
+        ([], Some(market)) => clob.cancel_market_orders(market, asset_id).await?,
+        ([], None) => clob.cancel_all().await?,
+        (_, None) => clob.cancel_orders(order_ids).await?,
+        (_, Some(_)) => return Err("--order-id cannot be combined with --market".into()),
+    };
+    println!("{}", response);
+    Ok(())
+}
+
+/// `kill-switch`: read or change the persisted kill switch state.
+fn kill_switch_command(settings: &Settings, action: KillSwitchAction) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut switch = KillSwitch::load(&settings.kill_switch)?;
+    match action {
+        KillSwitchAction::Status => match switch.tripped() {
+            Some(tripped) => println!("Tripped: {}", tripped),
+            None => println!("Not tripped"),
+        },
+        KillSwitchAction::Trip { reason } => {
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            if !switch.trip(TripReason::Manual { reason }, now_ms)? {
+                println!("Already tripped: {}", switch.tripped().expect("tripped"));
+            } else {
+                println!("Tripped; state written to {}", settings.kill_switch.state_file.display());
+            }
+        }
+        KillSwitchAction::Reset => {
+            switch.reset()?;
+            println!("Kill switch reset");
+        }
+    }
+    Ok(())
+}
+
+/// How often `record` logs how much it has captured.
+const RECORD_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `record`: capture the market data the bot would trade on, until stopped.
+async fn record_command(settings: &Settings) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let recorder = Recorder::start(&settings.recorder)?;
+    let (mut poly_stream, _poly_task) = supervisor::spawn_recorded(
+        "polymarket",
+        BackoffPolicy::default(),
+        polymarket::keepalive(),
+        market_connector(settings),
+        Some(recorder.clone()),
+    );
+    let mut spot_stream =
+        feeds::spawn_all(&feeds::from_settings(settings), BackoffPolicy::default(), Some(&recorder));
+    let mut report = tokio::time::interval(RECORD_REPORT_INTERVAL);
+    report.tick().await;
+    let shutdown_requested = shutdown_signal();
+    tokio::pin!(shutdown_requested);
+    println!("Recording to {}; stop with Ctrl-C", settings.recorder.directory.display());
+
+    loop {
+        tokio::select! {
+            Some(event) = spot_stream.next() => {
+                if let FeedEvent::Connection { venue, event } = event {
+                    log_connection_event(venue, &event);
+                }
+            }
+            Some(event) = poly_stream.recv() => {
+                if !matches!(event, StreamEvent::Frame(_)) {
+                    log_connection_event("polymarket", &event);
+                }
+            }
+            _ = report.tick() => {
+                println!("Recorded {} frames ({} dropped)", recorder.recorded(), recorder.dropped());
+            }
+            signal = &mut shutdown_requested => {
+                println!("Received {}; stopping", signal);
+                break;
+            }
+        }
+    }
+    finish_recording(&recorder);
+    Ok(())
+}
+
+/// Write out what is still buffered and report the totals.
+fn finish_recording(recorder: &Recorder) {
+    recorder.flush();
+    println!("Recorded {} frames", recorder.recorded());
+    if recorder.dropped() > 0 {
+        eprintln!("Recorder dropped {} frames it could not keep up with", recorder.dropped());
+    }
+}
+
+/// `replay`: run capture files through the strategy and report the outcome.
+async fn replay_command(
+    settings: &Settings,
+    captures: &[PathBuf],
+    pace: Pace,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let frames = replay::read_captures(captures)?;
+    println!("Replaying {} captured frames", frames.len());
+    let report = Replay::new(settings)?.with_pace(pace).run(&frames).await;
+
+    let mut verdicts: BTreeMap<String, u64> = BTreeMap::new();
+    for decision in &report.decisions {
+        let verdict = match &decision.verdict {
+            Verdict::Rejected(rejection) => format!("rejected ({})", rejection.kind()),
+            verdict => verdict.kind().to_string(),
+        };
+        *verdicts.entry(verdict).or_default() += 1;
+    }
+    println!("Replayed {} frames ({} from unused sources)", report.frames, report.ignored);
+    println!("{} signals, {} fills", report.decisions.len(), report.fills.len());
+    for (verdict, count) in verdicts {
+        println!("  {}: {}", verdict, count);
+    }
+    for (market, pnl) in &report.pnl {
+        println!("PnL {}: realized {:.4}, unrealized {:.4}", market, pnl.realized, pnl.unrealized);
+    }
+    println!("PnL total: {:.4}", report.total.total());
+    Ok(())
+}
+
+/// `backtest`: replay capture files with order latency and fees, and score
+/// every market.
+async fn backtest_command(settings: &Settings, captures: &[PathBuf]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let frames = replay::read_captures(captures)?;
+    let latency = &settings.backtest;
+    println!(
+        "Backtesting {} captured frames: {}ms to the exchange, {}ms to the ack, {} bps taker fee",
+        frames.len(),
+        latency.decision_to_exchange_ms,
+        latency.exchange_to_ack_ms,
+        latency.fee_rate_bps
+    );
+    let report = backtest::run(settings, &frames).await?;
+
+    println!(
+        "{:<20} {:>7} {:>6} {:>6} {:>8} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "market", "signals", "orders", "filled", "hit rate", "fees", "realized", "unrealized", "total", "drawdown"
+    );
+    let rows = report.markets.iter().map(|(market, r)| (market.as_str(), r));
+    for (market, r) in rows.chain([("total", &report.total)]) {
+        let hit_rate = r.hit_rate().map_or("-".to_string(), |rate| format!("{:.1}%", rate * 100.0));
+        println!(
+            "{:<20} {:>7} {:>6} {:>6} {:>8} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.4}",
+            market,
+            r.signals,
+            r.orders,
+            r.filled,
+            hit_rate,
+            r.fees,
+            r.pnl.realized,
+            r.pnl.unrealized,
+            r.pnl.total(),
+            r.max_drawdown
+        );
+    }
+    println!("Backtested {} frames", report.frames);
+    Ok(())
 }
 
-impl ClobClient {
-    async fn new(host: &str) -> Result<Self, Box<dyn Error>> {
-        Ok(Self { host: host.to_string() })
+/// Parameter sets `sweep` prints; the files get all of them.
+const SWEEP_TOP: usize = 10;
+
+/// `sweep`: backtest every parameter set on every capture session and rank them.
+async fn sweep_command(
+    settings: &Settings,
+    captures: &[PathBuf],
+    jobs: usize,
+    csv: Option<&Path>,
+    json: Option<&Path>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let sessions = captures
+        .iter()
+        .map(|capture| replay::read_captures(std::slice::from_ref(capture)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let grid = sweep::Grid::new(&settings.sweep);
+    let params: Vec<String> = grid.params().map(|p| p.to_string()).collect();
+    println!(
+        "Sweeping {} of {} parameter sets ({}) over {} sessions, {} at a time",
+        settings.sweep.samples.map_or(grid.combinations(), |n| n.min(grid.combinations())),
+        grid.combinations(),
+        if params.is_empty() { "none configured".to_string() } else { params.join(", ") },
+        sessions.len(),
+        jobs
+    );
+    let results = sweep::run(settings, sessions, jobs).await?;
+
+    if let Some(path) = csv {
+        let mut out = BufWriter::new(File::create(path)?);
+        sweep::write_csv(&results, &mut out)?;
+        out.flush()?;
+        println!("Wrote {} ranked parameter sets to {}", results.len(), path.display());
     }
-    
-    // Placeholder for order placement
-    async fn place_order(&self, side: &str, size: f64) {
-        println!("Placing {} order of size {}", side, size);
+    if let Some(path) = json {
+        let mut out = BufWriter::new(File::create(path)?);
+        sweep::write_json(&results, &mut out)?;
+        out.flush()?;
+        println!("Wrote {} ranked parameter sets to {}", results.len(), path.display());
     }
+    println!("{:>4} {:>10} {:>8} {:>6} {:>10}  parameters", "rank", "pnl", "sharpe", "trades", "drawdown");
+    for (rank, result) in results.iter().take(SWEEP_TOP).enumerate() {
+        let sharpe = result.sharpe.map_or("-".to_string(), |s| format!("{:.3}", s));
+        let params: Vec<String> = result.params.iter().map(|(param, value)| format!("{}={}", param, value)).collect();
+        println!(
+            "{:>4} {:>10.4} {:>8} {:>6} {:>10.4}  {}",
+            rank + 1,
+            result.pnl,
+            sharpe,
+            result.trades,
+            result.max_drawdown,
+            params.join(" ")
+        );
+    }
+    Ok(())
 }
 
-async fn connect_binance_ws(symbol: &str) -> Result<tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>, Box<dyn Error>> {
-    // Using Binance.US or Coinbase URL in reality, but keeping function signature
-    let url = format!("wss://stream.binance.us:9443/ws/{}usdt@trade", symbol.to_lowercase());
-    let (ws_stream, _) = connect_async(url).await?;
-    println!("Connected to Binance/Exchange WS for {}", symbol);
-    Ok(ws_stream)
+/// Connects to the market channel for every configured token.
+fn market_connector(settings: &Settings) -> impl supervisor::Connect {
+    let url = settings.polymarket_ws_url.clone();
+    let asset_ids: Vec<String> =
+        settings.markets.iter().flat_map(|m| [m.yes_token_id.clone(), m.no_token_id.clone()]).collect();
+    move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+        let (url, asset_ids) = (url.clone(), asset_ids.clone());
+        Box::pin(async move { market_ws::connect_market_ws(&url, &asset_ids).await })
+    }
+}
+
+/// Log clock skew against the CLOB and fetch each token's trading parameters.
+async fn check_clob(clob: &ClobClient, token_ids: &[String]) -> Result<HashMap<String, TokenParams>, ClobError> {
+    let skew = chrono::Utc::now().timestamp() - clob.server_time().await?;
+    println!("CLOB reachable at {} (local clock skew {}s)", clob.host(), skew);
+    let mut params = HashMap::new();
+    for token_id in token_ids {
+        let p = clob.token_params(token_id).await?;
+        println!(
+            "Token {}: tick size {}, neg risk {}, fee rate {} bps",
+            token_id, p.tick_size, p.neg_risk, p.fee_rate_bps
+        );
+        params.insert(token_id.clone(), p);
+    }
+    Ok(params)
+}
+
+/// How long shutdown waits for in-flight order submissions and for the
+/// cancel-all request before exiting anyway.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Resolves with the signal's name on SIGINT (Ctrl-C) or, on unix, SIGTERM.
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                eprintln!("Cannot listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
 }
 
-async fn connect_poly_ws() -> Result<tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>, Box<dyn Error>> {
-    let url = "wss://ws-subscriptions-clob.polymarket.com/ws/market";
-    let (ws_stream, _) = connect_async(url).await?;
-    println!("Connected to Polymarket WS");
-    Ok(ws_stream)
+/// Let in-flight submissions land, then cancel every resting order so
+/// nothing is left on the book once the process exits.
+async fn shutdown(engine: &mut Engine, mut in_flight: JoinSet<Submission>) {
+    let drained = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+        while let Some(joined) = in_flight.join_next().await {
+            if let Ok(submission) = joined {
+                engine.on_submission(submission, chrono::Utc::now().timestamp_millis());
+            }
+        }
+    });
+    if drained.await.is_err() {
+        eprintln!("{} order submissions still pending; cancelling anyway", in_flight.len());
+        in_flight.abort_all();
+    }
+    let gateway = Arc::clone(engine.gateway());
+    cancel_all(gateway.as_ref(), engine.oms_mut(), "shutdown").await;
+}
+
+/// Cancel every resting order of the account, if it is authenticated.
+async fn cancel_all(gateway: &dyn ExecutionGateway, oms: &mut Oms, context: &str) {
+    if !gateway.is_authenticated() {
+        return;
+    }
+    match tokio::time::timeout(SHUTDOWN_TIMEOUT, gateway.cancel_all()).await {
+        Ok(Ok(response)) => {
+            oms.on_cancel(&response, chrono::Utc::now().timestamp_millis());
+            println!("Cancelled open orders: {}", response);
+        }
+        Ok(Err(e)) => eprintln!("Cancel-all on {} failed: {}", context, e),
+        Err(_) => eprintln!("Cancel-all on {} timed out after {:?}", context, SHUTDOWN_TIMEOUT),
+    }
+}
+
+/// How often feed outages, daily loss and the state file are checked.
+const KILL_SWITCH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Trip the kill switch unless it already is, then halt.
+async fn trip_kill_switch(
+    kill_switch: &mut KillSwitch,
+    reason: TripReason,
+    gateway: &dyn ExecutionGateway,
+    oms: &mut Oms,
+    metrics: &Metrics,
+) {
+    match kill_switch.trip(reason, chrono::Utc::now().timestamp_millis()) {
+        Ok(false) => return,
+        Ok(true) => {}
+        // Tripped in memory all the same.
+        Err(e) => eprintln!("{}", e),
+    }
+    if let Some(tripped) = kill_switch.tripped() {
+        halt(tripped, gateway, oms, metrics).await;
+    }
+}
+
+/// Stop trading after the kill switch tripped: cancel everything resting.
+async fn halt(tripped: &Tripped, gateway: &dyn ExecutionGateway, oms: &mut Oms, metrics: &Metrics) {
+    eprintln!("KILL SWITCH TRIPPED: {}; halting trading until `polymarket_bot kill-switch reset`", tripped);
+    metrics.inc("kill_switch_trips_total", &[("reason", tripped.reason.kind())]);
+    metrics.set("kill_switch_tripped", &[], 1.0);
+    cancel_all(gateway, oms, "kill switch").await;
+}
+
+/// Note connection losses and recoveries of `feed` for the kill switch.
+fn track_feed(kill_switch: &mut KillSwitch, feed: &str, event: &StreamEvent) {
+    match event {
+        StreamEvent::Connected { .. } => kill_switch.feed_up(feed),
+        StreamEvent::Disconnected { .. } | StreamEvent::Retrying { .. } => {
+            kill_switch.feed_down(feed, chrono::Utc::now().timestamp_millis())
+        }
+        StreamEvent::Frame(_) => {}
+    }
+}
+
+/// Failures are already reported by the supervisor; only note recoveries here.
+fn log_connection_event(source: &str, event: &StreamEvent) {
+    if let StreamEvent::Connected { reconnects } = event {
+        if *reconnects > 0 {
+            println!("{} reconnected (reconnect #{})", source, reconnects);
+        }
+    }
+}
+
+/// Next event from a stream that may not have been started; never resolves
+/// when there is none.
+async fn recv_optional<T>(stream: &mut Option<mpsc::Receiver<T>>) -> Option<T> {
+    match stream {
+        Some(stream) => stream.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// [`recv_optional`] for the paper exchange's unbounded event channel.
+async fn recv_paper(events: &mut Option<mpsc::UnboundedReceiver<UserEvent>>) -> Option<UserEvent> {
+    match events {
+        Some(events) => events.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Signs paper orders when no private_key is configured; nothing checks the
+/// signatures, so a throwaway key will do.
+fn paper_order_builder(settings: &Settings) -> Result<OrderBuilder, OrderError> {
+    let key: [u8; 32] = rand::random();
+    OrderBuilder::new(Wallet::from_hex(&hex::encode(key))?, settings.polygon_chain_id, SignatureType::Eoa, None)
+}
+
+/// How often PnL is logged and held markets are checked for resolution.
+const PNL_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Look up `market_id` in the background and hand it back if it has resolved.
+fn spawn_resolution_check(clob: &ClobClient, market_id: String, tx: &mpsc::Sender<Market>) {
+    let (clob, tx) = (clob.clone(), tx.clone());
+    tokio::spawn(async move {
+        match clob.market(&market_id).await {
+            Ok(market) if market.closed && market.tokens.iter().any(|t| t.winner) => {
+                let _ = tx.send(market).await;
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Resolution check for {} failed: {}", market_id, e),
+        }
+    });
+}
+
+/// Fetch a fresh `/book` snapshot for `token_id` in the background, retrying
+/// with backoff, and hand it back to the main loop, which owns the books.
+fn spawn_resync(clob: &ClobClient, token_id: String, tx: &mpsc::Sender<BookEvent>) {
+    let (clob, tx) = (clob.clone(), tx.clone());
+    tokio::spawn(async move {
+        let mut backoff = Backoff::new(BackoffPolicy::default());
+        loop {
+            match clob.book(&token_id).await {
+                Ok(snapshot) => {
+                    let _ = tx.send(snapshot).await;
+                    return;
+                }
+                Err(e) => {
+                    let delay = backoff.next_delay();
+                    eprintln!("Book snapshot for {} failed: {}; retrying in {:?}", token_id, e, delay);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    });
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    // 1. Initialize CLOB Client (Order Execution)
-    // "Zero-allocation hot paths" as per screenshot philosophy
-    let clob = ClobClient::new("https://clob.polymarket.com").await?;
+async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    dotenv::dotenv().ok();
+    let cli = Cli::parse();
+    let config_path = config::resolve_path(cli.config.as_deref());
+    let settings: Settings = match config::load_settings(&config_path) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    println!("Loaded configuration from {} ({} markets)", config_path.display(), settings.markets.len());
 
-    // 2. Connect to Polymarket Data Stream
-    let mut poly_stream = connect_poly_ws().await?;
+    if let Some(Command::ApiKey { derive, nonce, save }) = cli.command {
+        if let Err(e) = api_key_command(&settings, derive, nonce, save.as_deref()).await {
+            eprintln!("api-key failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Cancel { order_id, market, asset_id, .. }) = cli.command {
+        if let Err(e) = cancel_command(&settings, &order_id, market.as_deref(), asset_id.as_deref()).await {
+            eprintln!("cancel failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::KillSwitch { action }) = cli.command {
+        if let Err(e) = kill_switch_command(&settings, action) {
+            eprintln!("kill-switch failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Replay { captures, speed, fast }) = &cli.command {
+        if speed.is_nan() || *speed <= 0.0 {
+            eprintln!("--speed must be positive");
+            std::process::exit(2);
+        }
+        let pace = if *fast { Pace::AsFastAsPossible } else { Pace::Recorded { speed: *speed } };
+        if let Err(e) = replay_command(&settings, captures, pace).await {
+            eprintln!("replay failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Backtest { captures, decision_to_exchange_ms, exchange_to_ack_ms, fee_rate_bps }) = &cli.command {
+        let mut settings = settings.clone();
+        let overrides = &mut settings.backtest;
+        overrides.decision_to_exchange_ms = decision_to_exchange_ms.unwrap_or(overrides.decision_to_exchange_ms);
+        overrides.exchange_to_ack_ms = exchange_to_ack_ms.unwrap_or(overrides.exchange_to_ack_ms);
+        overrides.fee_rate_bps = fee_rate_bps.unwrap_or(overrides.fee_rate_bps);
+        if let Err(e) = backtest_command(&settings, captures).await {
+            eprintln!("backtest failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Sweep { captures, jobs, csv, json }) = &cli.command {
+        let jobs = jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+        if jobs == 0 {
+            eprintln!("--jobs must be at least 1");
+            std::process::exit(2);
+        }
+        if let Err(e) = sweep_command(&settings, captures, jobs, csv.as_deref(), json.as_deref()).await {
+            eprintln!("sweep failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Record) = cli.command {
+        if let Err(e) = record_command(&settings).await {
+            eprintln!("record failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let spot_feeds = feeds::from_settings(&settings);
+
+    let asset_ids: Vec<String> = settings
+        .markets
+        .iter()
+        .flat_map(|m| [m.yes_token_id.clone(), m.no_token_id.clone()])
+        .collect();
 
-    // 3. Connect to Spot Price Stream (Binance/Coinbase)
-    let mut binance_stream = connect_binance_ws("BTC").await?;
+    // 1. CLOB client and order signing; the engine owns everything after that
+    let mut orders = OrderBuilder::from_settings(&settings)?;
+    let mut clob = ClobClient::new(&settings.polymarket_api_url);
+    match &orders {
+        Some(orders) => {
+            println!("Signing orders as {} for funder {}", orders.signer(), orders.funder());
+            if let Some(credentials) = ApiCredentials::from_settings(&settings) {
+                clob = clob.with_auth(L2Auth::new(orders.signer(), credentials));
+            }
+        }
+        None if settings.paper_trading => orders = Some(paper_order_builder(&settings)?),
+        None => return Err("private_key is required to trade live; set paper_trading: true to simulate".into()),
+    }
+    let token_params = check_clob(&clob, &asset_ids).await.unwrap_or_else(|e| {
+        eprintln!("CLOB check failed: {}", e);
+        HashMap::new()
+    });
+    if clob.is_authenticated() {
+        match clob.open_orders(None, None).await {
+            Ok(open) => println!("API credentials accepted; {} open orders", open.len()),
+            Err(e) => eprintln!("API credentials check failed: {}", e),
+        }
+    }
+
+    // Raw frames of the market data streams, for replay and backtests
+    let recorder = match settings.recorder.enabled {
+        true => Some(Recorder::start(&settings.recorder)?),
+        false => None,
+    };
+
+    // 2. Connect to Polymarket Data Stream (reconnects on its own)
+    let (mut poly_stream, _poly_task) = supervisor::spawn_recorded(
+        "polymarket",
+        BackoffPolicy::default(),
+        polymarket::keepalive(),
+        market_connector(&settings),
+        recorder.clone(),
+    );
+
+    // Positions and PnL, published on metrics_port and logged periodically.
+    let metrics = Metrics::new();
+    match tokio::net::TcpListener::bind(("0.0.0.0", settings.metrics_port)).await {
+        Ok(listener) => {
+            println!("Serving metrics on port {}", settings.metrics_port);
+            tokio::spawn(metrics::serve(listener, metrics.clone()));
+        }
+        Err(e) => eprintln!("Cannot serve metrics on port {}: {}", settings.metrics_port, e),
+    }
+    let mut pnl_report = tokio::time::interval(PNL_REPORT_INTERVAL);
 
-    let mut last_binance_price = 0.0;
-    let mut last_poly_odds = 0.5;
+    // Orders go to the CLOB, or to a paper exchange that fills them against
+    // the local books and reports back like the user channel.
+    let (engine, mut paper_events) = if settings.paper_trading {
+        println!("Paper trading: orders fill against the local books and never reach the CLOB");
+        let (paper, events) = PaperGateway::new();
+        (Engine::paper(&settings, paper), Some(events))
+    } else {
+        let owner = clob.credentials().map(|c| c.api_key.clone()).unwrap_or_default();
+        (Engine::new(&settings, Arc::new(clob.clone()), owner), None)
+    };
+    let mut engine = engine.with_orders(orders).with_token_params(token_params).with_metrics(metrics.clone());
+    let gateway = Arc::clone(engine.gateway());
+
+    // 2b. Our own orders and fills, when the account has API credentials
+    let mut user_stream = clob.credentials().cloned().filter(|_| paper_events.is_none()).map(|credentials| {
+        let url = settings.polymarket_ws_url.clone();
+        let markets: Vec<String> = settings.markets.iter().map(|m| m.market_id.clone()).collect();
+        let connector = move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+            let (url, credentials, markets) = (url.clone(), credentials.clone(), markets.clone());
+            Box::pin(async move { user_ws::connect_user_ws(&url, &credentials, &markets).await })
+        };
+        supervisor::spawn("polymarket-user", BackoffPolicy::default(), polymarket::keepalive(), connector).0
+    });
+
+    // Books that diverge from the exchange are rebuilt from REST snapshots.
+    let (resync_tx, mut resync_rx) = mpsc::channel(64);
+
+    // A switch tripped before a restart keeps the bot halted.
+    let mut kill_switch = KillSwitch::load(&settings.kill_switch)?;
+    // Market data has to keep flowing, even over a socket that looks healthy.
+    let started_ms = chrono::Utc::now().timestamp_millis();
+    for feed in &spot_feeds {
+        kill_switch.watch_feed(feed.venue(), started_ms);
+    }
+    kill_switch.watch_feed("polymarket", started_ms);
+    let mut kill_switch_check = tokio::time::interval(KILL_SWITCH_CHECK_INTERVAL);
+    metrics.set("kill_switch_tripped", &[], 0.0);
+    let (resolved_tx, mut resolved_rx) = mpsc::channel(16);
+
+    // 3. Connect to Spot Price Streams (Binance/Kraken/Coinbase), one per venue in use
+    let mut spot_stream = feeds::spawn_all(&spot_feeds, BackoffPolicy::default(), recorder.as_ref());
+
+    // Order submissions run in the background; shutdown waits for them.
+    let mut in_flight = JoinSet::new();
+    let shutdown_requested = shutdown_signal();
+    tokio::pin!(shutdown_requested);
+    if let Some(tripped) = kill_switch.tripped() {
+        halt(tripped, gateway.as_ref(), engine.oms_mut(), &metrics).await;
+    }
 
     println!("Bot started. Enforcing the edge...");
 
     loop {
         tokio::select! {
             // Handle Spot Price Updates
-            Some(msg) = binance_stream.next() => {
-                match msg {
-                    Ok(Message::Text(text)) => {
-                        if let Ok(data) = serde_json::from_str::<Value>(&text) {
-                            if let Some(price_str) = data.get("p") {
-                                if let Ok(price) = price_str.as_str().unwrap_or("0").parse::<f64>() {
-                                    
-                                    // Calculate Edge
-                                    if last_binance_price > 0.0 {
-                                        let change = (price - last_binance_price) / last_binance_price;
-                                        
-                                        // "Check if spot moved beyond threshold (e.g. 2%)"
-                                        if change.abs() > 0.002 {
-                                            println!("Edge detected! Change: {:.4}%", change * 100.0);
-                                            // Trigger Trade
-                                            if change > 0.0 {
-                                                clob.place_order("BUY_YES", 100.0).await;
-                                            } else {
-                                                clob.place_order("BUY_NO", 100.0).await;
-                                            }
-                                        }
-                                    }
-                                    last_binance_price = price;
-                                }
+            Some(event) = spot_stream.next() => {
+                let tick = match event {
+                    FeedEvent::Tick(tick) => tick,
+                    FeedEvent::Connection { venue, event } => {
+                        track_feed(&mut kill_switch, venue, &event);
+                        log_connection_event(venue, &event);
+                        continue;
+                    }
+                };
+                kill_switch.feed_data(tick.venue, tick.received_at_ms);
+                for submission in engine.on_spot_tick(&tick, kill_switch.is_tripped()).submissions {
+                    let gateway = Arc::clone(&gateway);
+                    in_flight.spawn(async move {
+                        let OrderSubmission { id, order, order_type, post_only } = submission;
+                        (id, gateway.post_order(&order, order_type, post_only).await)
+                    });
+                }
+            }
+
+            // Handle Polymarket Updates (to track stale odds)
+            Some(event) = poly_stream.recv() => {
+                match event {
+                    StreamEvent::Frame(text) => {
+                        let received_at_ms = chrono::Utc::now().timestamp_millis();
+                        // A quiet market still answers the keepalive PING, so a PONG counts too.
+                        kill_switch.feed_data("polymarket", received_at_ms);
+                        for (token_id, reason) in engine.on_market_frame(&text, received_at_ms) {
+                            eprintln!("Book for {} diverged: {}; fetching snapshot", token_id, reason);
+                            spawn_resync(&clob, token_id, &resync_tx);
+                        }
+                    }
+                    event @ StreamEvent::Disconnected { .. } => {
+                        track_feed(&mut kill_switch, "polymarket", &event);
+                        engine.on_market_disconnected();
+                    }
+                    event => {
+                        track_feed(&mut kill_switch, "polymarket", &event);
+                        log_connection_event("polymarket", &event);
+                    }
+                }
+            }
+
+            // Handle order updates and fills for the account
+            Some(event) = recv_optional(&mut user_stream) => {
+                match event {
+                    StreamEvent::Frame(text) => match user_ws::parse_message(&text) {
+                        Ok(events) => {
+                            let now_ms = chrono::Utc::now().timestamp_millis();
+                            for event in events {
+                                engine.on_user_event(event, now_ms);
                             }
+                            engine.report_pnl(false);
                         }
+                        Err(raw) => eprintln!("Unexpected user channel frame: {}", raw),
+                    },
+                    event => {
+                        track_feed(&mut kill_switch, "polymarket-user", &event);
+                        log_connection_event("polymarket-user", &event);
                     }
-                    _ => {}
                 }
             }
 
-            // Handle Polymarket Updates (to track stale odds)
-            Some(msg) = poly_stream.next() => {
-                 // Update internal order book state...
-                 // "book still thinks it's 50/50"
+            // Fills and order changes on the paper exchange
+            Some(event) = recv_paper(&mut paper_events) => {
+                engine.on_user_event(event, chrono::Utc::now().timestamp_millis());
+                engine.report_pnl(false);
+            }
+
+            // Log PnL and look for resolved markets we still hold
+            _ = pnl_report.tick() => {
+                engine.report_pnl(true);
+                for market_id in engine.account().positions().open_markets() {
+                    spawn_resolution_check(&clob, market_id.to_string(), &resolved_tx);
+                }
+            }
+
+            // Trip on feed outages, the daily loss or a trip from the CLI
+            _ = kill_switch_check.tick() => {
+                let now_ms = chrono::Utc::now().timestamp_millis();
+                match kill_switch.poll_state_file() {
+                    Ok(true) => {
+                        if let Some(tripped) = kill_switch.tripped() {
+                            halt(tripped, gateway.as_ref(), engine.oms_mut(), &metrics).await;
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => eprintln!("{}", e),
+                }
+                let total_pnl = engine.total_pnl();
+                let reason = kill_switch.check_feeds(now_ms).or_else(|| kill_switch.on_pnl(total_pnl, now_ms));
+                if let Some(reason) = reason {
+                    trip_kill_switch(&mut kill_switch, reason, gateway.as_ref(), engine.oms_mut(), &metrics).await;
+                }
+            }
+
+            Some(market) = resolved_rx.recv() => {
+                engine.on_resolution(&market);
+                engine.report_pnl(true);
+            }
+
+            // Apply REST snapshots for diverged books
+            Some(snapshot) = resync_rx.recv() => {
+                engine.apply_resync(&snapshot, chrono::Utc::now().timestamp_millis());
+            }
+
+            // Fold finished order submissions into the OMS
+            Some(joined) = in_flight.join_next(), if !in_flight.is_empty() => match joined {
+                Ok(submission) => match engine.on_submission(submission, chrono::Utc::now().timestamp_millis()) {
+                    Some(OrderState::Rejected) => {
+                        if let Some(reason) = kill_switch.on_order_rejected() {
+                            trip_kill_switch(&mut kill_switch, reason, gateway.as_ref(), engine.oms_mut(), &metrics)
+                                .await;
+                        }
+                    }
+                    Some(_) => kill_switch.on_order_accepted(),
+                    None => {}
+                },
+                Err(e) => eprintln!("Order submission task failed: {}", e),
+            },
+
+            signal = &mut shutdown_requested => {
+                println!("Received {}; shutting down", signal);
+                break;
             }
         }
     }
+
+    shutdown(&mut engine, in_flight).await;
+    if let Some(recorder) = &recorder {
+        finish_recording(recorder);
+    }
+    engine.report_pnl(true);
+    for (reason, count) in engine.risk().rejections() {
+        println!("Risk rejections ({}): {}", reason, count);
+    }
+    let open = engine.oms().open_orders().count();
+    if open > 0 {
+        eprintln!("{} orders not confirmed closed at exit", open);
+    }
+    println!("Shutdown complete");
+    Ok(())
 }
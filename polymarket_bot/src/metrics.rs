@@ -0,0 +1,145 @@
+//! Prometheus metrics on `metrics_port`.
+//!
+//! A [`Metrics`] handle is cheap to clone and shared by whatever records
+//! figures; [`serve`] answers every HTTP request on its listener with the
+//! current values in the Prometheus text format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Gauge,
+    Counter,
+}
+
+#[derive(Debug)]
+struct Family {
+    kind: Kind,
+    /// Rendered label set (`{a="1"}` or empty) to value.
+    samples: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    families: Arc<Mutex<BTreeMap<String, Family>>>,
+}
+
+fn label_set(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, name: &str, kind: Kind, labels: &[(&str, &str)], f: impl FnOnce(&mut f64)) {
+        let mut families = self.families.lock().unwrap();
+        let family = families.entry(name.to_string()).or_insert_with(|| Family { kind, samples: BTreeMap::new() });
+        f(family.samples.entry(label_set(labels)).or_insert(0.0));
+    }
+
+    /// Set a gauge.
+    pub fn set(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.update(name, Kind::Gauge, labels, |v| *v = value);
+    }
+
+    /// Add one to a counter.
+    pub fn inc(&self, name: &str, labels: &[(&str, &str)]) {
+        self.update(name, Kind::Counter, labels, |v| *v += 1.0);
+    }
+
+    /// Current value, mainly for tests and logs.
+    pub fn get(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        let families = self.families.lock().unwrap();
+        families.get(name)?.samples.get(&label_set(labels)).copied()
+    }
+
+    /// Every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let families = self.families.lock().unwrap();
+        let mut out = String::new();
+        for (name, family) in families.iter() {
+            let kind = match family.kind {
+                Kind::Gauge => "gauge",
+                Kind::Counter => "counter",
+            };
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (labels, value) in &family.samples {
+                let _ = writeln!(out, "{}{} {}", name, labels, value);
+            }
+        }
+        out
+    }
+}
+
+/// Answer HTTP requests on `listener` with `metrics` until the task is dropped.
+pub async fn serve(listener: TcpListener, metrics: Metrics) {
+    loop {
+        let Ok((mut tcp, _)) = listener.accept().await else { continue };
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            // The request itself does not matter; read its head and reply.
+            let mut buf = [0u8; 4096];
+            let mut head = Vec::new();
+            while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < 16 * 1024 {
+                match tcp.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => head.extend_from_slice(&buf[..n]),
+                }
+            }
+            let body = metrics.render();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/plain; version=0.0.4\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = tcp.write_all(response.as_bytes()).await;
+            let _ = tcp.shutdown().await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_gauges_and_counters() {
+        let metrics = Metrics::new();
+        metrics.set("pnl", &[("market", "0xabc")], 1.5);
+        metrics.set("pnl", &[("market", "0xabc")], 2.5);
+        metrics.inc("rejections_total", &[("reason", "say \"no\"")]);
+        metrics.inc("rejections_total", &[("reason", "say \"no\"")]);
+        metrics.set("up", &[], 1.0);
+        assert_eq!(metrics.get("pnl", &[("market", "0xabc")]), Some(2.5));
+        assert_eq!(
+            metrics.render(),
+            "# TYPE pnl gauge\npnl{market=\"0xabc\"} 2.5\n\
+             # TYPE rejections_total counter\nrejections_total{reason=\"say \\\"no\\\"\"} 2\n\
+             # TYPE up gauge\nup 1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_over_http() {
+        let metrics = Metrics::new();
+        metrics.set("up", &[], 1.0);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/metrics", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, metrics.clone()));
+        let body = reqwest::get(&url).await.unwrap().text().await.unwrap();
+        assert_eq!(body, "# TYPE up gauge\nup 1\n");
+    }
+}
@@ -0,0 +1,421 @@
+//! Order management: every order the bot creates, from the strategy's signal
+//! to a terminal state.
+//!
+//! ```text
+//! Created -> Signed -> Sent -> Acknowledged -> PartiallyFilled -> Filled
+//!    |          |        |          |                 |
+//!    +----------+--------+----------+-----------------+--> Cancelled / Rejected / Expired
+//! ```
+//!
+//! The `POST /order` response and the user channel report on the same order
+//! in no particular order, so both are folded in as evidence: the order is
+//! indexed by its id (the EIP-712 hash) as soon as it is signed, fills only
+//! ever move `size_matched` forward, and reports that an order has already
+//! moved past (a late `PLACEMENT`, a fill after a cancel) leave the state
+//! alone. Explicit transitions the state machine does not allow are errors.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::OrderType;
+use crate::polymarket::clob::{CancelResponse, OrderResponse, PlacementStatus};
+use crate::polymarket::user_ws::{Fill, OrderEvent, OrderEventType};
+use crate::strategy::{Side, TradeSignal};
+
+/// Shares below which an order counts as completely filled.
+const SIZE_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderState {
+    Created,
+    Signed,
+    Sent,
+    /// Accepted by the CLOB and resting (or waiting out the matching delay).
+    Acknowledged,
+    PartiallyFilled,
+    Filled,
+    /// Cancelled by us, or the unfilled rest of a FOK/FAK order killed.
+    Cancelled,
+    Rejected,
+    /// A GTD order reached its expiration.
+    Expired,
+}
+
+impl OrderState {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderState::Filled | OrderState::Cancelled | OrderState::Rejected | OrderState::Expired)
+    }
+
+    /// Position along the happy path; terminal states rank last.
+    fn rank(&self) -> u8 {
+        match self {
+            OrderState::Created => 0,
+            OrderState::Signed => 1,
+            OrderState::Sent => 2,
+            OrderState::Acknowledged => 3,
+            OrderState::PartiallyFilled => 4,
+            _ => 5,
+        }
+    }
+
+    pub fn can_transition(&self, to: OrderState) -> bool {
+        use OrderState::*;
+        match (self, to) {
+            (Created, Signed) | (Signed, Sent) => true,
+            // Signing failed, or the order was dropped before it was sent.
+            (Created | Signed, Rejected | Cancelled) => true,
+            (Sent, Acknowledged) => true,
+            (Sent | Acknowledged, PartiallyFilled) | (PartiallyFilled, PartiallyFilled) => true,
+            (Sent | Acknowledged | PartiallyFilled, Filled | Cancelled | Expired) => true,
+            (Sent, Rejected) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for OrderState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A transition the state machine does not allow.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OmsError {
+    UnknownOrder(u64),
+    InvalidTransition { id: u64, from: OrderState, to: OrderState },
+}
+
+impl fmt::Display for OmsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OmsError::UnknownOrder(id) => write!(f, "no order #{}", id),
+            OmsError::InvalidTransition { id, from, to } => write!(f, "order #{} cannot go from {} to {}", id, from, to),
+        }
+    }
+}
+
+impl std::error::Error for OmsError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManagedOrder {
+    /// Local id, assigned at creation.
+    pub id: u64,
+    /// CLOB order id, known once signed.
+    pub order_id: Option<String>,
+    pub market_id: String,
+    pub token_id: String,
+    pub side: Side,
+    pub price: f64,
+    pub size: f64,
+    pub order_type: OrderType,
+    /// Seconds since the epoch; 0 unless GTD.
+    pub expiration: u64,
+    pub state: OrderState,
+    pub size_matched: f64,
+    /// Last rejection or failure reason.
+    pub error: Option<String>,
+    /// Every state entered, with the time in ms.
+    pub history: Vec<(OrderState, i64)>,
+}
+
+impl ManagedOrder {
+    pub fn is_open(&self) -> bool {
+        !self.state.is_terminal()
+    }
+
+    pub fn remaining(&self) -> f64 {
+        (self.size - self.size_matched).max(0.0)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Oms {
+    orders: HashMap<u64, ManagedOrder>,
+    by_order_id: HashMap<String, u64>,
+    fills: Vec<(u64, Fill)>,
+    next_id: u64,
+}
+
+impl Oms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a new order for `signal`; returns its local id.
+    pub fn create(&mut self, signal: &TradeSignal, order_type: OrderType, expiration: u64, now_ms: i64) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.orders.insert(
+            id,
+            ManagedOrder {
+                id,
+                order_id: None,
+                market_id: signal.market_id.clone(),
+                token_id: signal.token_id.clone(),
+                side: signal.side,
+                price: signal.price,
+                size: signal.size,
+                order_type,
+                expiration,
+                state: OrderState::Created,
+                size_matched: 0.0,
+                error: None,
+                history: vec![(OrderState::Created, now_ms)],
+            },
+        );
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ManagedOrder> {
+        self.orders.get(&id)
+    }
+
+    pub fn by_order_id(&self, order_id: &str) -> Option<&ManagedOrder> {
+        self.by_order_id.get(order_id).and_then(|id| self.orders.get(id))
+    }
+
+    /// Orders not yet in a terminal state.
+    pub fn open_orders(&self) -> impl Iterator<Item = &ManagedOrder> {
+        self.orders.values().filter(|o| o.is_open())
+    }
+
+    /// Quote currency the unfilled part of `market_id`'s open orders commits.
+    pub fn open_notional(&self, market_id: &str) -> f64 {
+        self.open_orders().filter(|o| o.market_id == market_id).map(|o| o.remaining() * o.price).sum()
+    }
+
+    pub fn has_open_order(&self, token_id: &str) -> bool {
+        self.open_orders().any(|o| o.token_id == token_id)
+    }
+
+    /// Every fill received, in arrival order, with the local order id.
+    pub fn fills(&self) -> &[(u64, Fill)] {
+        &self.fills
+    }
+
+    /// Move order `id` to `to`, or fail if the state machine forbids it.
+    pub fn transition(&mut self, id: u64, to: OrderState, now_ms: i64) -> Result<(), OmsError> {
+        let order = self.orders.get_mut(&id).ok_or(OmsError::UnknownOrder(id))?;
+        if !order.state.can_transition(to) {
+            return Err(OmsError::InvalidTransition { id, from: order.state, to });
+        }
+        order.state = to;
+        order.history.push((to, now_ms));
+        Ok(())
+    }
+
+    pub fn signed(&mut self, id: u64, order_id: &str, now_ms: i64) -> Result<(), OmsError> {
+        self.transition(id, OrderState::Signed, now_ms)?;
+        self.orders.get_mut(&id).expect("transitioned").order_id = Some(order_id.to_string());
+        self.by_order_id.insert(order_id.to_string(), id);
+        Ok(())
+    }
+
+    /// Terminal failure before or at submission.
+    pub fn reject(&mut self, id: u64, reason: &str, now_ms: i64) -> Result<(), OmsError> {
+        self.transition(id, OrderState::Rejected, now_ms)?;
+        self.orders.get_mut(&id).expect("transitioned").error = Some(reason.to_string());
+        Ok(())
+    }
+
+    /// Fold in the `POST /order` response for order `id`.
+    pub fn on_response(&mut self, id: u64, response: &OrderResponse, now_ms: i64) -> Result<OrderState, OmsError> {
+        let order = self.orders.get(&id).ok_or(OmsError::UnknownOrder(id))?;
+        if let Some(error) = response.error() {
+            self.reject(id, error, now_ms)?;
+            return Ok(OrderState::Rejected);
+        }
+        let matched = match response.status {
+            Some(PlacementStatus::Matched) => {
+                // Buys receive shares; sells give them up.
+                let shares = match order.side {
+                    Side::Buy => response.taking_amount,
+                    Side::Sell => response.making_amount,
+                };
+                shares.unwrap_or(order.size)
+            }
+            _ => 0.0,
+        };
+        let killed = order.order_type.is_marketable() && response.status != Some(PlacementStatus::Delayed);
+        self.advance(id, OrderState::Acknowledged, now_ms);
+        self.record_matched(id, matched, now_ms);
+        let order = &self.orders[&id];
+        if killed && order.is_open() {
+            // FOK/FAK never rest: whatever did not match is gone.
+            self.advance(id, OrderState::Cancelled, now_ms);
+        }
+        Ok(self.orders[&id].state)
+    }
+
+    /// Fold in a user-channel order event; `None` if the order is not ours to
+    /// track (placed by another process or before a restart).
+    pub fn on_order_event(&mut self, event: &OrderEvent, now_ms: i64) -> Option<OrderState> {
+        let id = *self.by_order_id.get(&event.id)?;
+        match event.kind {
+            OrderEventType::Placement => self.advance(id, OrderState::Acknowledged, now_ms),
+            OrderEventType::Update => self.record_matched(id, event.size_matched, now_ms),
+            OrderEventType::Cancellation => {
+                self.record_matched(id, event.size_matched, now_ms);
+                let order = &self.orders[&id];
+                let expired = order.expiration > 0 && event.timestamp >= order.expiration as i64;
+                self.advance(id, if expired { OrderState::Expired } else { OrderState::Cancelled }, now_ms);
+            }
+        }
+        Some(self.orders[&id].state)
+    }
+
+    /// Record one of our fills from the user channel.
+    pub fn on_fill(&mut self, fill: &Fill, now_ms: i64) -> Option<OrderState> {
+        let id = *self.by_order_id.get(&fill.order_id)?;
+        self.fills.push((id, fill.clone()));
+        let total: f64 = self.fills.iter().filter(|(i, _)| *i == id).map(|(_, f)| f.size).sum();
+        self.record_matched(id, total, now_ms);
+        Some(self.orders[&id].state)
+    }
+
+    /// Fold in a cancel response: listed orders are cancelled.
+    pub fn on_cancel(&mut self, response: &CancelResponse, now_ms: i64) {
+        for order_id in &response.canceled {
+            if let Some(&id) = self.by_order_id.get(order_id) {
+                self.advance(id, OrderState::Cancelled, now_ms);
+            }
+        }
+    }
+
+    /// Raise `size_matched` to `total` (never lower it) and move the order
+    /// to partially filled or filled accordingly.
+    fn record_matched(&mut self, id: u64, total: f64, now_ms: i64) {
+        let order = self.orders.get_mut(&id).expect("known order");
+        if total <= order.size_matched {
+            return;
+        }
+        order.size_matched = total;
+        let to = if order.remaining() <= SIZE_EPSILON { OrderState::Filled } else { OrderState::PartiallyFilled };
+        self.advance(id, to, now_ms);
+    }
+
+    /// Apply `to` when it moves the order forward; reports it has already
+    /// moved past are ignored.
+    fn advance(&mut self, id: u64, to: OrderState, now_ms: i64) {
+        let state = self.orders[&id].state;
+        if state.rank() < to.rank() || (state == to && to == OrderState::PartiallyFilled) {
+            let _ = self.transition(id, to, now_ms);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polymarket::user_ws::TradeStatus;
+    use crate::strategy::Direction;
+
+    fn signal() -> TradeSignal {
+        TradeSignal {
+            market_id: "0xabc".to_string(),
+            token_id: "yes".to_string(),
+            side: Side::Buy,
+            direction: Direction::Up,
+            delta: 0.02,
+            spot_price: 100.0,
+            price: 0.5,
+            size: 10.0,
+            notional: 5.0,
+        }
+    }
+
+    fn sent(oms: &mut Oms, order_type: OrderType) -> u64 {
+        let id = oms.create(&signal(), order_type, 0, 0);
+        oms.signed(id, &format!("0x{}", id), 1).unwrap();
+        oms.transition(id, OrderState::Sent, 2).unwrap();
+        id
+    }
+
+    fn response(json: &str) -> OrderResponse {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn fill(order_id: &str, trade_id: &str, size: f64) -> Fill {
+        Fill {
+            trade_id: trade_id.to_string(),
+            market: "0xabc".to_string(),
+            order_id: order_id.to_string(),
+            asset_id: "yes".to_string(),
+            side: Side::Buy,
+            price: 0.5,
+            size,
+            fee: 0.0,
+            status: TradeStatus::Matched,
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_transitions() {
+        let mut oms = Oms::new();
+        let id = oms.create(&signal(), OrderType::Gtc, 0, 0);
+        assert_eq!(
+            oms.transition(id, OrderState::Sent, 1),
+            Err(OmsError::InvalidTransition { id, from: OrderState::Created, to: OrderState::Sent })
+        );
+        oms.reject(id, "tick size", 1).unwrap();
+        assert!(oms.transition(id, OrderState::Signed, 2).is_err());
+        assert_eq!(oms.transition(99, OrderState::Signed, 2), Err(OmsError::UnknownOrder(99)));
+        assert!(!oms.has_open_order("yes"));
+    }
+
+    #[test]
+    fn resting_order_fills_through_user_channel() {
+        let mut oms = Oms::new();
+        let id = sent(&mut oms, OrderType::Gtc);
+        assert!(oms.has_open_order("yes"));
+        let state = oms.on_response(id, &response(r#"{"success":true,"orderID":"0x1","status":"live"}"#), 3);
+        assert_eq!(state, Ok(OrderState::Acknowledged));
+
+        assert_eq!(oms.on_fill(&fill("0x1", "t1", 4.0), 4), Some(OrderState::PartiallyFilled));
+        assert_eq!(oms.on_fill(&fill("0x1", "t2", 6.0), 5), Some(OrderState::Filled));
+        let order = oms.get(id).unwrap();
+        assert_eq!(order.size_matched, 10.0);
+        let states: Vec<OrderState> = order.history.iter().map(|(s, _)| *s).collect();
+        use OrderState::*;
+        assert_eq!(states, [Created, Signed, Sent, Acknowledged, PartiallyFilled, Filled]);
+        assert_eq!(oms.fills().len(), 2);
+        assert_eq!(oms.open_orders().count(), 0);
+    }
+
+    #[test]
+    fn user_channel_may_beat_the_rest_response() {
+        let mut oms = Oms::new();
+        let id = sent(&mut oms, OrderType::Fak);
+        assert_eq!(oms.on_fill(&fill("0x1", "t1", 4.0), 3), Some(OrderState::PartiallyFilled));
+        // The response's match covers the same shares; the rest of a FAK is killed.
+        let matched = r#"{"success":true,"orderID":"0x1","status":"matched","makingAmount":"2","takingAmount":"4"}"#;
+        assert_eq!(oms.on_response(id, &response(matched), 4), Ok(OrderState::Cancelled));
+        assert_eq!(oms.get(id).unwrap().size_matched, 4.0);
+    }
+
+    #[test]
+    fn rejections_cancellations_and_expiry() {
+        let mut oms = Oms::new();
+        let rejected = sent(&mut oms, OrderType::Fok);
+        let not_filled = r#"{"success":false,"errorMsg":"order couldn't be fully filled"}"#;
+        assert_eq!(oms.on_response(rejected, &response(not_filled), 3), Ok(OrderState::Rejected));
+        assert_eq!(oms.get(rejected).unwrap().error.as_deref(), Some("order couldn't be fully filled"));
+
+        let cancelled = sent(&mut oms, OrderType::Gtc);
+        oms.on_cancel(&CancelResponse { canceled: vec!["0x2".to_string()], ..Default::default() }, 3);
+        assert_eq!(oms.get(cancelled).unwrap().state, OrderState::Cancelled);
+
+        let mut gtd = signal();
+        gtd.token_id = "no".to_string();
+        let id = oms.create(&gtd, OrderType::Gtd, 1_000, 0);
+        oms.signed(id, "0x3", 1).unwrap();
+        oms.transition(id, OrderState::Sent, 2).unwrap();
+        let event = serde_json::json!({
+            "id": "0x3", "market": "0xabc", "asset_id": "no", "side": "BUY", "price": "0.5",
+            "original_size": "10", "size_matched": "0", "type": "CANCELLATION", "timestamp": "1000"
+        });
+        let event: OrderEvent = serde_json::from_value(event).unwrap();
+        assert_eq!(oms.on_order_event(&event, 3), Some(OrderState::Expired));
+    }
+}
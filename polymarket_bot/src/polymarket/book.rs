@@ -0,0 +1,513 @@
+//! In-memory L2 order books for Polymarket outcome tokens.
+//!
+//! A book is seeded by a `book` snapshot and then kept current by
+//! `price_change` deltas, each of which carries the new aggregate size at one
+//! price level. Deltas that arrive before the first snapshot are dropped.
+//!
+//! After every delta the book is checked against what the exchange says it
+//! should look like: the `best_bid`/`best_ask` carried on the change, a
+//! crossed-book sanity check, and the exchange `hash` when our hash of a
+//! snapshot has been seen to match the exchange's for that token. A book that
+//! fails any check stops being served until it is rebuilt from a fresh
+//! snapshot, fetched from the CLOB `/book` endpoint by
+//! [`ClobClient::book`](super::clob::ClobClient::book).
+
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use super::market_ws::{BookEvent, MarketEvent, PriceChange, PriceLevel};
+use crate::strategy::Side;
+
+/// Tolerance when comparing our top of book with the exchange's.
+const PRICE_EPSILON: f64 = 1e-9;
+
+/// Prices are stored as integer micro-units so they can key a `BTreeMap`.
+/// Polymarket ticks are at most 1e-4, so this is exact.
+const PRICE_SCALE: f64 = 1_000_000.0;
+
+fn price_key(price: f64) -> u64 {
+    (price * PRICE_SCALE).round() as u64
+}
+
+fn key_price(key: u64) -> f64 {
+    key as f64 / PRICE_SCALE
+}
+
+/// Why a book had to be rebuilt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResyncReason {
+    HashMismatch { expected: String, local: String },
+    TopOfBookMismatch { side: Side, expected: Option<f64>, local: Option<f64> },
+    Crossed { bid: f64, ask: f64 },
+}
+
+impl fmt::Display for ResyncReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResyncReason::HashMismatch { expected, local } => {
+                write!(f, "hash mismatch (exchange {}, local {})", expected, local)
+            }
+            ResyncReason::TopOfBookMismatch { side, expected, local } => {
+                write!(f, "best {} mismatch (exchange {:?}, local {:?})", side, expected, local)
+            }
+            ResyncReason::Crossed { bid, ask } => write!(f, "crossed book (bid {} >= ask {})", bid, ask),
+        }
+    }
+}
+
+/// Result of applying one `price_change` entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeOutcome {
+    Applied,
+    /// No snapshot yet, or the book is waiting for a resync.
+    NotReady,
+    /// Older than the state the book already reflects.
+    Stale,
+    /// Applied, but the book no longer matches the exchange.
+    Diverged(ResyncReason),
+}
+
+/// L2 book of one outcome token.
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    pub asset_id: String,
+    pub market: String,
+    bids: BTreeMap<u64, f64>,
+    asks: BTreeMap<u64, f64>,
+    ready: bool,
+    /// Whether our hash of the last snapshot matched the exchange's, i.e.
+    /// whether exchange hashes on deltas can be checked for this token.
+    hash_verifiable: bool,
+    /// A REST snapshot has been requested and not yet applied.
+    resync_pending: bool,
+    /// Number of times this book has been rebuilt after diverging.
+    pub resyncs: u64,
+    /// Exchange timestamp (ms) of the last snapshot or delta applied.
+    pub exchange_ts_ms: i64,
+    /// Local receive time (ms) of the last snapshot or delta applied.
+    pub updated_at_ms: i64,
+    /// Hash the exchange sent with the last snapshot or delta.
+    pub last_hash: Option<String>,
+}
+
+impl OrderBook {
+    pub fn new(asset_id: impl Into<String>) -> Self {
+        Self { asset_id: asset_id.into(), ..Self::default() }
+    }
+
+    /// Whether a snapshot has been applied.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// Replace the whole book with `snapshot`.
+    pub fn apply_snapshot(&mut self, snapshot: &BookEvent, received_at_ms: i64) {
+        self.market = snapshot.market.clone();
+        self.bids = Self::levels(&snapshot.bids);
+        self.asks = Self::levels(&snapshot.asks);
+        self.ready = true;
+        self.resync_pending = false;
+        self.exchange_ts_ms = snapshot.timestamp;
+        self.updated_at_ms = received_at_ms;
+        self.last_hash = snapshot.hash.clone();
+        self.hash_verifiable = snapshot.hash.as_deref() == Some(self.hash(snapshot.timestamp).as_str());
+    }
+
+    /// Stop serving this book until the next snapshot, e.g. after a disconnect.
+    pub fn invalidate(&mut self) {
+        self.ready = false;
+    }
+
+    fn levels(levels: &[PriceLevel]) -> BTreeMap<u64, f64> {
+        levels.iter().filter(|l| l.size > 0.0).map(|l| (price_key(l.price), l.size)).collect()
+    }
+
+    /// Set the aggregate size at one level, then verify the result.
+    pub fn apply_change(&mut self, change: &PriceChange, exchange_ts_ms: i64, received_at_ms: i64) -> ChangeOutcome {
+        if !self.ready {
+            return ChangeOutcome::NotReady;
+        }
+        if exchange_ts_ms < self.exchange_ts_ms {
+            return ChangeOutcome::Stale;
+        }
+        let side = match change.side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        let key = price_key(change.price);
+        if change.size > 0.0 {
+            side.insert(key, change.size);
+        } else {
+            side.remove(&key);
+        }
+        self.exchange_ts_ms = exchange_ts_ms;
+        self.updated_at_ms = received_at_ms;
+        self.last_hash = change.hash.clone();
+        match self.verify(change, exchange_ts_ms) {
+            Some(reason) => {
+                self.ready = false;
+                ChangeOutcome::Diverged(reason)
+            }
+            None => ChangeOutcome::Applied,
+        }
+    }
+
+    fn verify(&self, change: &PriceChange, exchange_ts_ms: i64) -> Option<ResyncReason> {
+        let bid = self.best_bid().map(|l| l.price);
+        let ask = self.best_ask().map(|l| l.price);
+        if let (Some(bid), Some(ask)) = (bid, ask) {
+            if bid >= ask {
+                return Some(ResyncReason::Crossed { bid, ask });
+            }
+        }
+        for (side, expected, local) in [(Side::Buy, change.best_bid, bid), (Side::Sell, change.best_ask, ask)] {
+            let Some(expected) = expected else { continue };
+            // The exchange reports an empty side as 0 (bids) or 1 (asks).
+            let empty = (side == Side::Buy && expected <= 0.0) || (side == Side::Sell && expected >= 1.0);
+            let matches = match local {
+                Some(local) => (local - expected).abs() < PRICE_EPSILON,
+                None => empty,
+            };
+            if !matches {
+                return Some(ResyncReason::TopOfBookMismatch { side, expected: Some(expected), local });
+            }
+        }
+        if self.hash_verifiable {
+            if let Some(expected) = &change.hash {
+                let local = self.hash(exchange_ts_ms);
+                if *expected != local {
+                    return Some(ResyncReason::HashMismatch { expected: expected.clone(), local });
+                }
+            }
+        }
+        None
+    }
+
+    /// Hash of the book as the CLOB computes it for a summary: SHA-1 over the
+    /// compact JSON of the summary with an empty `hash`, bids ascending and
+    /// asks descending, as they appear on the wire.
+    pub fn hash(&self, timestamp: i64) -> String {
+        #[derive(Serialize)]
+        struct Level {
+            price: String,
+            size: String,
+        }
+        #[derive(Serialize)]
+        struct Summary<'a> {
+            market: &'a str,
+            asset_id: &'a str,
+            timestamp: String,
+            bids: Vec<Level>,
+            asks: Vec<Level>,
+            hash: &'a str,
+        }
+        let level = |(&k, &size): (&u64, &f64)| Level { price: key_price(k).to_string(), size: size.to_string() };
+        let summary = Summary {
+            market: &self.market,
+            asset_id: &self.asset_id,
+            timestamp: timestamp.to_string(),
+            bids: self.bids.iter().map(level).collect(),
+            asks: self.asks.iter().rev().map(level).collect(),
+            hash: "",
+        };
+        let json = serde_json::to_string(&summary).expect("summary serializes");
+        hex::encode(Sha1::digest(json.as_bytes()))
+    }
+
+    pub fn best_bid(&self) -> Option<PriceLevel> {
+        self.bids.iter().next_back().map(|(&k, &size)| PriceLevel { price: key_price(k), size })
+    }
+
+    pub fn best_ask(&self) -> Option<PriceLevel> {
+        self.asks.iter().next().map(|(&k, &size)| PriceLevel { price: key_price(k), size })
+    }
+
+    /// Best `levels` price levels of `side`, best first.
+    pub fn depth(&self, side: Side, levels: usize) -> Vec<PriceLevel> {
+        let level = |(&k, &size): (&u64, &f64)| PriceLevel { price: key_price(k), size };
+        match side {
+            Side::Buy => self.bids.iter().rev().take(levels).map(level).collect(),
+            Side::Sell => self.asks.iter().take(levels).map(level).collect(),
+        }
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_bid()?.price + self.best_ask()?.price) / 2.0)
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+}
+
+/// Books for every subscribed token, keyed on asset id.
+#[derive(Debug, Default)]
+pub struct BookStore {
+    books: HashMap<String, OrderBook>,
+}
+
+/// What applying one market event did to the store.
+#[derive(Debug, Default, PartialEq)]
+pub struct ApplyOutcome {
+    /// Asset ids whose book changed and is still trustworthy.
+    pub updated: Vec<String>,
+    /// Asset ids that diverged and need a fresh snapshot; each is reported
+    /// once until [`BookStore::apply_resync`] rebuilds it.
+    pub resync: Vec<(String, ResyncReason)>,
+}
+
+impl BookStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The book for `asset_id`, if it is currently trustworthy.
+    pub fn get(&self, asset_id: &str) -> Option<&OrderBook> {
+        self.books.get(asset_id).filter(|b| b.is_ready())
+    }
+
+    pub fn get_mut(&mut self, asset_id: &str) -> &mut OrderBook {
+        self.books.entry(asset_id.to_string()).or_insert_with(|| OrderBook::new(asset_id))
+    }
+
+    /// Apply a market-channel event.
+    pub fn apply(&mut self, event: &MarketEvent, received_at_ms: i64) -> ApplyOutcome {
+        let mut outcome = ApplyOutcome::default();
+        match event {
+            MarketEvent::Book(snapshot) => {
+                self.get_mut(&snapshot.asset_id).apply_snapshot(snapshot, received_at_ms);
+                outcome.updated.push(snapshot.asset_id.clone());
+            }
+            MarketEvent::PriceChange(event) => {
+                for change in &event.changes {
+                    let book = self.get_mut(&change.asset_id);
+                    match book.apply_change(change, event.timestamp, received_at_ms) {
+                        ChangeOutcome::Applied => {
+                            if !outcome.updated.contains(&change.asset_id) {
+                                outcome.updated.push(change.asset_id.clone());
+                            }
+                        }
+                        ChangeOutcome::Diverged(reason) => {
+                            outcome.updated.retain(|id| id != &change.asset_id);
+                            if !book.resync_pending {
+                                book.resync_pending = true;
+                                outcome.resync.push((change.asset_id.clone(), reason));
+                            }
+                        }
+                        ChangeOutcome::NotReady | ChangeOutcome::Stale => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        outcome
+    }
+
+    /// Rebuild a diverged book from a REST snapshot and count the resync.
+    pub fn apply_resync(&mut self, snapshot: &BookEvent, received_at_ms: i64) {
+        let book = self.get_mut(&snapshot.asset_id);
+        // A newer websocket snapshot may already have repaired the book.
+        if book.is_ready() && book.exchange_ts_ms > snapshot.timestamp {
+            book.resync_pending = false;
+        } else {
+            book.apply_snapshot(snapshot, received_at_ms);
+        }
+        book.resyncs += 1;
+    }
+
+    /// Stop serving every book until fresh snapshots arrive, e.g. after the
+    /// market channel dropped and events may have been missed.
+    pub fn invalidate_all(&mut self) {
+        self.books.values_mut().for_each(OrderBook::invalidate);
+    }
+
+    /// Resync count per asset id.
+    pub fn resync_counts(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.books.iter().map(|(id, book)| (id.as_str(), book.resyncs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polymarket::market_ws::PriceChangeEvent;
+
+    fn level(price: f64, size: f64) -> PriceLevel {
+        PriceLevel { price, size }
+    }
+
+    fn snapshot(asset_id: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> MarketEvent {
+        MarketEvent::Book(BookEvent {
+            asset_id: asset_id.to_string(),
+            market: "0xmarket".to_string(),
+            bids: bids.iter().map(|&(p, s)| level(p, s)).collect(),
+            asks: asks.iter().map(|&(p, s)| level(p, s)).collect(),
+            timestamp: 1_000,
+            hash: Some("h0".to_string()),
+        })
+    }
+
+    fn delta(asset_id: &str, side: Side, price: f64, size: f64, timestamp: i64) -> MarketEvent {
+        MarketEvent::PriceChange(PriceChangeEvent {
+            market: "0xmarket".to_string(),
+            timestamp,
+            changes: vec![PriceChange {
+                asset_id: asset_id.to_string(),
+                price,
+                size,
+                side,
+                hash: Some(format!("h{}", timestamp)),
+                best_bid: None,
+                best_ask: None,
+            }],
+        })
+    }
+
+    #[test]
+    fn snapshot_orders_levels_and_drops_empty_ones() {
+        let mut store = BookStore::new();
+        store.apply(&snapshot("yes", &[(0.47, 10.0), (0.49, 20.0), (0.48, 0.0)], &[(0.53, 60.0), (0.51, 150.5)]), 5);
+        let book = store.get("yes").unwrap();
+        assert_eq!(book.best_bid(), Some(level(0.49, 20.0)));
+        assert_eq!(book.best_ask(), Some(level(0.51, 150.5)));
+        assert_eq!(book.depth(Side::Buy, 5), vec![level(0.49, 20.0), level(0.47, 10.0)]);
+        assert_eq!(book.depth(Side::Sell, 1), vec![level(0.51, 150.5)]);
+        assert!((book.mid().unwrap() - 0.50).abs() < 1e-9);
+        assert!((book.spread().unwrap() - 0.02).abs() < 1e-9);
+        assert_eq!((book.exchange_ts_ms, book.updated_at_ms), (1_000, 5));
+    }
+
+    #[test]
+    fn deltas_insert_update_and_remove_levels() {
+        let mut store = BookStore::new();
+        store.apply(&snapshot("yes", &[(0.49, 20.0)], &[(0.51, 100.0)]), 5);
+
+        // New better bid, resize the ask, then remove the old bid.
+        assert_eq!(store.apply(&delta("yes", Side::Buy, 0.50, 7.0, 1_001), 6).updated, vec!["yes".to_string()]);
+        store.apply(&delta("yes", Side::Sell, 0.51, 40.0, 1_002), 7);
+        store.apply(&delta("yes", Side::Buy, 0.49, 0.0, 1_003), 8);
+
+        let book = store.get("yes").unwrap();
+        assert_eq!(book.depth(Side::Buy, 5), vec![level(0.50, 7.0)]);
+        assert_eq!(book.best_ask(), Some(level(0.51, 40.0)));
+        assert_eq!((book.exchange_ts_ms, book.updated_at_ms), (1_003, 8));
+        assert_eq!(book.last_hash.as_deref(), Some("h1003"));
+
+        // Emptying a side leaves no mid.
+        store.apply(&delta("yes", Side::Buy, 0.50, 0.0, 1_004), 9);
+        assert_eq!(store.get("yes").unwrap().mid(), None);
+    }
+
+    #[test]
+    fn deltas_before_snapshot_are_ignored_and_snapshot_replaces_state() {
+        let mut store = BookStore::new();
+        assert_eq!(store.apply(&delta("no", Side::Buy, 0.40, 5.0, 900), 1), ApplyOutcome::default());
+        assert!(store.get("no").is_none());
+
+        store.apply(&snapshot("no", &[(0.41, 1.0)], &[(0.60, 2.0)]), 2);
+        store.apply(&delta("no", Side::Buy, 0.45, 3.0, 1_001), 3);
+        store.apply(&snapshot("no", &[(0.42, 1.0)], &[]), 4);
+
+        let book = store.get("no").unwrap();
+        assert_eq!(book.depth(Side::Buy, 5), vec![level(0.42, 1.0)]);
+        assert_eq!(book.best_ask(), None);
+    }
+
+    fn delta_with_top(asset_id: &str, side: Side, price: f64, size: f64, top: (f64, f64)) -> MarketEvent {
+        let MarketEvent::PriceChange(mut event) = delta(asset_id, side, price, size, 1_001) else { unreachable!() };
+        event.changes[0].best_bid = Some(top.0);
+        event.changes[0].best_ask = Some(top.1);
+        event.changes[0].hash = None;
+        MarketEvent::PriceChange(event)
+    }
+
+    #[test]
+    fn top_of_book_mismatch_requests_one_resync_until_rebuilt() {
+        let mut store = BookStore::new();
+        store.apply(&snapshot("yes", &[(0.49, 20.0)], &[(0.51, 100.0)]), 5);
+
+        // Matches what the exchange reports: fine.
+        let ok = store.apply(&delta_with_top("yes", Side::Buy, 0.50, 5.0, (0.50, 0.51)), 6);
+        assert_eq!(ok.updated, vec!["yes".to_string()]);
+
+        // We missed an ask level the exchange still has at 0.505.
+        let bad = store.apply(&delta_with_top("yes", Side::Sell, 0.51, 0.0, (0.50, 0.505)), 7);
+        assert!(bad.updated.is_empty());
+        assert!(matches!(
+            bad.resync.as_slice(),
+            [(id, ResyncReason::TopOfBookMismatch { side: Side::Sell, .. })] if id == "yes"
+        ));
+        assert!(store.get("yes").is_none());
+
+        // Further deltas are ignored and do not ask again while a resync is pending.
+        let again = store.apply(&delta_with_top("yes", Side::Buy, 0.48, 1.0, (0.50, 0.505)), 8);
+        assert_eq!(again, ApplyOutcome::default());
+
+        let MarketEvent::Book(rest) = snapshot("yes", &[(0.50, 5.0)], &[(0.505, 10.0)]) else { unreachable!() };
+        store.apply_resync(&rest, 9);
+        assert_eq!(store.get("yes").unwrap().best_ask(), Some(level(0.505, 10.0)));
+        assert_eq!(store.resync_counts().collect::<Vec<_>>(), vec![("yes", 1)]);
+    }
+
+    #[test]
+    fn crossed_book_diverges() {
+        let mut store = BookStore::new();
+        store.apply(&snapshot("yes", &[(0.49, 20.0)], &[(0.51, 100.0)]), 5);
+        let outcome = store.apply(&delta("yes", Side::Buy, 0.52, 1.0, 1_001), 6);
+        assert_eq!(outcome.resync, vec![("yes".to_string(), ResyncReason::Crossed { bid: 0.52, ask: 0.51 })]);
+    }
+
+    #[test]
+    fn exchange_hashes_are_checked_once_snapshot_hash_matches() {
+        let mut store = BookStore::new();
+        let mut book = OrderBook::new("yes");
+        book.market = "0xmarket".to_string();
+        book.apply_snapshot(
+            &BookEvent {
+                asset_id: "yes".to_string(),
+                market: "0xmarket".to_string(),
+                bids: vec![level(0.49, 20.0)],
+                asks: vec![level(0.51, 100.0)],
+                timestamp: 1_000,
+                hash: None,
+            },
+            0,
+        );
+        let snapshot_hash = book.hash(1_000);
+        book.apply_change(
+            &PriceChange {
+                asset_id: "yes".to_string(),
+                price: 0.50,
+                size: 5.0,
+                side: Side::Buy,
+                hash: None,
+                best_bid: None,
+                best_ask: None,
+            },
+            1_001,
+            0,
+        );
+        let after_delta_hash = book.hash(1_001);
+
+        // Our snapshot hash matches the exchange's, so delta hashes are enforced.
+        let MarketEvent::Book(mut snap) = snapshot("yes", &[(0.49, 20.0)], &[(0.51, 100.0)]) else { unreachable!() };
+        snap.hash = Some(snapshot_hash);
+        store.apply(&MarketEvent::Book(snap), 1);
+        let MarketEvent::PriceChange(mut good) = delta("yes", Side::Buy, 0.50, 5.0, 1_001) else { unreachable!() };
+        good.changes[0].hash = Some(after_delta_hash);
+        assert!(store.apply(&MarketEvent::PriceChange(good), 2).resync.is_empty());
+
+        let outcome = store.apply(&delta("yes", Side::Buy, 0.48, 1.0, 1_002), 3);
+        assert!(matches!(outcome.resync.as_slice(), [(_, ResyncReason::HashMismatch { .. })]));
+    }
+
+    #[test]
+    fn unverifiable_hashes_are_not_enforced() {
+        // The fixture snapshot hash ("h0") is not ours, so delta hashes are ignored.
+        let mut store = BookStore::new();
+        store.apply(&snapshot("yes", &[(0.49, 20.0)], &[(0.51, 100.0)]), 5);
+        assert!(store.apply(&delta("yes", Side::Buy, 0.48, 1.0, 1_001), 6).resync.is_empty());
+    }
+}
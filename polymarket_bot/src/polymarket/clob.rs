@@ -0,0 +1,792 @@
+//! Polymarket CLOB REST client.
+//!
+//! Covers the public market-data endpoints the bot needs: order book,
+//! midpoint, price, tick size, neg-risk flag, fee rate, market lookup and the
+//! server clock. API keys are created or derived with wallet-signed (L1)
+//! requests; endpoints that act on the account (posting, listing and
+//! cancelling orders by id, by market or all at once) are signed with the L2
+//! headers from [`L2Auth`] when the client has credentials. Responses are
+//! decoded into typed structs; any failure is a [`ClobError`] naming the
+//! endpoint. Requests time out rather than hang a caller on a stuck socket.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use super::auth::{self, ApiCredentials, L2Auth};
+use super::de;
+use super::eip712::Wallet;
+use super::market_ws::BookEvent;
+use super::order::{NewOrder, SignedOrder};
+use crate::config::OrderType;
+use crate::strategy::Side;
+
+/// Longest a connection to the CLOB may take to open.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest a whole request, response body included, may take.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a CLOB request failed.
+#[derive(Debug)]
+pub enum ClobError {
+    /// The request could not be sent or the response not read.
+    Http { endpoint: String, source: reqwest::Error },
+    /// No response in time; the request may still have been acted upon.
+    Timeout { endpoint: String },
+    /// Non-2xx response; `message` is the CLOB's `error` field when present,
+    /// otherwise the raw body.
+    Status { endpoint: String, status: u16, message: String },
+    /// 2xx response whose body did not have the expected shape.
+    Decode { endpoint: String, error: String, body: String },
+    /// The endpoint needs API credentials the client does not have or
+    /// cannot use.
+    Auth { endpoint: String, reason: String },
+}
+
+impl fmt::Display for ClobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClobError::Http { endpoint, source } => write!(f, "{}: request failed: {}", endpoint, source),
+            ClobError::Timeout { endpoint } => write!(f, "{}: timed out", endpoint),
+            ClobError::Status { endpoint, status, message } => write!(f, "{}: HTTP {}: {}", endpoint, status, message),
+            ClobError::Decode { endpoint, error, body } => {
+                write!(f, "{}: unexpected response ({}): {}", endpoint, error, body)
+            }
+            ClobError::Auth { endpoint, reason } => write!(f, "{}: cannot authenticate: {}", endpoint, reason),
+        }
+    }
+}
+
+impl std::error::Error for ClobError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClobError::Http { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Per-token parameters every order has to respect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenParams {
+    pub tick_size: f64,
+    /// Orders sign against the neg-risk exchange.
+    pub neg_risk: bool,
+    pub fee_rate_bps: u32,
+}
+
+/// One outcome token of a market.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MarketToken {
+    pub token_id: String,
+    pub outcome: String,
+    #[serde(default, deserialize_with = "de::opt_f64_from_str")]
+    pub price: Option<f64>,
+    #[serde(default)]
+    pub winner: bool,
+}
+
+/// A market as returned by `/markets`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Market {
+    pub condition_id: String,
+    #[serde(default)]
+    pub question_id: Option<String>,
+    #[serde(default)]
+    pub question: String,
+    #[serde(default)]
+    pub market_slug: Option<String>,
+    pub tokens: Vec<MarketToken>,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub minimum_tick_size: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub minimum_order_size: f64,
+    #[serde(default)]
+    pub neg_risk: bool,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub accepting_orders: bool,
+    #[serde(default)]
+    pub end_date_iso: Option<String>,
+    #[serde(default, deserialize_with = "de::opt_f64_from_str")]
+    pub maker_base_fee: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_f64_from_str")]
+    pub taker_base_fee: Option<f64>,
+}
+
+/// One page of `/markets`. The last page has `next_cursor` [`LAST_CURSOR`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MarketsPage {
+    #[serde(default)]
+    pub next_cursor: String,
+    pub data: Vec<Market>,
+}
+
+/// A resting order of the authenticated account, from `/data/orders`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OpenOrder {
+    pub id: String,
+    pub status: String,
+    pub market: String,
+    pub asset_id: String,
+    pub side: Side,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub original_size: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub size_matched: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub price: f64,
+    #[serde(default)]
+    pub outcome: String,
+    #[serde(default)]
+    pub order_type: String,
+    #[serde(default, deserialize_with = "de::opt_i64_from_str")]
+    pub expiration: Option<i64>,
+    #[serde(default, deserialize_with = "de::opt_i64_from_str")]
+    pub created_at: Option<i64>,
+}
+
+/// Outcome the CLOB reports for an accepted order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlacementStatus {
+    /// Resting on the book.
+    Live,
+    /// Matched on arrival (fully, or partially for FAK).
+    Matched,
+    /// Marketable and held back by the matching delay.
+    Delayed,
+    /// Marketable but not matched after the delay; it rests or is killed.
+    Unmatched,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Response to `POST /order`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OrderResponse {
+    pub success: bool,
+    #[serde(rename = "orderID", default)]
+    pub order_id: String,
+    #[serde(default)]
+    pub status: Option<PlacementStatus>,
+    /// What the account gave up in matches: USDC for buys, shares for sells.
+    #[serde(rename = "makingAmount", default, deserialize_with = "de::opt_f64_from_str")]
+    pub making_amount: Option<f64>,
+    /// What the account received in matches: shares for buys, USDC for sells.
+    #[serde(rename = "takingAmount", default, deserialize_with = "de::opt_f64_from_str")]
+    pub taking_amount: Option<f64>,
+    #[serde(rename = "errorMsg", default)]
+    pub error_msg: String,
+    #[serde(rename = "transactionsHashes", default)]
+    pub transaction_hashes: Vec<String>,
+}
+
+impl OrderResponse {
+    /// Error message when the CLOB did not accept the order.
+    pub fn error(&self) -> Option<&str> {
+        match (self.success, self.error_msg.as_str()) {
+            (true, "") => None,
+            (_, "") => Some("rejected without a message"),
+            (_, msg) => Some(msg),
+        }
+    }
+}
+
+impl fmt::Display for OrderResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(error) = self.error() {
+            return write!(f, "order {} rejected: {}", self.order_id, error);
+        }
+        write!(f, "order {} {:?}", self.order_id, self.status.unwrap_or(PlacementStatus::Unknown))?;
+        if let (Some(making), Some(taking)) = (self.making_amount, self.taking_amount) {
+            write!(f, " (matched: gave {}, got {})", making, taking)?;
+        }
+        Ok(())
+    }
+}
+
+/// Response to the cancel endpoints.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CancelResponse {
+    #[serde(default)]
+    pub canceled: Vec<String>,
+    /// Order id to the reason it could not be cancelled.
+    #[serde(default)]
+    pub not_canceled: BTreeMap<String, String>,
+}
+
+impl fmt::Display for CancelResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} cancelled", self.canceled.len())?;
+        for (order_id, reason) in &self.not_canceled {
+            write!(f, "; {} not cancelled: {}", order_id, reason)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    #[serde(default)]
+    next_cursor: String,
+    data: Vec<T>,
+}
+
+/// Cursor the CLOB returns once there are no more pages.
+pub const LAST_CURSOR: &str = "LTE=";
+
+#[derive(Deserialize)]
+struct Midpoint {
+    #[serde(deserialize_with = "de::f64_from_str")]
+    mid: f64,
+}
+
+#[derive(Deserialize)]
+struct Price {
+    #[serde(deserialize_with = "de::f64_from_str")]
+    price: f64,
+}
+
+#[derive(Deserialize)]
+struct TickSize {
+    #[serde(deserialize_with = "de::f64_from_str")]
+    minimum_tick_size: f64,
+}
+
+#[derive(Deserialize)]
+struct NegRisk {
+    neg_risk: bool,
+}
+
+#[derive(Deserialize)]
+struct FeeRate {
+    #[serde(deserialize_with = "de::f64_from_str")]
+    base_fee: f64,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// Async client for the CLOB REST API at `host`.
+#[derive(Debug, Clone)]
+pub struct ClobClient {
+    http: reqwest::Client,
+    host: String,
+    auth: Option<L2Auth>,
+}
+
+impl ClobClient {
+    pub fn new(host: &str) -> Self {
+        let http = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("HTTP client with default TLS");
+        Self::with_http(http, host)
+    }
+
+    /// Use a preconfigured `reqwest` client (timeouts, proxies).
+    pub fn with_http(http: reqwest::Client, host: &str) -> Self {
+        Self { http, host: host.trim_end_matches('/').to_string(), auth: None }
+    }
+
+    /// Sign account endpoints with these API credentials.
+    pub fn with_auth(mut self, auth: L2Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.auth.is_some()
+    }
+
+    /// API credentials account endpoints are signed with, if any.
+    pub fn credentials(&self) -> Option<&ApiCredentials> {
+        self.auth.as_ref().map(|a| &a.credentials)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T, ClobError> {
+        let request = self.http.get(format!("{}{}", self.host, path)).query(query);
+        self.send(path, request).await
+    }
+
+    /// Request with L1 headers signed by `wallet`, timestamped with the
+    /// server clock so local skew cannot invalidate the signature.
+    async fn request_l1<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        wallet: &Wallet,
+        chain_id: u64,
+        nonce: u64,
+    ) -> Result<T, ClobError> {
+        let timestamp = self.server_time().await?;
+        let headers = auth::l1_headers(wallet, chain_id, timestamp, nonce)
+            .map_err(|e| ClobError::Auth { endpoint: path.to_string(), reason: e.to_string() })?;
+        let mut request = self.http.request(method, format!("{}{}", self.host, path));
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        self.send(path, request).await
+    }
+
+    /// Request with L2 headers. The signature covers `path` and the exact
+    /// `body` bytes sent, not the query string.
+    async fn request_l2<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<String>,
+    ) -> Result<T, ClobError> {
+        let auth_err = |reason: String| ClobError::Auth { endpoint: path.to_string(), reason };
+        let auth = self.auth.as_ref().ok_or_else(|| auth_err("no API credentials configured".to_string()))?;
+        let timestamp = chrono::Utc::now().timestamp();
+        let headers = auth
+            .headers(timestamp, method.as_str(), path, body.as_deref())
+            .map_err(|e| auth_err(e.to_string()))?;
+        let mut request = self.http.request(method, format!("{}{}", self.host, path)).query(query);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        if let Some(body) = body {
+            request = request.header(reqwest::header::CONTENT_TYPE, "application/json").body(body);
+        }
+        self.send(path, request).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, endpoint: &str, request: reqwest::RequestBuilder) -> Result<T, ClobError> {
+        let http_err = |source: reqwest::Error| match source.is_timeout() {
+            true => ClobError::Timeout { endpoint: endpoint.to_string() },
+            false => ClobError::Http { endpoint: endpoint.to_string(), source },
+        };
+        let response = request.send().await.map_err(http_err)?;
+        let status = response.status();
+        let body = response.text().await.map_err(http_err)?;
+        if !status.is_success() {
+            let message = serde_json::from_str::<ErrorBody>(&body).map(|e| e.error).unwrap_or(body);
+            return Err(ClobError::Status { endpoint: endpoint.to_string(), status: status.as_u16(), message });
+        }
+        serde_json::from_str(&body).map_err(|e| ClobError::Decode {
+            endpoint: endpoint.to_string(),
+            error: e.to_string(),
+            body,
+        })
+    }
+
+    /// Server clock in seconds since the epoch.
+    pub async fn server_time(&self) -> Result<i64, ClobError> {
+        self.get("/time", &[]).await
+    }
+
+    /// Full order book summary of `token_id`, in the same shape as a
+    /// market-channel `book` snapshot.
+    pub async fn book(&self, token_id: &str) -> Result<BookEvent, ClobError> {
+        self.get("/book", &[("token_id", token_id)]).await
+    }
+
+    pub async fn midpoint(&self, token_id: &str) -> Result<f64, ClobError> {
+        self.get::<Midpoint>("/midpoint", &[("token_id", token_id)]).await.map(|m| m.mid)
+    }
+
+    /// Best price available to an order on `side`.
+    pub async fn price(&self, token_id: &str, side: Side) -> Result<f64, ClobError> {
+        self.get::<Price>("/price", &[("token_id", token_id), ("side", side.as_str())])
+            .await
+            .map(|p| p.price)
+    }
+
+    pub async fn tick_size(&self, token_id: &str) -> Result<f64, ClobError> {
+        self.get::<TickSize>("/tick-size", &[("token_id", token_id)])
+            .await
+            .map(|t| t.minimum_tick_size)
+    }
+
+    /// Whether `token_id` trades on the neg-risk exchange.
+    pub async fn neg_risk(&self, token_id: &str) -> Result<bool, ClobError> {
+        self.get::<NegRisk>("/neg-risk", &[("token_id", token_id)]).await.map(|n| n.neg_risk)
+    }
+
+    /// Base fee rate in basis points that orders on `token_id` must carry.
+    pub async fn fee_rate_bps(&self, token_id: &str) -> Result<u32, ClobError> {
+        self.get::<FeeRate>("/fee-rate", &[("token_id", token_id)])
+            .await
+            .map(|f| f.base_fee.round() as u32)
+    }
+
+    /// Tick size, neg-risk flag and fee rate of `token_id`.
+    pub async fn token_params(&self, token_id: &str) -> Result<TokenParams, ClobError> {
+        Ok(TokenParams {
+            tick_size: self.tick_size(token_id).await?,
+            neg_risk: self.neg_risk(token_id).await?,
+            fee_rate_bps: self.fee_rate_bps(token_id).await?,
+        })
+    }
+
+    pub async fn market(&self, condition_id: &str) -> Result<Market, ClobError> {
+        self.get(&format!("/markets/{}", condition_id), &[]).await
+    }
+
+    /// Create a new API key for `wallet` under `nonce`.
+    pub async fn create_api_key(&self, wallet: &Wallet, chain_id: u64, nonce: u64) -> Result<ApiCredentials, ClobError> {
+        self.request_l1(reqwest::Method::POST, "/auth/api-key", wallet, chain_id, nonce).await
+    }
+
+    /// Recover the API key previously created for `wallet` under `nonce`.
+    pub async fn derive_api_key(&self, wallet: &Wallet, chain_id: u64, nonce: u64) -> Result<ApiCredentials, ClobError> {
+        self.request_l1(reqwest::Method::GET, "/auth/derive-api-key", wallet, chain_id, nonce).await
+    }
+
+    /// Create an API key, or derive the existing one if the CLOB refuses to
+    /// create another under the same nonce.
+    pub async fn create_or_derive_api_key(
+        &self,
+        wallet: &Wallet,
+        chain_id: u64,
+        nonce: u64,
+    ) -> Result<ApiCredentials, ClobError> {
+        match self.create_api_key(wallet, chain_id, nonce).await {
+            Err(ClobError::Status { .. }) => self.derive_api_key(wallet, chain_id, nonce).await,
+            result => result,
+        }
+    }
+
+    /// Submit a signed order. Rejections the CLOB reports in a 2xx body are
+    /// in [`OrderResponse::error`]; others are [`ClobError::Status`].
+    pub async fn post_order(
+        &self,
+        order: &SignedOrder,
+        order_type: OrderType,
+        post_only: bool,
+    ) -> Result<OrderResponse, ClobError> {
+        let owner = self.credentials().map(|c| c.api_key.as_str()).unwrap_or_default();
+        let body = NewOrder { order, owner, order_type: order_type.as_str(), post_only };
+        let body = serde_json::to_string(&body).expect("order serializes");
+        self.request_l2(reqwest::Method::POST, "/order", &[], Some(body)).await
+    }
+
+    /// Cancel one order by id.
+    pub async fn cancel_order(&self, order_id: &str) -> Result<CancelResponse, ClobError> {
+        let body = serde_json::json!({ "orderID": order_id }).to_string();
+        self.request_l2(reqwest::Method::DELETE, "/order", &[], Some(body)).await
+    }
+
+    /// Cancel several orders by id in one request.
+    pub async fn cancel_orders(&self, order_ids: &[String]) -> Result<CancelResponse, ClobError> {
+        let body = serde_json::to_string(order_ids).expect("order ids serialize");
+        self.request_l2(reqwest::Method::DELETE, "/orders", &[], Some(body)).await
+    }
+
+    /// Cancel every resting order in a market (condition id), optionally
+    /// only those on one outcome token.
+    pub async fn cancel_market_orders(&self, market: &str, asset_id: Option<&str>) -> Result<CancelResponse, ClobError> {
+        let body = serde_json::json!({ "market": market, "asset_id": asset_id.unwrap_or_default() }).to_string();
+        self.request_l2(reqwest::Method::DELETE, "/cancel-market-orders", &[], Some(body)).await
+    }
+
+    /// Cancel every resting order of the account.
+    pub async fn cancel_all(&self) -> Result<CancelResponse, ClobError> {
+        self.request_l2(reqwest::Method::DELETE, "/cancel-all", &[], None).await
+    }
+
+    /// Every resting order of the account, optionally narrowed to one market
+    /// (condition id) or one outcome token.
+    pub async fn open_orders(&self, market: Option<&str>, asset_id: Option<&str>) -> Result<Vec<OpenOrder>, ClobError> {
+        let mut orders = Vec::new();
+        let mut cursor = String::new();
+        loop {
+            let mut query: Vec<(&str, &str)> = Vec::new();
+            query.extend(market.map(|m| ("market", m)));
+            query.extend(asset_id.map(|a| ("asset_id", a)));
+            if !cursor.is_empty() {
+                query.push(("next_cursor", &cursor));
+            }
+            let page: Page<OpenOrder> = self.request_l2(reqwest::Method::GET, "/data/orders", &query, None).await?;
+            orders.extend(page.data);
+            if page.next_cursor.is_empty() || page.next_cursor == LAST_CURSOR {
+                return Ok(orders);
+            }
+            cursor = page.next_cursor;
+        }
+    }
+
+    /// One page of markets, starting at `next_cursor` (first page if `None`).
+    pub async fn markets(&self, next_cursor: Option<&str>) -> Result<MarketsPage, ClobError> {
+        let query: Vec<(&str, &str)> = next_cursor.map(|c| ("next_cursor", c)).into_iter().collect();
+        self.get("/markets", &query).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polymarket::mock_http::{MockServer, Route};
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/clob/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    const TOKEN: &str = "71321045679252212594626385532706912750332728571942532289631379312455583992563";
+
+    #[tokio::test]
+    async fn decodes_market_data_endpoints() {
+        let server = MockServer::start(vec![
+            Route::new("GET", "/time", 200, "1729084877"),
+            Route::new("GET", "/book", 200, fixture("book.json")),
+            Route::new("GET", "/midpoint", 200, r#"{"mid":"0.505"}"#),
+            Route::new("GET", "/price", 200, r#"{"price":"0.51"}"#),
+            Route::new("GET", "/tick-size", 200, r#"{"minimum_tick_size":0.01}"#),
+            Route::new("GET", "/neg-risk", 200, r#"{"neg_risk":true}"#),
+            Route::new("GET", "/fee-rate", 200, r#"{"base_fee":0}"#),
+        ])
+        .await;
+        let client = ClobClient::new(&format!("{}/", server.url));
+
+        assert_eq!(client.server_time().await.unwrap(), 1_729_084_877);
+        let book = client.book(TOKEN).await.unwrap();
+        assert_eq!((book.asset_id.as_str(), book.bids.len(), book.asks.len()), (TOKEN, 2, 2));
+        assert_eq!(book.timestamp, 1_729_084_877_448);
+        assert_eq!(client.midpoint(TOKEN).await.unwrap(), 0.505);
+        assert_eq!(client.price(TOKEN, Side::Sell).await.unwrap(), 0.51);
+        assert_eq!(client.tick_size(TOKEN).await.unwrap(), 0.01);
+        assert!(client.neg_risk(TOKEN).await.unwrap());
+        assert_eq!(client.fee_rate_bps(TOKEN).await.unwrap(), 0);
+        let params = client.token_params(TOKEN).await.unwrap();
+        assert_eq!(params, TokenParams { tick_size: 0.01, neg_risk: true, fee_rate_bps: 0 });
+
+        let targets: Vec<String> = server.requests().into_iter().map(|r| r.target).collect();
+        assert_eq!(targets[1], format!("/book?token_id={}", TOKEN));
+        assert_eq!(targets[3], format!("/price?token_id={}&side=SELL", TOKEN));
+    }
+
+    #[tokio::test]
+    async fn decodes_markets() {
+        let server = MockServer::start(vec![
+            Route::new("GET", "/markets", 200, fixture("markets.json")),
+            Route::new("GET", "/markets/0xabc", 200, fixture("market.json")),
+        ])
+        .await;
+        let client = ClobClient::new(&server.url);
+
+        let page = client.markets(Some("MA==")).await.unwrap();
+        assert_eq!(page.next_cursor, LAST_CURSOR);
+        assert_eq!(page.data.len(), 1);
+        assert_eq!(server.requests()[0].target, "/markets?next_cursor=MA%3D%3D");
+
+        let market = client.market("0xabc").await.unwrap();
+        assert_eq!(market.minimum_tick_size, 0.01);
+        assert!(market.neg_risk && market.accepting_orders);
+        assert_eq!(market.tokens[1].outcome, "No");
+        assert_eq!(market.tokens[1].price, Some(0.495));
+    }
+
+    #[tokio::test]
+    async fn signs_account_requests_with_l2_headers() {
+        let server = MockServer::start(vec![Route::new("GET", "/data/orders", 200, fixture("orders.json"))]).await;
+        let unauthenticated = ClobClient::new(&server.url);
+        assert!(matches!(unauthenticated.open_orders(None, None).await, Err(ClobError::Auth { .. })));
+
+        let client = authenticated(&server);
+        let orders = client.open_orders(Some("0xabc"), None).await.unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!((orders[0].side, orders[0].original_size, orders[0].size_matched), (Side::Buy, 100.0, 25.5));
+
+        let request = &server.requests()[0];
+        assert_eq!(request.target, "/data/orders?market=0xabc");
+        assert_eq!(request.header("poly_address"), Some("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+        assert_eq!(request.header("poly_api_key"), Some("key-1"));
+        assert_eq!(request.header("poly_passphrase"), Some("pass"));
+        let timestamp: i64 = request.header("poly_timestamp").unwrap().parse().unwrap();
+        let expected = auth::hmac_signature("c2VjcmV0", timestamp, "GET", "/data/orders", None).unwrap();
+        assert_eq!(request.header("poly_signature"), Some(expected.as_str()));
+    }
+
+    #[tokio::test]
+    async fn creates_or_derives_api_keys_with_l1_headers() {
+        let credentials = r#"{"apiKey":"key-1","secret":"c2VjcmV0","passphrase":"pass"}"#;
+        let server = MockServer::start(vec![
+            Route::new("GET", "/time", 200, "10000000"),
+            Route::new("POST", "/auth/api-key", 400, r#"{"error":"Could not create api key"}"#),
+            Route::new("GET", "/auth/derive-api-key", 200, credentials),
+        ])
+        .await;
+        let client = ClobClient::new(&server.url);
+        let wallet = Wallet::from_hex("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").unwrap();
+
+        let created = client.create_or_derive_api_key(&wallet, 80002, 23).await.unwrap();
+        assert_eq!((created.api_key.as_str(), created.secret.expose()), ("key-1", "c2VjcmV0"));
+
+        let requests = server.requests();
+        let methods: Vec<(&str, &str)> = requests.iter().map(|r| (r.method.as_str(), r.target.as_str())).collect();
+        assert_eq!(
+            methods,
+            [("GET", "/time"), ("POST", "/auth/api-key"), ("GET", "/time"), ("GET", "/auth/derive-api-key")]
+        );
+        let derive = &requests[3];
+        let signature = auth::clob_auth_signature(&wallet, 80002, 10_000_000, 23).unwrap().to_string();
+        assert_eq!(derive.header("poly_signature"), Some(signature.as_str()));
+        assert_eq!(derive.header("poly_timestamp"), Some("10000000"));
+        assert_eq!(derive.header("poly_nonce"), Some("23"));
+        assert_eq!(derive.header("poly_address"), Some("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+    }
+
+    fn authenticated(server: &MockServer) -> ClobClient {
+        use crate::config::Secret;
+        use crate::polymarket::auth::ApiCredentials;
+
+        let credentials = ApiCredentials {
+            api_key: "key-1".to_string(),
+            secret: Secret::new("c2VjcmV0"),
+            passphrase: Secret::new("pass"),
+        };
+        let address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+        ClobClient::new(&server.url).with_auth(L2Auth::new(address, credentials))
+    }
+
+    fn signed_order() -> SignedOrder {
+        use crate::config::SignatureType;
+        use crate::polymarket::order::{OrderArgs, OrderBuilder};
+
+        let wallet = Wallet::from_hex("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").unwrap();
+        let builder = OrderBuilder::new(wallet, 137, SignatureType::Eoa, None).unwrap();
+        let args = OrderArgs {
+            token_id: TOKEN.to_string(),
+            side: Side::Buy,
+            price: 0.5,
+            size: 20.0,
+            fee_rate_bps: 0,
+            order_type: OrderType::Fak,
+            expiration: 0,
+            nonce: 0,
+        };
+        builder.build_signed(&args, 0.01, false).unwrap()
+    }
+
+    #[tokio::test]
+    async fn posts_signed_orders_and_parses_responses() {
+        let server = MockServer::start(vec![Route::new("POST", "/order", 200, fixture("order_matched.json"))]).await;
+        let client = authenticated(&server);
+        let order = signed_order();
+
+        let response = client.post_order(&order, OrderType::Fak, false).await.unwrap();
+        assert_eq!(response.error(), None);
+        assert_eq!(response.order_id, "0x2d4e37d43ce67ac26fd34fbded7ac34fdcba1b2aff632aac52b36483f1d5eeb8");
+        assert_eq!(response.status, Some(PlacementStatus::Matched));
+        assert_eq!((response.making_amount, response.taking_amount), (Some(5.0), Some(10.0)));
+
+        let request = &server.requests()[0];
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!((&body["owner"], &body["orderType"], &body["postOnly"]), (&"key-1".into(), &"FAK".into(), &false.into()));
+        assert_eq!(body["order"]["signature"], order.signature.to_string());
+        let timestamp: i64 = request.header("poly_timestamp").unwrap().parse().unwrap();
+        let expected = auth::hmac_signature("c2VjcmV0", timestamp, "POST", "/order", Some(&request.body)).unwrap();
+        assert_eq!(request.header("poly_signature"), Some(expected.as_str()));
+    }
+
+    #[tokio::test]
+    async fn reports_order_rejections() {
+        let server = MockServer::start(vec![Route::new("POST", "/order", 200, fixture("order_rejected.json"))]).await;
+        let response = authenticated(&server).post_order(&signed_order(), OrderType::Fok, false).await.unwrap();
+        assert_eq!(response.error(), Some("order couldn't be fully filled. FOK orders are fully filled or killed."));
+        assert!(response.to_string().contains("rejected"));
+
+        let server = MockServer::start(vec![Route::new(
+            "POST",
+            "/order",
+            400,
+            r#"{"error":"not enough balance / allowance"}"#,
+        )])
+        .await;
+        match authenticated(&server).post_order(&signed_order(), OrderType::Gtc, true).await {
+            Err(ClobError::Status { status: 400, message, .. }) => assert_eq!(message, "not enough balance / allowance"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn cancels_by_id_market_and_account() {
+        let server = MockServer::start(vec![
+            Route::new("DELETE", "/order", 200, r#"{"canceled":["0xaaa"],"not_canceled":{}}"#),
+            Route::new("DELETE", "/orders", 200, fixture("cancel.json")),
+            Route::new("DELETE", "/cancel-market-orders", 200, r#"{"canceled":[],"not_canceled":{}}"#),
+            Route::new("DELETE", "/cancel-all", 200, r#"{"canceled":["0xccc"],"not_canceled":{}}"#),
+        ])
+        .await;
+        assert!(matches!(ClobClient::new(&server.url).cancel_all().await, Err(ClobError::Auth { .. })));
+        let client = authenticated(&server);
+
+        assert_eq!(client.cancel_order("0xaaa").await.unwrap().canceled, ["0xaaa"]);
+        let response = client.cancel_orders(&["0xaaa".to_string(), "0xbbb".to_string()]).await.unwrap();
+        assert_eq!(response.canceled, ["0xaaa"]);
+        assert_eq!(response.not_canceled["0xbbb"], "order not found or already canceled");
+        assert_eq!(response.to_string(), "1 cancelled; 0xbbb not cancelled: order not found or already canceled");
+        assert!(client.cancel_market_orders("0xabc", None).await.unwrap().canceled.is_empty());
+        assert_eq!(client.cancel_all().await.unwrap().canceled, ["0xccc"]);
+
+        let requests = server.requests();
+        let bodies: Vec<&str> = requests.iter().map(|r| r.body.as_str()).collect();
+        assert_eq!(bodies, [r#"{"orderID":"0xaaa"}"#, r#"["0xaaa","0xbbb"]"#, r#"{"asset_id":"","market":"0xabc"}"#, ""]);
+        let timestamp: i64 = requests[0].header("poly_timestamp").unwrap().parse().unwrap();
+        let expected = auth::hmac_signature("c2VjcmV0", timestamp, "DELETE", "/order", Some(bodies[0])).unwrap();
+        assert_eq!(requests[0].header("poly_signature"), Some(expected.as_str()));
+    }
+
+    #[tokio::test]
+    async fn reports_status_and_decode_errors() {
+        let server = MockServer::start(vec![
+            Route::new("GET", "/book", 404, r#"{"error":"No orderbook exists for the requested token id"}"#),
+            Route::new("GET", "/midpoint", 500, "upstream timeout"),
+            Route::new("GET", "/tick-size", 200, r#"{"tick":"0.01"}"#),
+        ])
+        .await;
+        let client = ClobClient::new(&server.url);
+
+        match client.book("1").await {
+            Err(ClobError::Status { endpoint, status: 404, message }) => {
+                assert_eq!(endpoint, "/book");
+                assert_eq!(message, "No orderbook exists for the requested token id");
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(
+            client.midpoint("1").await,
+            Err(ClobError::Status { status: 500, message, .. }) if message == "upstream timeout"
+        ));
+        assert!(matches!(client.tick_size("1").await, Err(ClobError::Decode { .. })));
+
+        let unreachable = ClobClient::new("http://127.0.0.1:1");
+        assert!(matches!(unreachable.server_time().await, Err(ClobError::Http { .. })));
+    }
+
+    #[tokio::test]
+    async fn times_out_on_a_server_that_never_answers() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((tcp, _)) = listener.accept().await {
+                sockets.push(tcp);
+            }
+        });
+        let http = reqwest::Client::builder().timeout(Duration::from_millis(100)).build().unwrap();
+        let client = ClobClient::with_http(http, &url);
+
+        match client.book("1").await {
+            Err(ClobError::Timeout { endpoint }) => assert_eq!(endpoint, "/book"),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! EIP-712 typed-data hashing and secp256k1 signing.
+//!
+//! Only what the CLOB needs: a domain with `name`, `version`, `chainId` and an
+//! optional `verifyingContract`, static struct encoding from 32-byte words,
+//! and recoverable signatures in the `r || s || v` form with `v` in {27, 28}.
+
+use k256::ecdsa::SigningKey;
+use serde::{Serialize, Serializer};
+use sha3::{Digest, Keccak256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Why a key, address, number or signature could not be handled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SigningError {
+    InvalidKey(String),
+    InvalidAddress(String),
+    InvalidNumber(String),
+    Sign(String),
+}
+
+impl fmt::Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningError::InvalidKey(reason) => write!(f, "invalid private key: {}", reason),
+            SigningError::InvalidAddress(s) => write!(f, "invalid address {:?}", s),
+            SigningError::InvalidNumber(s) => write!(f, "invalid uint256 {:?}", s),
+            SigningError::Sign(reason) => write!(f, "signing failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SigningError {}
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// 20-byte Ethereum address; displays in EIP-55 checksum form.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Address(pub [u8; 20]);
+
+impl Address {
+    pub const ZERO: Address = Address([0; 20]);
+
+    /// ABI encoding as a left-padded 32-byte word.
+    pub fn to_word(&self) -> [u8; 32] {
+        let mut word = [0u8; 32];
+        word[12..].copy_from_slice(&self.0);
+        word
+    }
+}
+
+impl FromStr for Address {
+    type Err = SigningError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+        let bytes = hex::decode(hex).map_err(|_| SigningError::InvalidAddress(s.to_string()))?;
+        let bytes: [u8; 20] = bytes.try_into().map_err(|_| SigningError::InvalidAddress(s.to_string()))?;
+        Ok(Address(bytes))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lower = hex::encode(self.0);
+        let hash = keccak256(lower.as_bytes());
+        f.write_str("0x")?;
+        for (i, c) in lower.chars().enumerate() {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if c.is_ascii_alphabetic() && nibble >= 8 {
+                write!(f, "{}", c.to_ascii_uppercase())?;
+            } else {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Address({})", self)
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// `uint256` word of a `u64`.
+pub fn uint_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// `uint256` word of a decimal string such as an outcome token id.
+pub fn uint_word_from_dec(s: &str) -> Result<[u8; 32], SigningError> {
+    let invalid = || SigningError::InvalidNumber(s.to_string());
+    if s.is_empty() {
+        return Err(invalid());
+    }
+    let mut word = [0u8; 32];
+    for c in s.chars() {
+        let mut carry = c.to_digit(10).ok_or_else(invalid)?;
+        for byte in word.iter_mut().rev() {
+            let v = *byte as u32 * 10 + carry;
+            *byte = v as u8;
+            carry = v >> 8;
+        }
+        if carry != 0 {
+            return Err(invalid());
+        }
+    }
+    Ok(word)
+}
+
+/// `keccak256(typeString || words...)`: the hash of a struct whose members
+/// are all static or already hashed.
+pub fn hash_struct(type_string: &str, words: &[[u8; 32]]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(32 * (words.len() + 1));
+    data.extend_from_slice(&keccak256(type_string.as_bytes()));
+    words.iter().for_each(|w| data.extend_from_slice(w));
+    keccak256(&data)
+}
+
+/// EIP-712 signing domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Domain {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub chain_id: u64,
+    pub verifying_contract: Option<Address>,
+}
+
+impl Domain {
+    pub fn separator(&self) -> [u8; 32] {
+        let mut words = vec![
+            keccak256(self.name.as_bytes()),
+            keccak256(self.version.as_bytes()),
+            uint_word(self.chain_id),
+        ];
+        match self.verifying_contract {
+            Some(contract) => {
+                words.push(contract.to_word());
+                hash_struct(
+                    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+                    &words,
+                )
+            }
+            None => hash_struct("EIP712Domain(string name,string version,uint256 chainId)", &words),
+        }
+    }
+
+    /// Digest to sign for a struct hashed under this domain.
+    pub fn digest(&self, struct_hash: &[u8; 32]) -> [u8; 32] {
+        let mut data = Vec::with_capacity(66);
+        data.extend_from_slice(&[0x19, 0x01]);
+        data.extend_from_slice(&self.separator());
+        data.extend_from_slice(struct_hash);
+        keccak256(&data)
+    }
+}
+
+/// `r || s || v` recoverable signature.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Signature(pub [u8; 65]);
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signature({})", self)
+    }
+}
+
+/// secp256k1 key that signs on behalf of an address.
+#[derive(Clone)]
+pub struct Wallet {
+    key: SigningKey,
+    address: Address,
+}
+
+impl Wallet {
+    /// Load a hex private key, with or without `0x`.
+    pub fn from_hex(private_key: &str) -> Result<Self, SigningError> {
+        let hex = private_key.trim().trim_start_matches("0x");
+        let bytes = hex::decode(hex).map_err(|e| SigningError::InvalidKey(e.to_string()))?;
+        let key = SigningKey::from_slice(&bytes).map_err(|e| SigningError::InvalidKey(e.to_string()))?;
+        let point = key.verifying_key().to_encoded_point(false);
+        let hash = keccak256(&point.as_bytes()[1..]);
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&hash[12..]);
+        Ok(Self { key, address: Address(address) })
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Sign a 32-byte digest (deterministic, RFC 6979, low-s).
+    pub fn sign_digest(&self, digest: &[u8; 32]) -> Result<Signature, SigningError> {
+        let (signature, recovery_id) =
+            self.key.sign_prehash_recoverable(digest).map_err(|e| SigningError::Sign(e.to_string()))?;
+        let mut out = [0u8; 65];
+        out[..64].copy_from_slice(&signature.to_bytes());
+        out[64] = 27 + recovery_id.to_byte();
+        Ok(Signature(out))
+    }
+}
+
+impl fmt::Debug for Wallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Wallet({})", self.address)
+    }
+}
+
+/// Address that produced `signature` over `digest`.
+pub fn recover(digest: &[u8; 32], signature: &Signature) -> Result<Address, SigningError> {
+    use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, VerifyingKey};
+    let sign_err = |e: k256::ecdsa::Error| SigningError::Sign(e.to_string());
+    let sig = EcdsaSignature::from_slice(&signature.0[..64]).map_err(sign_err)?;
+    let recovery_id = RecoveryId::from_byte(signature.0[64].wrapping_sub(27))
+        .ok_or_else(|| SigningError::Sign("bad recovery id".to_string()))?;
+    let key = VerifyingKey::recover_from_prehash(digest, &sig, recovery_id).map_err(sign_err)?;
+    let hash = keccak256(&key.to_encoded_point(false).as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    Ok(Address(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_checksummed_addresses() {
+        // First Hardhat/Anvil development account.
+        let wallet = Wallet::from_hex("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").unwrap();
+        assert_eq!(wallet.address().to_string(), "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+        let parsed: Address = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".parse().unwrap();
+        assert_eq!(parsed, wallet.address());
+        assert!("0x1234".parse::<Address>().is_err());
+        assert!(Wallet::from_hex("0x00").is_err());
+    }
+
+    #[test]
+    fn encodes_decimal_uint256() {
+        assert_eq!(uint_word_from_dec("1").unwrap(), uint_word(1));
+        assert_eq!(uint_word_from_dec("18446744073709551616").unwrap()[23], 1);
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        assert_eq!(uint_word_from_dec(max).unwrap(), [0xff; 32]);
+        assert!(uint_word_from_dec("115792089237316195423570985008687907853269984665640564039457584007913129639936").is_err());
+        assert!(uint_word_from_dec("12a").is_err());
+    }
+
+    /// The `Mail` example from the EIP-712 specification.
+    #[test]
+    fn matches_eip712_specification_example() {
+        let domain = Domain {
+            name: "Ether Mail",
+            version: "1",
+            chain_id: 1,
+            verifying_contract: Some("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC".parse().unwrap()),
+        };
+        assert_eq!(
+            hex::encode(domain.separator()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+
+        const PERSON: &str = "Person(string name,address wallet)";
+        let person = |name: &str, wallet: &str| {
+            hash_struct(PERSON, &[keccak256(name.as_bytes()), wallet.parse::<Address>().unwrap().to_word()])
+        };
+        let mail = hash_struct(
+            &format!("Mail(Person from,Person to,string contents){}", PERSON),
+            &[
+                person("Cow", "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+                person("Bob", "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+                keccak256(b"Hello, Bob!"),
+            ],
+        );
+        assert_eq!(hex::encode(mail), "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e");
+        let digest = domain.digest(&mail);
+        assert_eq!(hex::encode(digest), "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2");
+
+        let wallet = Wallet::from_hex(&hex::encode(keccak256(b"cow"))).unwrap();
+        assert_eq!(wallet.address().to_string(), "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826");
+        let signature = wallet.sign_digest(&digest).unwrap();
+        assert_eq!(
+            signature.to_string(),
+            "0x4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d\
+             07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562\
+             1c"
+        );
+        assert_eq!(recover(&digest, &signature).unwrap(), wallet.address());
+    }
+}
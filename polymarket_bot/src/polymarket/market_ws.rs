@@ -0,0 +1,244 @@
+//! Polymarket `market` websocket channel.
+//!
+//! After connecting to `<polymarket_ws_url>/market` the client subscribes with
+//! the outcome token ids (`assets_ids`). The server then pushes `book`
+//! snapshots, `price_change` deltas, `tick_size_change` and `last_trade_price`
+//! events, either one object per frame or several in a JSON array.
+
+use futures_util::SinkExt;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+use super::de;
+use crate::feeds::{FeedError, WsStream};
+use crate::strategy::Side;
+
+/// One price level as sent by the CLOB.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PriceLevel {
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub price: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub size: f64,
+}
+
+/// Full snapshot of one token's book.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BookEvent {
+    pub asset_id: String,
+    pub market: String,
+    #[serde(alias = "buys", default)]
+    pub bids: Vec<PriceLevel>,
+    #[serde(alias = "sells", default)]
+    pub asks: Vec<PriceLevel>,
+    #[serde(deserialize_with = "de::i64_from_str")]
+    pub timestamp: i64,
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+/// New aggregate size at one level of one token's book (`size` 0 removes it).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PriceChange {
+    pub asset_id: String,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub price: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub size: f64,
+    pub side: Side,
+    #[serde(default)]
+    pub hash: Option<String>,
+    #[serde(default, deserialize_with = "de::opt_f64_from_str")]
+    pub best_bid: Option<f64>,
+    #[serde(default, deserialize_with = "de::opt_f64_from_str")]
+    pub best_ask: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceChangeEvent {
+    pub market: String,
+    pub timestamp: i64,
+    pub changes: Vec<PriceChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TickSizeChangeEvent {
+    pub asset_id: String,
+    pub market: String,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub old_tick_size: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub new_tick_size: f64,
+    #[serde(deserialize_with = "de::i64_from_str")]
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LastTradePriceEvent {
+    pub asset_id: String,
+    pub market: String,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub price: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub size: f64,
+    pub side: Side,
+    #[serde(default, deserialize_with = "de::opt_f64_from_str")]
+    pub fee_rate_bps: Option<f64>,
+    #[serde(deserialize_with = "de::i64_from_str")]
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketEvent {
+    Book(BookEvent),
+    PriceChange(PriceChangeEvent),
+    TickSizeChange(TickSizeChangeEvent),
+    LastTradePrice(LastTradePriceEvent),
+    /// An `event_type` this client does not model yet.
+    Unknown { event_type: String },
+    /// A known `event_type` whose payload did not match the expected shape.
+    Malformed { event_type: String, error: String },
+}
+
+/// Both the current (`price_changes`) and legacy (`asset_id` + `changes`)
+/// shapes of `price_change`.
+#[derive(Deserialize)]
+struct RawPriceChange {
+    market: String,
+    #[serde(deserialize_with = "de::i64_from_str")]
+    timestamp: i64,
+    #[serde(default)]
+    price_changes: Vec<PriceChange>,
+    #[serde(default)]
+    asset_id: Option<String>,
+    #[serde(default)]
+    hash: Option<String>,
+    #[serde(default)]
+    changes: Vec<LegacyChange>,
+}
+
+#[derive(Deserialize)]
+struct LegacyChange {
+    #[serde(deserialize_with = "de::f64_from_str")]
+    price: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    size: f64,
+    side: Side,
+}
+
+impl From<RawPriceChange> for PriceChangeEvent {
+    fn from(raw: RawPriceChange) -> Self {
+        let mut changes = raw.price_changes;
+        if let Some(asset_id) = raw.asset_id {
+            changes.extend(raw.changes.into_iter().map(|c| PriceChange {
+                asset_id: asset_id.clone(),
+                price: c.price,
+                size: c.size,
+                side: c.side,
+                hash: raw.hash.clone(),
+                best_bid: None,
+                best_ask: None,
+            }));
+        }
+        PriceChangeEvent { market: raw.market, timestamp: raw.timestamp, changes }
+    }
+}
+
+/// Subscription frame for the outcome tokens `asset_ids`.
+pub fn subscribe_message(asset_ids: &[String]) -> String {
+    serde_json::json!({ "assets_ids": asset_ids, "type": "market" }).to_string()
+}
+
+/// Parse one text frame into events. Keepalive replies carry none; other
+/// non-JSON frames (`INVALID OPERATION`) are returned as the error so callers
+/// can log them.
+pub fn parse_message(text: &str) -> Result<Vec<MarketEvent>, String> {
+    if text.trim() == super::PONG {
+        return Ok(Vec::new());
+    }
+    let value: Value = serde_json::from_str(text).map_err(|_| text.trim().to_string())?;
+    Ok(match value {
+        Value::Array(items) => items.into_iter().map(parse_event).collect(),
+        other => vec![parse_event(other)],
+    })
+}
+
+fn parse_event(value: Value) -> MarketEvent {
+    let event_type = value.get("event_type").and_then(Value::as_str).unwrap_or_default().to_string();
+    let parsed = match event_type.as_str() {
+        "book" => serde_json::from_value(value).map(MarketEvent::Book),
+        "price_change" => serde_json::from_value::<RawPriceChange>(value).map(|raw| MarketEvent::PriceChange(raw.into())),
+        "tick_size_change" => serde_json::from_value(value).map(MarketEvent::TickSizeChange),
+        "last_trade_price" => serde_json::from_value(value).map(MarketEvent::LastTradePrice),
+        _ => return MarketEvent::Unknown { event_type },
+    };
+    parsed.unwrap_or_else(|e| MarketEvent::Malformed { event_type, error: e.to_string() })
+}
+
+/// Connect to the market channel under `base_url` and subscribe to `asset_ids`.
+pub async fn connect_market_ws(base_url: &str, asset_ids: &[String]) -> Result<WsStream, FeedError> {
+    let url = format!("{}/market", base_url.trim_end_matches('/'));
+    let (mut ws_stream, _) = connect_async(url).await?;
+    ws_stream.send(Message::Text(subscribe_message(asset_ids))).await?;
+    println!("Connected to Polymarket WS for {} tokens", asset_ids.len());
+    Ok(ws_stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/polymarket/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    #[test]
+    fn parses_book_snapshot_arrays() {
+        let events = parse_message(&fixture("book.json")).unwrap();
+        let [MarketEvent::Book(book)] = events.as_slice() else { panic!("{:?}", events) };
+        assert_eq!(book.timestamp, 1_729_084_877_448);
+        assert_eq!(book.bids.len(), 3);
+        assert_eq!(book.asks[2], PriceLevel { price: 0.51, size: 150.5 });
+        assert_eq!(book.hash.as_deref(), Some("3cd4d61e042c81560c9037ece0c61f3b1a8fbbdd"));
+    }
+
+    #[test]
+    fn parses_both_price_change_shapes() {
+        let events = parse_message(&fixture("price_change.json")).unwrap();
+        let [MarketEvent::PriceChange(event)] = events.as_slice() else { panic!("{:?}", events) };
+        assert_eq!(event.changes.len(), 2);
+        assert_eq!(event.changes[1].side, Side::Sell);
+        assert_eq!(event.changes[0].best_ask, Some(0.51));
+
+        let events = parse_message(&fixture("price_change_legacy.json")).unwrap();
+        let [MarketEvent::PriceChange(event)] = events.as_slice() else { panic!("{:?}", events) };
+        assert_eq!(event.changes.len(), 3);
+        assert!(event.changes.iter().all(|c| c.asset_id.starts_with("6581") && c.hash.is_some()));
+        assert_eq!((event.changes[2].price, event.changes[2].size), (0.3, 0.0));
+    }
+
+    #[test]
+    fn parses_tick_size_and_last_trade() {
+        let events = parse_message(&fixture("tick_size_change.json")).unwrap();
+        let [MarketEvent::TickSizeChange(event)] = events.as_slice() else { panic!("{:?}", events) };
+        assert_eq!((event.old_tick_size, event.new_tick_size), (0.01, 0.001));
+
+        let events = parse_message(&fixture("last_trade_price.json")).unwrap();
+        let [MarketEvent::LastTradePrice(event)] = events.as_slice() else { panic!("{:?}", events) };
+        assert_eq!((event.price, event.side, event.fee_rate_bps), (0.456, Side::Buy, Some(0.0)));
+    }
+
+    #[test]
+    fn unknown_and_malformed_events_do_not_fail_the_frame() {
+        assert_eq!(
+            parse_message(&fixture("unknown.json")).unwrap(),
+            vec![MarketEvent::Unknown { event_type: "best_bid_ask".to_string() }]
+        );
+        let events = parse_message(r#"{"event_type":"book","asset_id":"1"}"#).unwrap();
+        assert!(matches!(events.as_slice(), [MarketEvent::Malformed { .. }]));
+        assert_eq!(parse_message("INVALID OPERATION"), Err("INVALID OPERATION".to_string()));
+        assert_eq!(parse_message("PONG"), Ok(Vec::new()));
+    }
+}
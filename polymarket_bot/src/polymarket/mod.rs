@@ -0,0 +1,65 @@
+//! Polymarket CLOB connectivity.
+
+pub mod auth;
+pub mod book;
+pub mod clob;
+pub mod eip712;
+pub mod market_ws;
+pub mod order;
+pub mod user_ws;
+#[cfg(test)]
+pub(crate) mod mock_http;
+
+use crate::supervisor::Keepalive;
+
+/// Both channels answer the `PING` text frame with this one.
+pub const PONG: &str = "PONG";
+
+/// The market and user channels drop sockets that send no `PING` for a while.
+pub fn keepalive() -> Keepalive {
+    Keepalive { text: Some("PING"), ..Keepalive::default() }
+}
+
+/// Serde helpers for the CLOB's habit of sending numbers as strings.
+pub(crate) mod de {
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrNum<T> {
+        Str(String),
+        Num(T),
+    }
+
+    pub fn f64_from_str<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+        match StrOrNum::<f64>::deserialize(d)? {
+            StrOrNum::Str(s) => s.trim().parse().map_err(serde::de::Error::custom),
+            StrOrNum::Num(n) => Ok(n),
+        }
+    }
+
+    pub fn opt_f64_from_str<'de, D: Deserializer<'de>>(d: D) -> Result<Option<f64>, D::Error> {
+        match Option::<StrOrNum<f64>>::deserialize(d)? {
+            Some(StrOrNum::Str(s)) if s.trim().is_empty() => Ok(None),
+            Some(StrOrNum::Str(s)) => s.trim().parse().map(Some).map_err(serde::de::Error::custom),
+            Some(StrOrNum::Num(n)) => Ok(Some(n)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn opt_i64_from_str<'de, D: Deserializer<'de>>(d: D) -> Result<Option<i64>, D::Error> {
+        match Option::<StrOrNum<i64>>::deserialize(d)? {
+            Some(StrOrNum::Str(s)) if s.trim().is_empty() => Ok(None),
+            Some(StrOrNum::Str(s)) => s.trim().parse().map(Some).map_err(serde::de::Error::custom),
+            Some(StrOrNum::Num(n)) => Ok(Some(n)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn i64_from_str<'de, D: Deserializer<'de>>(d: D) -> Result<i64, D::Error> {
+        match StrOrNum::<i64>::deserialize(d)? {
+            StrOrNum::Str(s) => s.trim().parse().map_err(serde::de::Error::custom),
+            StrOrNum::Num(n) => Ok(n),
+        }
+    }
+}
@@ -0,0 +1,564 @@
+//! Polymarket CTF Exchange orders: construction and EIP-712 signing.
+//!
+//! Mirrors `py-order-utils`/`py-clob-client`: an order for `size` shares at
+//! `price` becomes integer maker/taker amounts in 6-decimal units (USDC for
+//! the collateral leg, conditional tokens for the share leg), rounded the way
+//! the CLOB expects for the token's tick size and time in force, and is signed
+//! against the regular or the neg-risk exchange contract.
+
+use rand::Rng;
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::eip712::{self, Address, Domain, Signature, SigningError, Wallet};
+use crate::config::{OrderType, SignatureType, Settings};
+use crate::strategy::Side;
+
+const ORDER_TYPE: &str = "Order(uint256 salt,address maker,address signer,address taker,uint256 tokenId,\
+uint256 makerAmount,uint256 takerAmount,uint256 expiration,uint256 nonce,uint256 feeRateBps,uint8 side,\
+uint8 signatureType)";
+
+const DOMAIN_NAME: &str = "Polymarket CTF Exchange";
+const DOMAIN_VERSION: &str = "1";
+
+/// Decimals of both USDC and the conditional tokens.
+const TOKEN_DECIMALS: u32 = 6;
+/// Share sizes are rounded down to this many decimals.
+const SIZE_DECIMALS: u32 = 2;
+
+/// Exchange contracts of one chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contracts {
+    pub exchange: Address,
+    pub neg_risk_exchange: Address,
+}
+
+/// Exchange contracts on Polygon mainnet (137) and Amoy (80002).
+pub fn contracts(chain_id: u64) -> Option<Contracts> {
+    let (exchange, neg_risk_exchange) = match chain_id {
+        137 => ("0x4bFb41d5B3570DeFd03C39a9A4D8dE6Bd8B8982E", "0xC5d563A36AE78145C45a50134d48A1215220f80a"),
+        80002 => ("0xdFE02Eb6733538f8Ea35D585af8DE5958AD99E40", "0xC5d563A36AE78145C45a50134d48A1215220f80a"),
+        _ => return None,
+    };
+    Some(Contracts {
+        exchange: exchange.parse().expect("valid address"),
+        neg_risk_exchange: neg_risk_exchange.parse().expect("valid address"),
+    })
+}
+
+impl SignatureType {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            SignatureType::Eoa => 0,
+            SignatureType::PolyProxy => 1,
+            SignatureType::PolyGnosisSafe => 2,
+        }
+    }
+}
+
+impl OrderType {
+    /// `orderType` on the wire.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderType::Gtc => "GTC",
+            OrderType::Gtd => "GTD",
+            OrderType::Fok => "FOK",
+            OrderType::Fak => "FAK",
+        }
+    }
+
+    /// Whether the order executes immediately against the book and never rests.
+    pub fn is_marketable(&self) -> bool {
+        matches!(self, OrderType::Fok | OrderType::Fak)
+    }
+}
+
+impl fmt::Display for OrderType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn side_u8(side: Side) -> u8 {
+    match side {
+        Side::Buy => 0,
+        Side::Sell => 1,
+    }
+}
+
+/// Why an order could not be built or signed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderError {
+    UnsupportedChain(u64),
+    /// Tick sizes other than 0.1, 0.01, 0.001 and 0.0001.
+    UnsupportedTickSize(f64),
+    /// Price outside `[tick_size, 1 - tick_size]`.
+    PriceOutOfRange { price: f64, tick_size: f64 },
+    /// Size rounds down to zero shares.
+    SizeTooSmall(f64),
+    /// GTD orders need an expiration; every other type must have none.
+    InvalidExpiration { order_type: OrderType, expiration: u64 },
+    Signing(SigningError),
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::UnsupportedChain(id) => write!(f, "no exchange contracts known for chain {}", id),
+            OrderError::UnsupportedTickSize(tick) => write!(f, "unsupported tick size {}", tick),
+            OrderError::PriceOutOfRange { price, tick_size } => {
+                write!(f, "price {} outside [{}, {}]", price, tick_size, 1.0 - tick_size)
+            }
+            OrderError::SizeTooSmall(size) => write!(f, "size {} rounds down to zero shares", size),
+            OrderError::InvalidExpiration { order_type, expiration } => {
+                write!(f, "expiration {} is invalid for {} orders", expiration, order_type)
+            }
+            OrderError::Signing(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
+impl From<SigningError> for OrderError {
+    fn from(e: SigningError) -> Self {
+        OrderError::Signing(e)
+    }
+}
+
+/// Price decimals for a tick size.
+fn price_decimals(tick_size: f64) -> Result<u32, OrderError> {
+    (1..=4)
+        .find(|&d| (tick_size - 10f64.powi(-(d as i32))).abs() < 1e-12)
+        .ok_or(OrderError::UnsupportedTickSize(tick_size))
+}
+
+/// Price and size of an order in integer units: price in ticks of
+/// 10^-decimals, size in hundredths of a share.
+fn order_units(price: f64, size: f64, tick_size: f64) -> Result<(u32, u64, u64), OrderError> {
+    let decimals = price_decimals(tick_size)?;
+    let price_units = (price * 10f64.powi(decimals as i32)).round();
+    if !price.is_finite() || price_units < 1.0 || price_units > 10f64.powi(decimals as i32) - 1.0 {
+        return Err(OrderError::PriceOutOfRange { price, tick_size });
+    }
+    // Tolerate binary representation error (12.34 * 100 = 1233.9999...).
+    let size_units = (size * 10f64.powi(SIZE_DECIMALS as i32) + 1e-9).floor();
+    if !size.is_finite() || size_units < 1.0 {
+        return Err(OrderError::SizeTooSmall(size));
+    }
+    Ok((decimals, price_units as u64, size_units as u64))
+}
+
+/// `(maker_amount, taker_amount)` in 6-decimal units for a limit order.
+///
+/// The price is rounded to the tick and the size down to 2 decimals, so the
+/// collateral leg `size * price` is exact at the CLOB's amount precision.
+pub fn order_amounts(side: Side, price: f64, size: f64, tick_size: f64) -> Result<(u64, u64), OrderError> {
+    let (decimals, price_units, size_units) = order_units(price, size, tick_size)?;
+    let shares = size_units * 10u64.pow(TOKEN_DECIMALS - SIZE_DECIMALS);
+    let collateral = size_units * price_units * 10u64.pow(TOKEN_DECIMALS - SIZE_DECIMALS - decimals);
+    Ok(match side {
+        Side::Buy => (collateral, shares),
+        Side::Sell => (shares, collateral),
+    })
+}
+
+/// `(maker_amount, taker_amount)` for a FOK/FAK order.
+///
+/// The CLOB takes market-style buys as a USDC amount with at most 2 decimals,
+/// so the spend `size * price` is rounded down to the cent and the share leg
+/// becomes `spend / price`, rounded down to the tick's amount precision.
+/// Sells are specified in shares and match [`order_amounts`].
+pub fn market_order_amounts(side: Side, price: f64, size: f64, tick_size: f64) -> Result<(u64, u64), OrderError> {
+    if side == Side::Sell {
+        return order_amounts(side, price, size, tick_size);
+    }
+    let (decimals, price_units, size_units) = order_units(price, size, tick_size)?;
+    let cents = size_units * price_units / 10u64.pow(decimals);
+    if cents == 0 {
+        return Err(OrderError::SizeTooSmall(size));
+    }
+    // Shares at `decimals + 2` places: cents / 100 / (price_units / 10^decimals).
+    let shares = cents * 10u64.pow(2 * decimals) / price_units;
+    Ok((
+        cents * 10u64.pow(TOKEN_DECIMALS - 2),
+        shares * 10u64.pow(TOKEN_DECIMALS - SIZE_DECIMALS - decimals),
+    ))
+}
+
+/// What the strategy wants: `size` shares of `token_id` at `price`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderArgs {
+    pub token_id: String,
+    pub side: Side,
+    pub price: f64,
+    pub size: f64,
+    /// Must equal the token's current fee rate (`/fee-rate`).
+    pub fee_rate_bps: u32,
+    pub order_type: OrderType,
+    /// Unix seconds after which a GTD order is void; 0 for every other type.
+    /// The CLOB only accepts expirations at least a minute out.
+    pub expiration: u64,
+    /// Exchange nonce; orders with a nonce below the on-chain one are void.
+    pub nonce: u64,
+}
+
+/// Unsigned CTF Exchange order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub salt: u64,
+    pub maker: Address,
+    pub signer: Address,
+    pub taker: Address,
+    /// Outcome token id as a decimal string.
+    pub token_id: String,
+    pub maker_amount: u64,
+    pub taker_amount: u64,
+    pub expiration: u64,
+    pub nonce: u64,
+    pub fee_rate_bps: u32,
+    pub side: Side,
+    pub signature_type: SignatureType,
+}
+
+impl Order {
+    /// EIP-712 `hashStruct` of the order.
+    pub fn struct_hash(&self) -> Result<[u8; 32], SigningError> {
+        Ok(eip712::hash_struct(
+            ORDER_TYPE,
+            &[
+                eip712::uint_word(self.salt),
+                self.maker.to_word(),
+                self.signer.to_word(),
+                self.taker.to_word(),
+                eip712::uint_word_from_dec(&self.token_id)?,
+                eip712::uint_word(self.maker_amount),
+                eip712::uint_word(self.taker_amount),
+                eip712::uint_word(self.expiration),
+                eip712::uint_word(self.nonce),
+                eip712::uint_word(self.fee_rate_bps as u64),
+                eip712::uint_word(side_u8(self.side) as u64),
+                eip712::uint_word(self.signature_type.as_u8() as u64),
+            ],
+        ))
+    }
+}
+
+/// Order plus signature, serialized in the shape `POST /order` expects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedOrder {
+    pub order: Order,
+    pub signature: Signature,
+    /// EIP-712 digest, which the CLOB uses as the order id.
+    pub hash: [u8; 32],
+}
+
+impl SignedOrder {
+    /// Order id as reported by the CLOB (`0x`-prefixed digest).
+    pub fn order_id(&self) -> String {
+        format!("0x{}", hex::encode(self.hash))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WireOrder<'a> {
+    salt: u64,
+    maker: Address,
+    signer: Address,
+    taker: Address,
+    token_id: &'a str,
+    maker_amount: String,
+    taker_amount: String,
+    expiration: String,
+    nonce: String,
+    fee_rate_bps: String,
+    side: Side,
+    signature_type: u8,
+    signature: String,
+}
+
+impl Serialize for SignedOrder {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let o = &self.order;
+        WireOrder {
+            salt: o.salt,
+            maker: o.maker,
+            signer: o.signer,
+            taker: o.taker,
+            token_id: &o.token_id,
+            maker_amount: o.maker_amount.to_string(),
+            taker_amount: o.taker_amount.to_string(),
+            expiration: o.expiration.to_string(),
+            nonce: o.nonce.to_string(),
+            fee_rate_bps: o.fee_rate_bps.to_string(),
+            side: o.side,
+            signature_type: o.signature_type.as_u8(),
+            signature: self.signature.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Body of `POST /order`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewOrder<'a> {
+    pub order: &'a SignedOrder,
+    /// API key of the account placing the order.
+    pub owner: &'a str,
+    pub order_type: &'static str,
+    pub post_only: bool,
+}
+
+/// Builds and signs orders for one wallet on one chain.
+#[derive(Debug, Clone)]
+pub struct OrderBuilder {
+    wallet: Wallet,
+    chain_id: u64,
+    contracts: Contracts,
+    signature_type: SignatureType,
+    funder: Address,
+    /// Last salt handed out, when salts are sequential rather than random.
+    salts: Option<Arc<AtomicU64>>,
+}
+
+impl OrderBuilder {
+    /// `funder` holds the funds; it defaults to the wallet's own address.
+    pub fn new(
+        wallet: Wallet,
+        chain_id: u64,
+        signature_type: SignatureType,
+        funder: Option<Address>,
+    ) -> Result<Self, OrderError> {
+        let contracts = contracts(chain_id).ok_or(OrderError::UnsupportedChain(chain_id))?;
+        let funder = funder.unwrap_or_else(|| wallet.address());
+        Ok(Self { wallet, chain_id, contracts, signature_type, funder, salts: None })
+    }
+
+    /// Builder for the configured wallet, or `None` if no `private_key` is set.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, OrderError> {
+        let Some(key) = &settings.private_key else { return Ok(None) };
+        let wallet = Wallet::from_hex(key.expose())?;
+        let funder = settings.funder_address.as_deref().map(str::parse).transpose()?;
+        Self::new(wallet, settings.polygon_chain_id, settings.signature_type, funder).map(Some)
+    }
+
+    /// Salt orders 1, 2, 3, ... instead of randomly, so a replay signs the
+    /// same orders every time. Clones share the sequence.
+    pub fn with_sequential_salts(mut self) -> Self {
+        self.salts = Some(Arc::new(AtomicU64::new(0)));
+        self
+    }
+
+    pub fn signer(&self) -> Address {
+        self.wallet.address()
+    }
+
+    pub fn funder(&self) -> Address {
+        self.funder
+    }
+
+    /// Signing domain of the regular or the neg-risk exchange.
+    pub fn domain(&self, neg_risk: bool) -> Domain {
+        let contract = if neg_risk { self.contracts.neg_risk_exchange } else { self.contracts.exchange };
+        Domain { name: DOMAIN_NAME, version: DOMAIN_VERSION, chain_id: self.chain_id, verifying_contract: Some(contract) }
+    }
+
+    /// Unsigned order for `args` on a token with `tick_size`, with a fresh salt.
+    pub fn build(&self, args: &OrderArgs, tick_size: f64) -> Result<Order, OrderError> {
+        if (args.order_type == OrderType::Gtd) != (args.expiration > 0) {
+            return Err(OrderError::InvalidExpiration { order_type: args.order_type, expiration: args.expiration });
+        }
+        let (maker_amount, taker_amount) = if args.order_type.is_marketable() {
+            market_order_amounts(args.side, args.price, args.size, tick_size)?
+        } else {
+            order_amounts(args.side, args.price, args.size, tick_size)?
+        };
+        let salt = match &self.salts {
+            Some(salts) => salts.fetch_add(1, Ordering::Relaxed) + 1,
+            // Same scheme as py-order-utils: now (seconds) scaled by a random fraction.
+            None => (chrono::Utc::now().timestamp() as f64 * rand::thread_rng().gen::<f64>()).round() as u64,
+        };
+        Ok(Order {
+            salt,
+            maker: self.funder,
+            signer: self.wallet.address(),
+            taker: Address::ZERO,
+            token_id: args.token_id.clone(),
+            maker_amount,
+            taker_amount,
+            expiration: args.expiration,
+            nonce: args.nonce,
+            fee_rate_bps: args.fee_rate_bps,
+            side: args.side,
+            signature_type: self.signature_type,
+        })
+    }
+
+    pub fn sign(&self, order: Order, neg_risk: bool) -> Result<SignedOrder, OrderError> {
+        let hash = self.domain(neg_risk).digest(&order.struct_hash()?);
+        let signature = self.wallet.sign_digest(&hash)?;
+        Ok(SignedOrder { order, signature, hash })
+    }
+
+    pub fn build_signed(&self, args: &OrderArgs, tick_size: f64, neg_risk: bool) -> Result<SignedOrder, OrderError> {
+        self.sign(self.build(args, tick_size)?, neg_risk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HARDHAT_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const TOKEN: &str = "71321045679252212594626385532706912750332728571942532289631379312455583992563";
+
+    fn builder() -> OrderBuilder {
+        OrderBuilder::new(Wallet::from_hex(HARDHAT_KEY).unwrap(), 137, SignatureType::Eoa, None).unwrap()
+    }
+
+    fn fixed_order(side: Side) -> Order {
+        Order {
+            salt: 479_249_096_354,
+            maker: builder().funder(),
+            signer: builder().signer(),
+            taker: Address::ZERO,
+            token_id: TOKEN.to_string(),
+            maker_amount: 50_000_000,
+            taker_amount: 100_000_000,
+            expiration: 0,
+            nonce: 0,
+            fee_rate_bps: 0,
+            side,
+            signature_type: SignatureType::Eoa,
+        }
+    }
+
+    #[test]
+    fn rounds_amounts_per_tick_size() {
+        assert_eq!(order_amounts(Side::Buy, 0.5, 100.0, 0.01), Ok((50_000_000, 100_000_000)));
+        assert_eq!(order_amounts(Side::Sell, 0.5, 100.0, 0.01), Ok((100_000_000, 50_000_000)));
+        // Size rounds down to 2 decimals, price to the tick.
+        assert_eq!(order_amounts(Side::Buy, 0.567, 12.349, 0.01), Ok((7_033_800, 12_340_000)));
+        assert_eq!(order_amounts(Side::Buy, 0.1234, 12.34, 0.0001), Ok((1_522_756, 12_340_000)));
+        assert_eq!(order_amounts(Side::Sell, 0.7, 3.0, 0.1), Ok((3_000_000, 2_100_000)));
+
+        assert_eq!(order_amounts(Side::Buy, 0.5, 1.0, 0.05), Err(OrderError::UnsupportedTickSize(0.05)));
+        assert!(matches!(order_amounts(Side::Buy, 0.999, 1.0, 0.01), Err(OrderError::PriceOutOfRange { .. })));
+        assert!(matches!(order_amounts(Side::Buy, 0.0, 1.0, 0.01), Err(OrderError::PriceOutOfRange { .. })));
+        assert_eq!(order_amounts(Side::Buy, 0.5, 0.004, 0.01), Err(OrderError::SizeTooSmall(0.004)));
+    }
+
+    #[test]
+    fn builds_orders_for_funder_and_signer() {
+        let safe: Address = "0x0000000000000000000000000000000000000001".parse().unwrap();
+        let builder =
+            OrderBuilder::new(Wallet::from_hex(HARDHAT_KEY).unwrap(), 137, SignatureType::PolyGnosisSafe, Some(safe))
+                .unwrap();
+        let args = OrderArgs {
+            token_id: TOKEN.to_string(),
+            side: Side::Buy,
+            price: 0.52,
+            size: 10.0,
+            fee_rate_bps: 0,
+            order_type: OrderType::Gtc,
+            expiration: 0,
+            nonce: 0,
+        };
+        let order = builder.build(&args, 0.01).unwrap();
+        assert_eq!((order.maker, order.signer), (safe, builder.signer()));
+        assert_eq!((order.maker_amount, order.taker_amount), (5_200_000, 10_000_000));
+        assert_eq!(order.signature_type.as_u8(), 2);
+        assert!(OrderBuilder::new(Wallet::from_hex(HARDHAT_KEY).unwrap(), 1, SignatureType::Eoa, None).is_err());
+
+        // Market-style buys spend whole cents.
+        let fok = builder.build(&OrderArgs { order_type: OrderType::Fok, price: 0.57, size: 12.34, ..args.clone() }, 0.01);
+        assert_eq!(fok.map(|o| (o.maker_amount, o.taker_amount)), Ok((7_030_000, 12_333_300)));
+
+        let gtd = OrderArgs { order_type: OrderType::Gtd, ..args.clone() };
+        assert!(matches!(builder.build(&gtd, 0.01), Err(OrderError::InvalidExpiration { .. })));
+        let order = builder.build(&OrderArgs { expiration: 1_729_085_000, ..gtd }, 0.01).unwrap();
+        assert_eq!(order.expiration, 1_729_085_000);
+        let fak = OrderArgs { order_type: OrderType::Fak, expiration: 1_729_085_000, ..args };
+        assert!(matches!(builder.build(&fak, 0.01), Err(OrderError::InvalidExpiration { .. })));
+    }
+
+    #[test]
+    fn sequential_salts_make_signing_repeatable() {
+        let args = OrderArgs {
+            token_id: TOKEN.to_string(),
+            side: Side::Buy,
+            price: 0.52,
+            size: 10.0,
+            fee_rate_bps: 0,
+            order_type: OrderType::Gtc,
+            expiration: 0,
+            nonce: 0,
+        };
+        let sign_two = || {
+            let builder = builder().with_sequential_salts();
+            let first = builder.build_signed(&args, 0.01, false).unwrap();
+            let second = builder.clone().build_signed(&args, 0.01, false).unwrap();
+            (first, second)
+        };
+        let (first, second) = sign_two();
+        assert_eq!((first.order.salt, second.order.salt), (1, 2));
+        assert_eq!(sign_two(), (first, second));
+    }
+
+    #[test]
+    fn rounds_market_buys_to_whole_cents() {
+        assert_eq!(market_order_amounts(Side::Buy, 0.5, 100.0, 0.01), Ok((50_000_000, 100_000_000)));
+        assert_eq!(market_order_amounts(Side::Buy, 0.57, 12.34, 0.01), Ok((7_030_000, 12_333_300)));
+        assert_eq!(market_order_amounts(Side::Buy, 0.123, 10.0, 0.001), Ok((1_230_000, 10_000_000)));
+        assert_eq!(market_order_amounts(Side::Buy, 0.33, 10.0, 0.01), Ok((3_300_000, 10_000_000)));
+        assert_eq!(market_order_amounts(Side::Buy, 0.33, 0.01, 0.01), Err(OrderError::SizeTooSmall(0.01)));
+        assert_eq!(market_order_amounts(Side::Sell, 0.57, 12.34, 0.01), order_amounts(Side::Sell, 0.57, 12.34, 0.01));
+    }
+
+    /// Pinned vectors for the Hardhat key, so any change to order hashing or
+    /// signing shows up offline. Each signature also recovers to the signer;
+    /// the primitives underneath are checked against the EIP-712 spec example.
+    #[test]
+    fn signs_against_regular_and_neg_risk_domains() {
+        let builder = builder();
+        let regular = builder.sign(fixed_order(Side::Buy), false).unwrap();
+        let neg_risk = builder.sign(fixed_order(Side::Buy), true).unwrap();
+        assert_ne!(regular.hash, neg_risk.hash);
+        for signed in [&regular, &neg_risk] {
+            assert_eq!(eip712::recover(&signed.hash, &signed.signature).unwrap(), builder.signer());
+        }
+        assert_eq!(regular.order_id(), REGULAR_HASH);
+        assert_eq!(regular.signature.to_string(), REGULAR_SIGNATURE);
+        assert_eq!(neg_risk.order_id(), NEG_RISK_HASH);
+        assert_eq!(neg_risk.signature.to_string(), NEG_RISK_SIGNATURE);
+    }
+
+    const REGULAR_HASH: &str = "0x2d4e37d43ce67ac26fd34fbded7ac34fdcba1b2aff632aac52b36483f1d5eeb8";
+    const REGULAR_SIGNATURE: &str = "0x4e4a18de9ac827f073445bb64331b74a5f57feed1b86424cfaa61db51ae0c0de\
+                                     291110ad3c3541ac576a93bfd35adca6f9e4861ce3d46123e56eeadf3e55fd0c1c";
+    const NEG_RISK_HASH: &str = "0xb1d77fd3871ea5e3fff30e907e82b1d19fe9e1bd189db182016c35fa9585d1e5";
+    const NEG_RISK_SIGNATURE: &str = "0xe15baa30a58ebec55410643d75389c5c30089a836220f0098342b660c2f14055\
+                                      516841d79c05ff628e10f9f4dd608a5f85cd9413641008f31ad8708d37457eae1b";
+
+    #[test]
+    fn serializes_in_clob_wire_format() {
+        let signed = builder().sign(fixed_order(Side::Sell), false).unwrap();
+        let body = NewOrder { order: &signed, owner: "key-1", order_type: OrderType::Fak.as_str(), post_only: false };
+        let body = serde_json::to_value(&body).unwrap();
+        assert_eq!((&body["owner"], &body["orderType"], &body["postOnly"]), (&"key-1".into(), &"FAK".into(), &false.into()));
+        let json = &body["order"];
+        assert_eq!(json["salt"], 479_249_096_354u64);
+        assert_eq!(json["maker"], "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+        assert_eq!(json["taker"], "0x0000000000000000000000000000000000000000");
+        assert_eq!(json["tokenId"], TOKEN);
+        assert_eq!(json["makerAmount"], "50000000");
+        assert_eq!(json["feeRateBps"], "0");
+        assert_eq!(json["side"], "SELL");
+        assert_eq!(json["signatureType"], 0);
+        assert_eq!(json["signature"].as_str().unwrap().len(), 132);
+    }
+}
@@ -0,0 +1,307 @@
+//! Polymarket `user` websocket channel.
+//!
+//! After connecting to `<polymarket_ws_url>/user` the client authenticates
+//! with its API credentials in the subscription frame and names the markets
+//! (condition ids) it wants. The server then pushes `order` events when one of
+//! the account's orders is placed, partially matched or cancelled, and `trade`
+//! events as a match moves from `MATCHED` through `MINED` to `CONFIRMED` (or
+//! `RETRYING`/`FAILED`). Framing matches the market channel: one object per
+//! frame or several in a JSON array.
+
+use futures_util::SinkExt;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+use super::auth::ApiCredentials;
+use super::de;
+use crate::feeds::{FeedError, WsStream};
+use crate::strategy::Side;
+
+/// What happened to an order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum OrderEventType {
+    Placement,
+    /// Part of the order matched; `size_matched` is the new total.
+    Update,
+    Cancellation,
+}
+
+/// State of one of the account's orders after a change.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OrderEvent {
+    /// Order id (the order hash).
+    pub id: String,
+    pub market: String,
+    pub asset_id: String,
+    pub side: Side,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub price: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub original_size: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub size_matched: f64,
+    #[serde(default)]
+    pub outcome: String,
+    /// API key that owns the order.
+    #[serde(default)]
+    pub owner: String,
+    #[serde(rename = "type")]
+    pub kind: OrderEventType,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    pub associate_trades: Vec<String>,
+    #[serde(deserialize_with = "de::i64_from_str")]
+    pub timestamp: i64,
+}
+
+impl OrderEvent {
+    pub fn remaining(&self) -> f64 {
+        (self.original_size - self.size_matched).max(0.0)
+    }
+}
+
+/// Settlement progress of a trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TradeStatus {
+    /// Matched by the operator; not yet sent on chain.
+    Matched,
+    Mined,
+    Confirmed,
+    /// The settlement transaction failed and is being resubmitted.
+    Retrying,
+    /// Settlement failed for good; the match is void.
+    Failed,
+}
+
+/// One resting order filled against the taker.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MakerOrder {
+    pub order_id: String,
+    pub asset_id: String,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub matched_amount: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub price: f64,
+    #[serde(default)]
+    pub outcome: String,
+    #[serde(default)]
+    pub owner: String,
+    /// Not sent by older servers; see [`TradeEvent::fills`].
+    #[serde(default)]
+    pub side: Option<Side>,
+}
+
+/// A match involving one of the account's orders, as taker or maker.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TradeEvent {
+    /// Trade id.
+    pub id: String,
+    pub market: String,
+    /// Token the taker traded.
+    pub asset_id: String,
+    /// Taker side.
+    pub side: Side,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub price: f64,
+    #[serde(deserialize_with = "de::f64_from_str")]
+    pub size: f64,
+    pub status: TradeStatus,
+    pub taker_order_id: String,
+    /// API key of the taker.
+    #[serde(default)]
+    pub owner: String,
+    #[serde(default)]
+    pub maker_orders: Vec<MakerOrder>,
+    /// Fee rate of the taker order; makers pay none.
+    #[serde(default, deserialize_with = "de::opt_f64_from_str")]
+    pub fee_rate_bps: Option<f64>,
+    #[serde(deserialize_with = "de::i64_from_str")]
+    pub timestamp: i64,
+}
+
+/// The account's share of a trade, from the account's point of view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub trade_id: String,
+    pub market: String,
+    pub order_id: String,
+    pub asset_id: String,
+    pub side: Side,
+    pub price: f64,
+    pub size: f64,
+    /// USDC paid in fees.
+    pub fee: f64,
+    pub status: TradeStatus,
+}
+
+/// Polymarket's taker fee: `base rate * min(price, 1 - price) * size`.
+pub fn taker_fee(fee_rate_bps: f64, price: f64, size: f64) -> f64 {
+    fee_rate_bps / 10_000.0 * price.min(1.0 - price) * size
+}
+
+impl TradeEvent {
+    /// Fills belonging to the API key `owner`: the taker leg if it placed the
+    /// taker order, plus each of its maker orders. A maker on the same token
+    /// traded the opposite side; one on the complementary token (a mint or
+    /// merge match) traded the same side as the taker.
+    pub fn fills(&self, owner: &str) -> Vec<Fill> {
+        let fill = |order_id: &str, asset_id: &str, side, price, size, fee| Fill {
+            trade_id: self.id.clone(),
+            market: self.market.clone(),
+            order_id: order_id.to_string(),
+            asset_id: asset_id.to_string(),
+            side,
+            price,
+            size,
+            fee,
+            status: self.status,
+        };
+        let mut fills = Vec::new();
+        if self.owner == owner {
+            let fee = taker_fee(self.fee_rate_bps.unwrap_or(0.0), self.price, self.size);
+            fills.push(fill(&self.taker_order_id, &self.asset_id, self.side, self.price, self.size, fee));
+        }
+        for maker in self.maker_orders.iter().filter(|m| m.owner == owner) {
+            let side = maker.side.unwrap_or(match (maker.asset_id == self.asset_id, self.side) {
+                (false, side) => side,
+                (true, Side::Buy) => Side::Sell,
+                (true, Side::Sell) => Side::Buy,
+            });
+            fills.push(fill(&maker.order_id, &maker.asset_id, side, maker.price, maker.matched_amount, 0.0));
+        }
+        fills
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserEvent {
+    Order(OrderEvent),
+    Trade(TradeEvent),
+    /// An `event_type` this client does not model yet.
+    Unknown { event_type: String },
+    /// A known `event_type` whose payload did not match the expected shape.
+    Malformed { event_type: String, error: String },
+}
+
+fn null_as_empty<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(Option::<Vec<String>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Authenticated subscription frame for the markets (condition ids) `markets`.
+pub fn subscribe_message(credentials: &ApiCredentials, markets: &[String]) -> String {
+    serde_json::json!({
+        "auth": {
+            "apiKey": credentials.api_key,
+            "secret": credentials.secret.expose(),
+            "passphrase": credentials.passphrase.expose(),
+        },
+        "markets": markets,
+        "type": "user",
+    })
+    .to_string()
+}
+
+/// Parse one text frame into events. Keepalive replies carry none; other
+/// non-JSON frames are returned as the error so callers can log them.
+pub fn parse_message(text: &str) -> Result<Vec<UserEvent>, String> {
+    if text.trim() == super::PONG {
+        return Ok(Vec::new());
+    }
+    let value: Value = serde_json::from_str(text).map_err(|_| text.trim().to_string())?;
+    Ok(match value {
+        Value::Array(items) => items.into_iter().map(parse_event).collect(),
+        other => vec![parse_event(other)],
+    })
+}
+
+fn parse_event(value: Value) -> UserEvent {
+    let event_type = value.get("event_type").and_then(Value::as_str).unwrap_or_default().to_string();
+    let parsed = match event_type.as_str() {
+        "order" => serde_json::from_value(value).map(UserEvent::Order),
+        "trade" => serde_json::from_value(value).map(UserEvent::Trade),
+        _ => return UserEvent::Unknown { event_type },
+    };
+    parsed.unwrap_or_else(|e| UserEvent::Malformed { event_type, error: e.to_string() })
+}
+
+/// Connect to the user channel under `base_url` and subscribe to `markets`.
+pub async fn connect_user_ws(
+    base_url: &str,
+    credentials: &ApiCredentials,
+    markets: &[String],
+) -> Result<WsStream, FeedError> {
+    let url = format!("{}/user", base_url.trim_end_matches('/'));
+    let (mut ws_stream, _) = connect_async(url).await?;
+    ws_stream.send(Message::Text(subscribe_message(credentials, markets))).await?;
+    println!("Connected to Polymarket user channel for {} markets", markets.len());
+    Ok(ws_stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Secret;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/polymarket/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    #[test]
+    fn subscribes_with_credentials() {
+        let credentials = ApiCredentials {
+            api_key: "key-1".to_string(),
+            secret: Secret::new("c2VjcmV0"),
+            passphrase: Secret::new("pass"),
+        };
+        let frame: Value = serde_json::from_str(&subscribe_message(&credentials, &["0xabc".to_string()])).unwrap();
+        assert_eq!(frame["auth"]["apiKey"], "key-1");
+        assert_eq!(frame["auth"]["secret"], "c2VjcmV0");
+        assert_eq!(frame["auth"]["passphrase"], "pass");
+        assert_eq!(frame["markets"][0], "0xabc");
+        assert_eq!(frame["type"], "user");
+    }
+
+    #[test]
+    fn parses_order_events() {
+        let events = parse_message(&fixture("user_order.json")).unwrap();
+        let [UserEvent::Order(placed), UserEvent::Order(updated)] = events.as_slice() else { panic!("{:?}", events) };
+        assert_eq!((placed.kind, placed.side, placed.price), (OrderEventType::Placement, Side::Sell, 0.57));
+        assert!(placed.associate_trades.is_empty());
+        assert_eq!(updated.kind, OrderEventType::Update);
+        assert_eq!((updated.size_matched, updated.remaining()), (4.0, 6.0));
+        assert_eq!(updated.associate_trades, ["28c4d2eb-bbea-40e7-a9f0-b2fdb56b2c2e"]);
+    }
+
+    #[test]
+    fn attributes_trade_fills_to_the_owner() {
+        let events = parse_message(&fixture("user_trade.json")).unwrap();
+        let [UserEvent::Trade(trade)] = events.as_slice() else { panic!("{:?}", events) };
+        assert_eq!((trade.status, trade.side, trade.size), (TradeStatus::Matched, Side::Buy, 10.0));
+
+        let taker = trade.fills("taker-key");
+        assert_eq!(taker.len(), 1);
+        assert_eq!(taker[0].order_id, trade.taker_order_id);
+        assert_eq!((taker[0].side, taker[0].price, taker[0].size), (Side::Buy, 0.57, 10.0));
+        // 1% of min(0.57, 0.43) per share.
+        assert!((taker[0].fee - 0.043).abs() < 1e-12);
+
+        // One maker sold the same token, the other bought the complement.
+        let makers = trade.fills("maker-key");
+        let sides: Vec<(Side, f64, f64)> = makers.iter().map(|f| (f.side, f.price, f.size)).collect();
+        assert_eq!(sides, [(Side::Sell, 0.57, 6.0), (Side::Buy, 0.43, 4.0)]);
+        assert!(makers.iter().all(|f| f.fee == 0.0));
+        assert!(trade.fills("someone-else").is_empty());
+    }
+
+    #[test]
+    fn unknown_and_malformed_events_do_not_fail_the_frame() {
+        let events = parse_message(r#"[{"event_type":"heartbeat"},{"event_type":"trade","id":"1"}]"#).unwrap();
+        assert_eq!(events[0], UserEvent::Unknown { event_type: "heartbeat".to_string() });
+        assert!(matches!(events[1], UserEvent::Malformed { .. }));
+        assert_eq!(parse_message("PONG"), Ok(Vec::new()));
+        assert_eq!(parse_message("INVALID OPERATION"), Err("INVALID OPERATION".to_string()));
+    }
+}
@@ -0,0 +1,246 @@
+//! Share positions and PnL per outcome token.
+//!
+//! Positions use average cost: buys raise the share count and blend their
+//! price into the average, sells and redemptions realize
+//! `(price - average cost) * shares`. Unrealized PnL marks the remaining
+//! shares to the local book mid; a token without a two-sided book is marked
+//! at its average cost, i.e. contributes nothing until it can be priced.
+//! Trading fees are deducted from realized PnL.
+
+use std::collections::BTreeMap;
+
+use crate::polymarket::book::BookStore;
+use crate::strategy::Side;
+
+/// Shares below which a position counts as flat.
+const SHARE_EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Position {
+    /// Market (condition id) the token belongs to.
+    pub market: String,
+    /// Net shares; negative only if more was sold than the bot bought.
+    pub shares: f64,
+    pub avg_cost: f64,
+    pub realized_pnl: f64,
+}
+
+impl Position {
+    /// Add a trade of `size` shares at `price`.
+    pub fn apply(&mut self, side: Side, price: f64, size: f64) {
+        let signed = match side {
+            Side::Buy => size,
+            Side::Sell => -size,
+        };
+        let same_direction = self.shares == 0.0 || self.shares.signum() == signed.signum();
+        if same_direction {
+            let total = self.shares + signed;
+            self.avg_cost = (self.avg_cost * self.shares.abs() + price * size) / total.abs();
+            self.shares = total;
+            return;
+        }
+        // Reducing (and possibly flipping) the position.
+        let closed = size.min(self.shares.abs());
+        self.realized_pnl += (price - self.avg_cost) * closed * self.shares.signum();
+        self.shares += signed;
+        if self.shares.abs() < SHARE_EPSILON {
+            self.shares = 0.0;
+            self.avg_cost = 0.0;
+        } else if self.shares.signum() == signed.signum() {
+            self.avg_cost = price;
+        }
+    }
+
+    /// Fees count against realized PnL; a negative fee refunds one.
+    pub fn charge_fee(&mut self, fee: f64) {
+        self.realized_pnl -= fee;
+    }
+
+    /// Settle every share at `payout` (1 for the winning outcome, 0 otherwise).
+    pub fn redeem(&mut self, payout: f64) {
+        self.realized_pnl += (payout - self.avg_cost) * self.shares;
+        self.shares = 0.0;
+        self.avg_cost = 0.0;
+    }
+
+    pub fn unrealized_pnl(&self, mark: f64) -> f64 {
+        (mark - self.avg_cost) * self.shares
+    }
+}
+
+/// Realized and unrealized PnL of one market, or of the whole account.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pnl {
+    pub realized: f64,
+    pub unrealized: f64,
+    /// Open shares valued at the mark.
+    pub exposure: f64,
+}
+
+impl Pnl {
+    pub fn total(&self) -> f64 {
+        self.realized + self.unrealized
+    }
+
+    fn add(&mut self, other: Pnl) {
+        self.realized += other.realized;
+        self.unrealized += other.unrealized;
+        self.exposure += other.exposure;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Positions {
+    by_token: BTreeMap<String, Position>,
+}
+
+impl Positions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, market: &str, asset_id: &str, side: Side, price: f64, size: f64) {
+        let position = self.by_token.entry(asset_id.to_string()).or_default();
+        position.market = market.to_string();
+        position.apply(side, price, size);
+    }
+
+    pub fn charge_fee(&mut self, asset_id: &str, fee: f64) {
+        if let Some(position) = self.by_token.get_mut(asset_id) {
+            position.charge_fee(fee);
+        }
+    }
+
+    /// Settle `asset_id` at `payout` per share.
+    pub fn redeem(&mut self, asset_id: &str, payout: f64) {
+        if let Some(position) = self.by_token.get_mut(asset_id) {
+            position.redeem(payout);
+        }
+    }
+
+    pub fn get(&self, asset_id: &str) -> Option<&Position> {
+        self.by_token.get(asset_id)
+    }
+
+    /// Net shares of `asset_id`.
+    pub fn shares(&self, asset_id: &str) -> f64 {
+        self.get(asset_id).map_or(0.0, |p| p.shares)
+    }
+
+    /// Every token ever traded, keyed by token id.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Position)> {
+        self.by_token.iter()
+    }
+
+    /// What the shares held of `asset_ids` cost, long positions only.
+    pub fn cost_basis(&self, asset_ids: &[&str]) -> f64 {
+        asset_ids
+            .iter()
+            .filter_map(|asset_id| self.get(asset_id))
+            .filter(|p| p.shares > 0.0)
+            .map(|p| p.shares * p.avg_cost)
+            .sum()
+    }
+
+    /// Markets with shares still open.
+    pub fn open_markets(&self) -> Vec<&str> {
+        let mut markets: Vec<&str> =
+            self.by_token.values().filter(|p| p.shares != 0.0).map(|p| p.market.as_str()).collect();
+        markets.sort_unstable();
+        markets.dedup();
+        markets
+    }
+
+    fn token_pnl(asset_id: &str, position: &Position, books: &BookStore) -> Pnl {
+        let mark = books.get(asset_id).and_then(|b| b.mid()).unwrap_or(position.avg_cost);
+        Pnl {
+            realized: position.realized_pnl,
+            unrealized: position.unrealized_pnl(mark),
+            exposure: position.shares * mark,
+        }
+    }
+
+    /// PnL per market, marked to `books`.
+    pub fn market_pnl(&self, books: &BookStore) -> BTreeMap<&str, Pnl> {
+        let mut markets: BTreeMap<&str, Pnl> = BTreeMap::new();
+        for (asset_id, position) in &self.by_token {
+            markets.entry(position.market.as_str()).or_default().add(Self::token_pnl(asset_id, position, books));
+        }
+        markets
+    }
+
+    pub fn total_pnl(&self, books: &BookStore) -> Pnl {
+        let mut total = Pnl::default();
+        for pnl in self.market_pnl(books).into_values() {
+            total.add(pnl);
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polymarket::market_ws::{BookEvent, PriceLevel};
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn average_cost_and_realized_pnl() {
+        let mut position = Position::default();
+        position.apply(Side::Buy, 0.40, 10.0);
+        position.apply(Side::Buy, 0.50, 30.0);
+        assert_close(position.avg_cost, 0.475);
+        position.apply(Side::Sell, 0.60, 20.0);
+        assert_close(position.shares, 20.0);
+        assert_close(position.realized_pnl, 2.5);
+        assert_close(position.unrealized_pnl(0.55), 1.5);
+
+        // Selling through flat opens the other way at the sale price.
+        position.apply(Side::Sell, 0.50, 25.0);
+        assert_close(position.shares, -5.0);
+        assert_close(position.avg_cost, 0.50);
+        assert_close(position.realized_pnl, 3.0);
+    }
+
+    #[test]
+    fn redemption_realizes_the_payout() {
+        let mut positions = Positions::new();
+        positions.apply("0xabc", "yes", Side::Buy, 0.30, 10.0);
+        positions.apply("0xabc", "no", Side::Buy, 0.60, 5.0);
+        assert_eq!(positions.open_markets(), ["0xabc"]);
+        positions.redeem("yes", 1.0);
+        positions.redeem("no", 0.0);
+        let books = BookStore::new();
+        let total = positions.total_pnl(&books);
+        assert_close(total.realized, 7.0 - 3.0);
+        assert_eq!((total.unrealized, total.exposure), (0.0, 0.0));
+        assert!(positions.open_markets().is_empty());
+    }
+
+    #[test]
+    fn marks_to_the_book_mid_per_market() {
+        let mut positions = Positions::new();
+        positions.apply("0xabc", "yes", Side::Buy, 0.40, 10.0);
+        positions.apply("0xdef", "other", Side::Buy, 0.20, 10.0);
+        let mut books = BookStore::new();
+        let book = BookEvent {
+            asset_id: "yes".to_string(),
+            market: "0xabc".to_string(),
+            bids: vec![PriceLevel { price: 0.48, size: 100.0 }],
+            asks: vec![PriceLevel { price: 0.52, size: 100.0 }],
+            timestamp: 1,
+            hash: None,
+        };
+        books.apply_resync(&book, 1);
+
+        let markets = positions.market_pnl(&books);
+        assert_close(markets["0xabc"].unrealized, 1.0);
+        assert_close(markets["0xabc"].exposure, 5.0);
+        // No book for `other`: marked at cost.
+        assert_eq!(markets["0xdef"].unrealized, 0.0);
+        assert_close(positions.total_pnl(&books).total(), 1.0);
+    }
+}
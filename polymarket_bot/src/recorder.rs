@@ -0,0 +1,328 @@
+//! Raw market-data capture.
+//!
+//! Every websocket frame of a recorded stream is appended to a JSON-lines
+//! capture file as one [`CapturedFrame`], together with the connects and
+//! disconnects around them so gaps in the data are visible. Frames are
+//! stamped where the supervisor receives them, with both a monotonic clock
+//! (for intervals) and the wall clock.
+//!
+//! Files are written to `recorder.directory` and named after the time they
+//! were opened; once one reaches `max_file_mb` it is closed and the next one
+//! started, and only the newest `max_files` are kept. Writing happens on its
+//! own thread so a slow disk never stalls a feed: if the writer falls behind,
+//! frames are dropped and counted rather than queued without bound.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, io};
+
+use crate::config::RecorderConfig;
+
+/// Frames buffered between the feeds and the writer thread.
+const CHANNEL_CAPACITY: usize = 65_536;
+
+/// Longest a written frame may sit in the write buffer.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+const FILE_PREFIX: &str = "capture-";
+const FILE_SUFFIX: &str = ".jsonl";
+
+/// What happened on the stream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CapturedEvent {
+    Connected,
+    /// One text frame, exactly as received.
+    Frame { text: String },
+    Disconnected { reason: String },
+}
+
+/// One line of a capture file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedFrame {
+    /// Stream name, e.g. `binance` or `polymarket`.
+    pub source: String,
+    /// Nanoseconds since the recorder started, on the monotonic clock.
+    pub mono_ns: u64,
+    /// Wall-clock receive time in milliseconds since the epoch.
+    pub wall_ms: i64,
+    #[serde(flatten)]
+    pub event: CapturedEvent,
+}
+
+/// The capture directory could not be set up.
+#[derive(Debug)]
+pub struct RecorderError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for RecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "capture directory {}: {}", self.path.display(), self.reason)
+    }
+}
+
+impl std::error::Error for RecorderError {}
+
+enum Command {
+    Write(CapturedFrame),
+    /// Write out everything received so far, then acknowledge.
+    Flush(mpsc::Sender<()>),
+}
+
+/// Handle to the capture writer; clones share one writer and clock.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    tx: SyncSender<Command>,
+    started: Instant,
+    recorded: Arc<AtomicU64>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Recorder {
+    /// Create the capture directory and start the writer thread.
+    pub fn start(config: &RecorderConfig) -> Result<Self, RecorderError> {
+        Self::with_max_file_bytes(config, config.max_file_mb * 1024 * 1024)
+    }
+
+    fn with_max_file_bytes(config: &RecorderConfig, max_file_bytes: u64) -> Result<Self, RecorderError> {
+        let directory = config.directory.clone();
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| RecorderError { path: directory.clone(), reason: e.to_string() })?;
+        let (tx, rx) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let writer = Writer {
+            directory,
+            max_file_bytes,
+            max_files: config.max_files,
+            file: None,
+            written: 0,
+            opened: 0,
+            failing: false,
+        };
+        std::thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || writer.run(rx))
+            .map_err(|e| RecorderError { path: config.directory.clone(), reason: e.to_string() })?;
+        Ok(Self {
+            tx,
+            started: Instant::now(),
+            recorded: Arc::new(AtomicU64::new(0)),
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Stamp `event` from `source` with the current time and queue it.
+    pub fn record(&self, source: &str, event: CapturedEvent) {
+        let frame = CapturedFrame {
+            source: source.to_string(),
+            mono_ns: self.started.elapsed().as_nanos() as u64,
+            wall_ms: chrono::Utc::now().timestamp_millis(),
+            event,
+        };
+        match self.tx.try_send(Command::Write(frame)) {
+            Ok(()) => self.recorded.fetch_add(1, Ordering::Relaxed),
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => self.dropped.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// Frames queued for writing so far.
+    pub fn recorded(&self) -> u64 {
+        self.recorded.load(Ordering::Relaxed)
+    }
+
+    /// Frames lost because the writer fell behind or stopped.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Block until every frame recorded so far is on disk.
+    pub fn flush(&self) {
+        let (ack_tx, ack_rx) = mpsc::channel();
+        if self.tx.send(Command::Flush(ack_tx)).is_ok() {
+            let _ = ack_rx.recv();
+        }
+    }
+}
+
+/// Capture files in `directory`, oldest first.
+pub fn capture_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX)
+        })
+        .collect();
+    // Names start with a sortable UTC timestamp.
+    files.sort();
+    Ok(files)
+}
+
+struct Writer {
+    directory: PathBuf,
+    max_file_bytes: u64,
+    max_files: Option<usize>,
+    file: Option<BufWriter<File>>,
+    written: u64,
+    /// Files opened so far, to keep names unique within one second.
+    opened: u64,
+    /// Whether the last write failed, so a broken disk is reported once.
+    failing: bool,
+}
+
+impl Writer {
+    fn run(mut self, rx: Receiver<Command>) {
+        loop {
+            match rx.recv_timeout(FLUSH_INTERVAL) {
+                Ok(Command::Write(frame)) => {
+                    let result = self.write(&frame);
+                    self.report(result);
+                }
+                Ok(Command::Flush(ack)) => {
+                    let result = self.flush();
+                    self.report(result);
+                    let _ = ack.send(());
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let result = self.flush();
+                    self.report(result);
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let _ = self.flush();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn report(&mut self, result: io::Result<()>) {
+        match result {
+            Err(e) if !self.failing => {
+                eprintln!("Recorder: cannot write to {}: {}", self.directory.display(), e);
+                self.failing = true;
+            }
+            Err(_) => {}
+            Ok(()) => self.failing = false,
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&mut self, frame: &CapturedFrame) -> io::Result<()> {
+        let mut line = serde_json::to_string(frame).expect("frame serializes");
+        line.push('\n');
+        if self.file.is_none() || (self.written > 0 && self.written + line.len() as u64 > self.max_file_bytes) {
+            self.rotate()?;
+        }
+        let file = self.file.as_mut().expect("opened above");
+        file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+        self.opened += 1;
+        let name = format!(
+            "{}{}-{:04}{}",
+            FILE_PREFIX,
+            chrono::Utc::now().format("%Y%m%dT%H%M%SZ"),
+            self.opened,
+            FILE_SUFFIX
+        );
+        let path = self.directory.join(name);
+        self.file = Some(BufWriter::new(File::options().create(true).append(true).open(&path)?));
+        self.written = 0;
+        println!("Recording market data to {}", path.display());
+
+        if let Some(max_files) = self.max_files {
+            let files = capture_files(&self.directory)?;
+            for old in files.iter().take(files.len().saturating_sub(max_files)) {
+                std::fs::remove_file(old)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str, max_files: Option<usize>) -> RecorderConfig {
+        let directory = std::env::temp_dir().join(format!("recorder-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        RecorderConfig { enabled: true, directory, max_file_mb: 1, max_files }
+    }
+
+    fn read(path: &Path) -> Vec<CapturedFrame> {
+        let text = std::fs::read_to_string(path).unwrap();
+        text.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn writes_stamped_frames_as_json_lines() {
+        let config = config("lines", None);
+        let recorder = Recorder::start(&config).unwrap();
+        recorder.record("polymarket", CapturedEvent::Connected);
+        recorder.record("binance", CapturedEvent::Frame { text: r#"{"e":"trade"}"#.to_string() });
+        recorder.record("polymarket", CapturedEvent::Disconnected { reason: "end of stream".to_string() });
+        recorder.flush();
+
+        let files = capture_files(&config.directory).unwrap();
+        assert_eq!(files.len(), 1);
+        let frames = read(&files[0]);
+        let events: Vec<(&str, &CapturedEvent)> = frames.iter().map(|f| (f.source.as_str(), &f.event)).collect();
+        assert_eq!(
+            events,
+            [
+                ("polymarket", &CapturedEvent::Connected),
+                ("binance", &CapturedEvent::Frame { text: r#"{"e":"trade"}"#.to_string() }),
+                ("polymarket", &CapturedEvent::Disconnected { reason: "end of stream".to_string() }),
+            ]
+        );
+        assert!(frames.windows(2).all(|w| w[0].mono_ns <= w[1].mono_ns));
+        assert!(frames[0].wall_ms > 1_700_000_000_000);
+        assert_eq!((recorder.recorded(), recorder.dropped()), (3, 0));
+
+        let line = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(line.starts_with(r#"{"source":"polymarket","mono_ns":"#), "{}", line);
+    }
+
+    #[test]
+    fn rotates_and_keeps_the_newest_files() {
+        let config = config("rotate", Some(2));
+        let recorder = Recorder::with_max_file_bytes(&config, 200).unwrap();
+        for i in 0..10 {
+            recorder.record("binance", CapturedEvent::Frame { text: format!("frame-{:03}-{}", i, "x".repeat(100)) });
+        }
+        recorder.flush();
+
+        let files = capture_files(&config.directory).unwrap();
+        assert_eq!(files.len(), 2);
+        let frames: Vec<CapturedFrame> = files.iter().flat_map(|f| read(f)).collect();
+        let texts: Vec<&str> = frames
+            .iter()
+            .map(|f| match &f.event {
+                CapturedEvent::Frame { text } => &text[..9],
+                other => panic!("{:?}", other),
+            })
+            .collect();
+        // One frame per file; the oldest eight were rotated out.
+        assert_eq!(texts, ["frame-008", "frame-009"]);
+    }
+}
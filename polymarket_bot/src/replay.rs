@@ -0,0 +1,388 @@
+//! Deterministic replay of capture files.
+//!
+//! [`Replay`] feeds captured frames to an [`Engine`] the way the live loop
+//! does: spot frames through their venue's parser, market-channel frames into
+//! the books, and the resulting orders to a [`PaperGateway`]. The clock is the
+//! capture's receive time of the frame being replayed, and order salts are
+//! sequential, so one capture always replays to the same decisions and fills.
+//!
+//! The `backtest` settings delay the simulated exchange: an order reaches the
+//! paper book `decision_to_exchange_ms` after the decision, so it fills
+//! against the book as it was by then, and its response, fills and order
+//! updates reach the engine `exchange_to_ack_ms` after they happened. Every
+//! token pays the configured taker fee.
+//!
+//! Frames keep their recorded spacing, optionally sped up, or are replayed
+//! back to back. Books that diverge wait for the next websocket snapshot, as
+//! there is no REST endpoint to resync from, and no kill switch is consulted.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::config::{SignatureType, Settings};
+use crate::engine::{Decision, Engine, OrderSubmission, Submission};
+use crate::execution::paper::PaperGateway;
+use crate::feeds::{self, SpotFeed};
+use crate::polymarket::clob::TokenParams;
+use crate::polymarket::eip712::Wallet;
+use crate::polymarket::order::{OrderBuilder, OrderError};
+use crate::polymarket::user_ws::{Fill, UserEvent};
+use crate::positions::Pnl;
+use crate::recorder::{self, CapturedEvent, CapturedFrame};
+
+/// Source name the market channel is recorded under.
+const MARKET_SOURCE: &str = "polymarket";
+
+/// Signs replayed orders; nothing checks the signatures.
+const REPLAY_KEY: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+/// Tick size assumed for tokens without trading parameters.
+const DEFAULT_TICK_SIZE: f64 = 0.01;
+
+/// A capture file could not be read.
+#[derive(Debug)]
+pub struct CaptureError {
+    pub path: PathBuf,
+    /// 1-based line of a malformed frame.
+    pub line: Option<usize>,
+    pub reason: String,
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "capture {}:{}: {}", self.path.display(), line, self.reason),
+            None => write!(f, "capture {}: {}", self.path.display(), self.reason),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// Every frame of the capture file at `path`, in recorded order.
+pub fn read_capture(path: &Path) -> Result<Vec<CapturedFrame>, CaptureError> {
+    let error = |line, reason: String| CaptureError { path: path.to_path_buf(), line, reason };
+    let text = std::fs::read_to_string(path).map_err(|e| error(None, e.to_string()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| error(Some(i + 1), e.to_string())))
+        .collect()
+}
+
+/// Frames of every capture file in `paths`, where a directory stands for the
+/// capture files in it, oldest first.
+pub fn read_captures(paths: &[PathBuf]) -> Result<Vec<CapturedFrame>, CaptureError> {
+    let mut frames = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let files = recorder::capture_files(path)
+                .map_err(|e| CaptureError { path: path.clone(), line: None, reason: e.to_string() })?;
+            for file in files {
+                frames.extend(read_capture(&file)?);
+            }
+        } else {
+            frames.extend(read_capture(path)?);
+        }
+    }
+    Ok(frames)
+}
+
+/// How fast frames are replayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pace {
+    /// Keep the recorded gaps between frames, divided by `speed`.
+    Recorded { speed: f64 },
+    /// No waiting between frames.
+    AsFastAsPossible,
+}
+
+/// What a replay did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Frames replayed.
+    pub frames: u64,
+    /// Frames from sources no configured market uses.
+    pub ignored: u64,
+    pub decisions: Vec<Decision>,
+    pub fills: Vec<Fill>,
+    /// PnL per market at the end, marked against the final books.
+    pub pnl: BTreeMap<String, Pnl>,
+    pub total: Pnl,
+}
+
+/// What the simulated exchange does some time after the frame that caused it.
+enum Pending {
+    /// A submitted order reaches the exchange.
+    Arrival(OrderSubmission),
+    /// The exchange's response to a submission reaches the engine.
+    Response(Submission),
+    /// A trade or order update reaches the engine.
+    Event(UserEvent),
+}
+
+pub struct Replay {
+    engine: Engine,
+    events: mpsc::UnboundedReceiver<UserEvent>,
+    /// Time of what is being replayed; the paper exchange's clock.
+    clock: Arc<AtomicI64>,
+    feeds: HashMap<&'static str, Arc<dyn SpotFeed>>,
+    pace: Pace,
+    to_exchange_ms: i64,
+    to_ack_ms: i64,
+    /// Keyed on when it happens, then on the order it was scheduled in.
+    pending: BTreeMap<(i64, u64), Pending>,
+    scheduled: u64,
+}
+
+impl Replay {
+    /// A replay of the markets in `settings`, as fast as possible, on a paper
+    /// account trading every token at the default tick size, with the
+    /// latencies and fee of `settings.backtest`.
+    pub fn new(settings: &Settings) -> Result<Self, OrderError> {
+        let clock = Arc::new(AtomicI64::new(0));
+        let paper_clock = Arc::clone(&clock);
+        let (paper, events) = PaperGateway::new();
+        let paper = paper.with_clock(Arc::new(move || paper_clock.load(Ordering::SeqCst)));
+        let orders = OrderBuilder::new(Wallet::from_hex(REPLAY_KEY)?, settings.polygon_chain_id, SignatureType::Eoa, None)?
+            .with_sequential_salts();
+        let fee_rate_bps = settings.backtest.fee_rate_bps;
+        let token_params = settings
+            .markets
+            .iter()
+            .flat_map(|m| [&m.yes_token_id, &m.no_token_id])
+            .map(|token_id| {
+                (token_id.clone(), TokenParams { tick_size: DEFAULT_TICK_SIZE, neg_risk: false, fee_rate_bps })
+            })
+            .collect();
+        let engine = Engine::paper(settings, paper).with_orders(Some(orders)).with_token_params(token_params);
+        let feeds = feeds::from_settings(settings).into_iter().map(|feed| (feed.venue(), feed)).collect();
+        Ok(Self {
+            engine,
+            events,
+            clock,
+            feeds,
+            pace: Pace::AsFastAsPossible,
+            to_exchange_ms: settings.backtest.decision_to_exchange_ms as i64,
+            to_ack_ms: settings.backtest.exchange_to_ack_ms as i64,
+            pending: BTreeMap::new(),
+            scheduled: 0,
+        })
+    }
+
+    pub fn with_pace(mut self, pace: Pace) -> Self {
+        self.pace = pace;
+        self
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Replay `frames` in order and report what the engine did with them.
+    pub async fn run(mut self, frames: &[CapturedFrame]) -> ReplayReport {
+        self.run_observed(frames, |_, _| {}).await
+    }
+
+    /// [`Replay::run`], showing `observe` the engine and the time after every
+    /// frame and once more when everything pending has happened.
+    pub async fn run_observed(
+        &mut self,
+        frames: &[CapturedFrame],
+        mut observe: impl FnMut(&Engine, i64),
+    ) -> ReplayReport {
+        let mut report = ReplayReport::default();
+        // Local instant the frame stamped `.1` was replayed at; reset whenever
+        // the monotonic stamps restart with a new recording session.
+        let mut origin: Option<(tokio::time::Instant, u64)> = None;
+        for frame in frames {
+            if let Pace::Recorded { speed } = self.pace {
+                match origin {
+                    Some((start, first)) if frame.mono_ns >= first => {
+                        let offset = Duration::from_nanos(frame.mono_ns - first).div_f64(speed);
+                        tokio::time::sleep_until(start + offset).await;
+                    }
+                    _ => origin = Some((tokio::time::Instant::now(), frame.mono_ns)),
+                }
+            }
+            let now_ms = frame.wall_ms;
+            self.step(frame, &mut report).await;
+            observe(&self.engine, now_ms);
+        }
+        // Orders still on their way arrive, and their answers are heard.
+        if let Some(&(last_ms, _)) = self.pending.keys().next_back() {
+            self.advance(i64::MAX).await;
+            observe(&self.engine, last_ms);
+        }
+
+        let positions = self.engine.account().positions();
+        let books = self.engine.books();
+        report.pnl = positions.market_pnl(books).into_iter().map(|(market, pnl)| (market.to_string(), pnl)).collect();
+        report.total = positions.total_pnl(books);
+        report.fills = self.engine.oms().fills().iter().map(|(_, fill)| fill.clone()).collect();
+        report
+    }
+
+    async fn step(&mut self, frame: &CapturedFrame, report: &mut ReplayReport) {
+        let now_ms = frame.wall_ms;
+        self.advance(now_ms).await;
+        self.clock.store(now_ms, Ordering::SeqCst);
+        match (frame.source.as_str(), &frame.event) {
+            (MARKET_SOURCE, CapturedEvent::Frame { text }) => {
+                report.frames += 1;
+                for (token_id, reason) in self.engine.on_market_frame(text, now_ms) {
+                    eprintln!("Book for {} diverged: {}; waiting for the next snapshot", token_id, reason);
+                }
+            }
+            (MARKET_SOURCE, CapturedEvent::Disconnected { .. }) => self.engine.on_market_disconnected(),
+            (source, CapturedEvent::Frame { text }) => {
+                let Some(feed) = self.feeds.get(source) else {
+                    report.ignored += 1;
+                    return;
+                };
+                report.frames += 1;
+                let Some(tick) = feed.parse(text, now_ms) else { return };
+                let outcome = self.engine.on_spot_tick(&tick, false);
+                report.decisions.extend(outcome.decisions);
+                for submission in outcome.submissions {
+                    self.schedule(now_ms + self.to_exchange_ms, Pending::Arrival(submission));
+                }
+            }
+            // Spot feeds reconnect on their own and keep no state here.
+            _ => {}
+        }
+        // Resting orders the frame's books crossed.
+        self.collect_events(now_ms);
+        self.advance(now_ms).await;
+    }
+
+    fn schedule(&mut self, at_ms: i64, pending: Pending) {
+        self.scheduled += 1;
+        self.pending.insert((at_ms, self.scheduled), pending);
+    }
+
+    /// Queue what the paper exchange reported at `at_ms` for delivery.
+    fn collect_events(&mut self, at_ms: i64) {
+        while let Ok(event) = self.events.try_recv() {
+            self.schedule(at_ms + self.to_ack_ms, Pending::Event(event));
+        }
+    }
+
+    /// Let everything scheduled up to `until_ms` happen, in order.
+    async fn advance(&mut self, until_ms: i64) {
+        while let Some(entry) = self.pending.first_entry() {
+            let at_ms = entry.key().0;
+            if at_ms > until_ms {
+                break;
+            }
+            self.clock.store(at_ms, Ordering::SeqCst);
+            match entry.remove() {
+                Pending::Arrival(submission) => {
+                    let OrderSubmission { id, order, order_type, post_only } = submission;
+                    let gateway = Arc::clone(self.engine.gateway());
+                    let result = gateway.post_order(&order, order_type, post_only).await;
+                    self.schedule(at_ms + self.to_ack_ms, Pending::Response((id, result)));
+                    self.collect_events(at_ms);
+                }
+                Pending::Response(submission) => {
+                    self.engine.on_submission(submission, at_ms);
+                }
+                Pending::Event(event) => self.engine.on_user_event(event, at_ms),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Verdict;
+    use crate::strategy::Side;
+
+    const YES: &str = "1111";
+    const NO: &str = "2222";
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay").join(name)
+    }
+
+    fn settings() -> Settings {
+        let yaml = r#"
+spot_venue: binance
+markets:
+  - market_id: "0xabc"
+    symbol: "btc/usdt"
+    yes_token_id: "1111"
+    no_token_id: "2222"
+    threshold_pct: 0.005
+    order_type: fok
+risk:
+  max_notional_per_trade: 10.0
+  self_slippage_buffer_pct: 0.0
+"#;
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    async fn replay() -> ReplayReport {
+        let frames = read_capture(&fixture("capture.jsonl")).unwrap();
+        Replay::new(&settings()).unwrap().run(&frames).await
+    }
+
+    #[tokio::test]
+    async fn replays_a_capture_into_decisions_and_fills() {
+        let report = replay().await;
+        assert_eq!((report.frames, report.ignored), (6, 1));
+
+        let decisions: Vec<(i64, &str, f64, &Verdict)> = report
+            .decisions
+            .iter()
+            .map(|d| (d.at_ms, d.signal.token_id.as_str(), d.signal.price, &d.verdict))
+            .collect();
+        assert_eq!(
+            decisions,
+            [
+                (1_700_000_002_000, YES, 0.5, &Verdict::Submitted { id: 1 }),
+                (1_700_000_004_000, NO, 0.25, &Verdict::Submitted { id: 2 }),
+            ]
+        );
+        let fills: Vec<(&str, Side, f64, f64)> =
+            report.fills.iter().map(|f| (f.asset_id.as_str(), f.side, f.price, f.size)).collect();
+        assert_eq!(fills, [(YES, Side::Buy, 0.5, 20.0), (NO, Side::Buy, 0.25, 40.0)]);
+
+        // Marked at the final mids: YES 0.625, NO 0.225.
+        let pnl = report.pnl["0xabc"];
+        assert!((pnl.unrealized - (20.0 * 0.125 - 40.0 * 0.025)).abs() < 1e-9, "{:?}", pnl);
+        assert_eq!(report.total, pnl);
+    }
+
+    #[tokio::test]
+    async fn identical_captures_replay_identically() {
+        assert_eq!(replay().await, replay().await);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_recorded_spacing() {
+        let frames = read_capture(&fixture("capture.jsonl")).unwrap();
+        let paced = Replay::new(&settings()).unwrap().with_pace(Pace::Recorded { speed: 100.0 });
+        let started = std::time::Instant::now();
+        let report = paced.run(&frames).await;
+        // Five recorded seconds at 100x.
+        assert!(started.elapsed() >= Duration::from_millis(50), "{:?}", started.elapsed());
+        assert_eq!(report, replay().await);
+    }
+
+    #[test]
+    fn reports_the_line_of_a_malformed_frame() {
+        let path = std::env::temp_dir().join(format!("replay-malformed-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "{\"source\":\"binance\",\"mono_ns\":1,\"wall_ms\":1,\"kind\":\"connected\"}\nnot json\n")
+            .unwrap();
+        let error = read_capture(&path).unwrap_err();
+        assert_eq!(error.line, Some(2));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+//! Pre-trade risk checks every order must pass before it is signed.
+//!
+//! Limits come from the `risk:` block and each market's `max_position`.
+//! Exposure counts both what is already held of the market's two tokens (at
+//! cost) and what open orders could still add, so a burst of unfilled orders
+//! cannot exceed the limit.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+use crate::config::{MarketConfig, RiskConfig, Settings};
+use crate::oms::Oms;
+use crate::polymarket::book::BookStore;
+use crate::positions::Positions;
+use crate::strategy::{Side, TradeSignal};
+
+const MINUTE_MS: i64 = 60_000;
+
+/// Why an order was refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskRejection {
+    MaxNotional { notional: f64, limit: f64 },
+    RateLimit { limit: u32 },
+    MaxPosition { market_id: String, exposure: f64, limit: f64 },
+    MaxOpenOrders { limit: usize },
+    /// No two-sided book to check the price against.
+    NoMidPrice { token_id: String },
+    PriceBand { price: f64, mid: f64, band: f64 },
+    Cooldown { token_id: String, remaining_ms: i64 },
+}
+
+impl RiskRejection {
+    /// Stable label for logs and metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RiskRejection::MaxNotional { .. } => "max_notional",
+            RiskRejection::RateLimit { .. } => "rate_limit",
+            RiskRejection::MaxPosition { .. } => "max_position",
+            RiskRejection::MaxOpenOrders { .. } => "max_open_orders",
+            RiskRejection::NoMidPrice { .. } => "no_mid_price",
+            RiskRejection::PriceBand { .. } => "price_band",
+            RiskRejection::Cooldown { .. } => "cooldown",
+        }
+    }
+}
+
+impl fmt::Display for RiskRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskRejection::MaxNotional { notional, limit } => {
+                write!(f, "notional {:.2} exceeds max_notional_per_trade {:.2}", notional, limit)
+            }
+            RiskRejection::RateLimit { limit } => write!(f, "already {} orders in the last minute", limit),
+            RiskRejection::MaxPosition { market_id, exposure, limit } => {
+                write!(f, "exposure on {} would reach {:.2}, above max_position {:.2}", market_id, exposure, limit)
+            }
+            RiskRejection::MaxOpenOrders { limit } => write!(f, "already {} open orders", limit),
+            RiskRejection::NoMidPrice { token_id } => write!(f, "no two-sided book for {}", token_id),
+            RiskRejection::PriceBand { price, mid, band } => {
+                write!(f, "price {:.4} is more than {} from the mid {:.4}", price, band, mid)
+            }
+            RiskRejection::Cooldown { token_id, remaining_ms } => {
+                write!(f, "{} is cooling down for another {}ms", token_id, remaining_ms)
+            }
+        }
+    }
+}
+
+/// State the checks look at besides the order itself.
+pub struct RiskContext<'a> {
+    pub oms: &'a Oms,
+    pub positions: &'a Positions,
+    pub books: &'a BookStore,
+}
+
+pub struct RiskEngine {
+    config: RiskConfig,
+    /// Acceptance times within the last minute, oldest first.
+    recent: VecDeque<i64>,
+    last_order_ms: HashMap<String, i64>,
+    rejections: BTreeMap<&'static str, u64>,
+}
+
+impl RiskEngine {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            config: settings.risk.clone(),
+            recent: VecDeque::new(),
+            last_order_ms: HashMap::new(),
+            rejections: BTreeMap::new(),
+        }
+    }
+
+    /// Check `signal` on `market` against every limit; an accepted order
+    /// counts toward the rate limit and starts the token's cooldown.
+    pub fn check(
+        &mut self,
+        signal: &TradeSignal,
+        market: &MarketConfig,
+        ctx: &RiskContext,
+        now_ms: i64,
+    ) -> Result<(), RiskRejection> {
+        match self.evaluate(signal, market, ctx, now_ms) {
+            Ok(()) => {
+                self.recent.push_back(now_ms);
+                self.last_order_ms.insert(signal.token_id.clone(), now_ms);
+                Ok(())
+            }
+            Err(rejection) => {
+                *self.rejections.entry(rejection.kind()).or_default() += 1;
+                Err(rejection)
+            }
+        }
+    }
+
+    /// Rejections so far, by [`RiskRejection::kind`].
+    pub fn rejections(&self) -> &BTreeMap<&'static str, u64> {
+        &self.rejections
+    }
+
+    fn evaluate(
+        &mut self,
+        signal: &TradeSignal,
+        market: &MarketConfig,
+        ctx: &RiskContext,
+        now_ms: i64,
+    ) -> Result<(), RiskRejection> {
+        let limit = self.config.max_notional_per_trade;
+        if signal.notional > limit {
+            return Err(RiskRejection::MaxNotional { notional: signal.notional, limit });
+        }
+
+        while self.recent.front().is_some_and(|t| now_ms - t >= MINUTE_MS) {
+            self.recent.pop_front();
+        }
+        let limit = self.config.max_trades_per_minute;
+        if self.recent.len() >= limit as usize {
+            return Err(RiskRejection::RateLimit { limit });
+        }
+
+        if let Some(&last) = self.last_order_ms.get(&signal.token_id) {
+            let remaining_ms = last + self.config.token_cooldown_ms - now_ms;
+            if remaining_ms > 0 {
+                return Err(RiskRejection::Cooldown { token_id: signal.token_id.clone(), remaining_ms });
+            }
+        }
+
+        let limit = self.config.max_open_orders;
+        if ctx.oms.open_orders().count() >= limit {
+            return Err(RiskRejection::MaxOpenOrders { limit });
+        }
+
+        // Sells only reduce what is held. Holdings go by token: fills name the
+        // market by condition id, which need not be how the config names it.
+        let limit = market.max_position;
+        let held = ctx.positions.cost_basis(&[&market.yes_token_id, &market.no_token_id]);
+        let exposure = held + ctx.oms.open_notional(&signal.market_id);
+        if signal.side == Side::Buy && exposure + signal.notional > limit {
+            return Err(RiskRejection::MaxPosition {
+                market_id: signal.market_id.clone(),
+                exposure: exposure + signal.notional,
+                limit,
+            });
+        }
+
+        let book = ctx.books.get(&signal.token_id);
+        let Some(mid) = book.and_then(|b| b.mid()) else {
+            return Err(RiskRejection::NoMidPrice { token_id: signal.token_id.clone() });
+        };
+        let band = self.config.price_band;
+        if (signal.price - mid).abs() > band {
+            return Err(RiskRejection::PriceBand { price: signal.price, mid, band });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OrderType;
+    use crate::polymarket::market_ws::{BookEvent, PriceLevel};
+    use crate::strategy::Direction;
+
+    fn settings() -> Settings {
+        let yaml = r#"
+markets:
+  - market_id: "0xabc"
+    symbol: "btc/usdt"
+    yes_token_id: "yes"
+    no_token_id: "no"
+    max_position: 100.0
+risk:
+  max_notional_per_trade: 50.0
+  max_trades_per_minute: 3
+  max_open_orders: 2
+  price_band: 0.05
+  token_cooldown_ms: 1000
+"#;
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn signal(token_id: &str, price: f64, notional: f64) -> TradeSignal {
+        TradeSignal {
+            market_id: "0xabc".to_string(),
+            token_id: token_id.to_string(),
+            side: Side::Buy,
+            direction: Direction::Up,
+            delta: 0.02,
+            spot_price: 100.0,
+            price,
+            size: notional / price,
+            notional,
+        }
+    }
+
+    fn books() -> BookStore {
+        let mut books = BookStore::new();
+        for token in ["yes", "no"] {
+            let book = BookEvent {
+                asset_id: token.to_string(),
+                market: "0xabc".to_string(),
+                bids: vec![PriceLevel { price: 0.49, size: 1000.0 }],
+                asks: vec![PriceLevel { price: 0.51, size: 1000.0 }],
+                timestamp: 1,
+                hash: None,
+            };
+            books.apply_resync(&book, 1);
+        }
+        books
+    }
+
+    #[test]
+    fn enforces_trade_limits() {
+        let settings = settings();
+        let market = &settings.markets[0];
+        let mut risk = RiskEngine::new(&settings);
+        let (oms, positions, books) = (Oms::new(), Positions::new(), books());
+        let ctx = RiskContext { oms: &oms, positions: &positions, books: &books };
+
+        assert_eq!(
+            risk.check(&signal("yes", 0.51, 60.0), market, &ctx, 0),
+            Err(RiskRejection::MaxNotional { notional: 60.0, limit: 50.0 })
+        );
+        let rejection = risk.check(&signal("yes", 0.60, 10.0), market, &ctx, 0);
+        assert!(matches!(rejection, Err(RiskRejection::PriceBand { .. })));
+        let rejection = risk.check(&signal("other", 0.51, 10.0), market, &ctx, 0);
+        assert!(matches!(rejection, Err(RiskRejection::NoMidPrice { .. })));
+
+        assert_eq!(risk.check(&signal("yes", 0.51, 10.0), market, &ctx, 0), Ok(()));
+        assert_eq!(
+            risk.check(&signal("yes", 0.51, 10.0), market, &ctx, 400),
+            Err(RiskRejection::Cooldown { token_id: "yes".to_string(), remaining_ms: 600 })
+        );
+        assert_eq!(risk.check(&signal("no", 0.51, 10.0), market, &ctx, 500), Ok(()));
+        assert_eq!(risk.check(&signal("yes", 0.51, 10.0), market, &ctx, 1_000), Ok(()));
+        assert_eq!(
+            risk.check(&signal("no", 0.51, 10.0), market, &ctx, 2_000),
+            Err(RiskRejection::RateLimit { limit: 3 })
+        );
+        // The first order leaves the one-minute window.
+        assert_eq!(risk.check(&signal("no", 0.51, 10.0), market, &ctx, 60_000), Ok(()));
+
+        assert_eq!(risk.rejections().get("cooldown"), Some(&1));
+        assert_eq!(risk.rejections().values().sum::<u64>(), 5);
+    }
+
+    #[test]
+    fn counts_holdings_and_open_orders_toward_limits() {
+        let settings = settings();
+        let market = &settings.markets[0];
+        let mut risk = RiskEngine::new(&settings);
+        let books = books();
+        let mut positions = Positions::new();
+        positions.apply("0xabc", "yes", Side::Buy, 0.5, 100.0);
+        let mut oms = Oms::new();
+        let id = oms.create(&signal("no", 0.5, 40.0), OrderType::Gtc, 0, 0);
+        oms.signed(id, "0x1", 0).unwrap();
+
+        let ctx = RiskContext { oms: &oms, positions: &positions, books: &books };
+        assert_eq!(
+            risk.check(&signal("yes", 0.51, 20.0), market, &ctx, 0),
+            Err(RiskRejection::MaxPosition { market_id: "0xabc".to_string(), exposure: 110.0, limit: 100.0 })
+        );
+
+        oms.create(&signal("no", 0.5, 1.0), OrderType::Gtc, 0, 0);
+        let ctx = RiskContext { oms: &oms, positions: &Positions::new(), books: &books };
+        assert_eq!(
+            risk.check(&signal("yes", 0.51, 5.0), market, &ctx, 0),
+            Err(RiskRejection::MaxOpenOrders { limit: 2 })
+        );
+    }
+
+    #[test]
+    fn counts_holdings_by_token_whatever_market_the_fills_name() {
+        let settings = settings();
+        let market = &settings.markets[0];
+        let mut risk = RiskEngine::new(&settings);
+        let (books, oms) = (books(), Oms::new());
+        // The user channel reports the condition id, not the configured id.
+        let mut positions = Positions::new();
+        let condition_id = "0x5f65177b394277fd294cd75650044e32ba009a95022d88a0c1d565897d72f8f1";
+        positions.apply(condition_id, "no", Side::Buy, 0.5, 190.0);
+        positions.apply("0xabc", "other-market-token", Side::Buy, 0.5, 1000.0);
+
+        let ctx = RiskContext { oms: &oms, positions: &positions, books: &books };
+        assert_eq!(risk.check(&signal("yes", 0.51, 5.0), market, &ctx, 0), Ok(()));
+        assert_eq!(
+            risk.check(&signal("no", 0.51, 10.0), market, &ctx, 0),
+            Err(RiskRejection::MaxPosition { market_id: "0xabc".to_string(), exposure: 105.0, limit: 100.0 })
+        );
+    }
+}
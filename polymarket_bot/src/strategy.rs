@@ -0,0 +1,355 @@
+//! Latency strategy: turns spot moves into Polymarket outcome-token orders.
+//!
+//! One [`MarketState`] is kept per configured market. Spot ticks are routed to
+//! every market that tracks the tick's symbol, and a move beyond the market's
+//! `threshold_pct` selects the outcome token that benefits from the move, the
+//! same way `LatencyStrategy._select_quote` does on the Python side.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::config::{MarketConfig, RiskConfig, Settings};
+use crate::feeds::SpotTick;
+use crate::oms::Oms;
+use crate::polymarket::book::BookStore;
+
+/// Direction of the spot move that triggered a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+        })
+    }
+}
+
+/// Order side on the CLOB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Order the strategy wants placed in response to a spot move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeSignal {
+    pub market_id: String,
+    pub token_id: String,
+    pub side: Side,
+    pub direction: Direction,
+    /// Relative spot move versus the market's reference price.
+    pub delta: f64,
+    pub spot_price: f64,
+    /// Limit price for the outcome token.
+    pub price: f64,
+    /// Outcome token shares.
+    pub size: f64,
+    /// `price * size`, the quote currency committed.
+    pub notional: f64,
+}
+
+/// Mutable per-market state.
+#[derive(Debug, Clone)]
+pub struct MarketState {
+    pub config: MarketConfig,
+    pub reference_price: Option<f64>,
+    /// Tick time the reference price was taken at.
+    pub reference_at_ms: i64,
+}
+
+impl MarketState {
+    pub fn new(config: MarketConfig) -> Self {
+        Self { config, reference_price: None, reference_at_ms: 0 }
+    }
+
+    /// Outcome token that pays out if the spot keeps moving in `direction`.
+    pub fn select_token(&self, direction: Direction) -> &str {
+        match (direction, self.config.yes_is_upside) {
+            (Direction::Up, true) | (Direction::Down, false) => &self.config.yes_token_id,
+            (Direction::Up, false) | (Direction::Down, true) => &self.config.no_token_id,
+        }
+    }
+}
+
+/// Holds every configured market, keyed on its lower-cased spot symbol.
+pub struct LatencyStrategy {
+    symbol_markets: HashMap<String, Vec<MarketState>>,
+    risk: RiskConfig,
+}
+
+impl LatencyStrategy {
+    pub fn new(settings: &Settings) -> Self {
+        let mut symbol_markets: HashMap<String, Vec<MarketState>> = HashMap::new();
+        for market in &settings.markets {
+            symbol_markets
+                .entry(market.symbol.to_lowercase())
+                .or_default()
+                .push(MarketState::new(market.clone()));
+        }
+        Self { symbol_markets, risk: settings.risk.clone() }
+    }
+
+    /// Distinct spot symbols the configured markets depend on.
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.symbol_markets.keys().map(String::as_str)
+    }
+
+    pub fn markets(&self) -> impl Iterator<Item = &MarketState> {
+        self.symbol_markets.values().flatten()
+    }
+
+    /// Feed a normalized spot tick to every market tracking its symbol and
+    /// return the orders that should be fired, priced off the local `books`.
+    /// Tokens with an order still open in `oms` are left alone. Orders are
+    /// sized to `max_notional_per_trade`; whether they fit the limits is up to
+    /// the risk engine. Venue does not matter here.
+    pub fn on_spot_tick(&mut self, tick: &SpotTick, books: &BookStore, oms: &Oms) -> Vec<TradeSignal> {
+        let Some(markets) = self.symbol_markets.get_mut(&tick.symbol.to_lowercase()) else {
+            return Vec::new();
+        };
+        markets
+            .iter_mut()
+            .filter_map(|state| Self::process_market(state, &self.risk, books, oms, tick))
+            .collect()
+    }
+
+    fn process_market(
+        state: &mut MarketState,
+        risk: &RiskConfig,
+        books: &BookStore,
+        oms: &Oms,
+        tick: &SpotTick,
+    ) -> Option<TradeSignal> {
+        let (price, now_ms) = (tick.last, tick.received_at_ms);
+        // Moves only count against a reference younger than the window.
+        let expired = state
+            .config
+            .reference_window_ms
+            .is_some_and(|window| now_ms.saturating_sub(state.reference_at_ms) > window as i64);
+        let reference = match state.reference_price {
+            Some(reference) if reference > 0.0 && !expired => reference,
+            _ => {
+                state.reference_price = Some(price);
+                state.reference_at_ms = now_ms;
+                return None;
+            }
+        };
+
+        let delta = (price - reference) / reference;
+        if delta.abs() < state.config.threshold_pct {
+            return None;
+        }
+        let direction = if delta > 0.0 { Direction::Up } else { Direction::Down };
+        // Keep the move pending until the previous order on the token is done.
+        if oms.has_open_order(state.select_token(direction)) {
+            return None;
+        }
+        // Whatever happens next, the move has been acted upon.
+        state.reference_price = Some(price);
+        state.reference_at_ms = now_ms;
+
+        let token_id = state.select_token(direction);
+        // No liquidity to lift on the stale side means nothing to do.
+        let best_ask = books.get(token_id)?.best_ask()?;
+        let limit_price = (best_ask.price * (1.0 + risk.self_slippage_buffer_pct)).min(1.0);
+
+        let size = (risk.max_notional_per_trade / limit_price).min(best_ask.size);
+        if size <= 0.0 {
+            return None;
+        }
+        let notional = size * limit_price;
+
+        let signal = TradeSignal {
+            market_id: state.config.market_id.clone(),
+            token_id: token_id.to_string(),
+            side: Side::Buy,
+            direction,
+            delta,
+            spot_price: price,
+            price: limit_price,
+            size,
+            notional,
+        };
+        Some(signal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polymarket::market_ws::{BookEvent, PriceLevel};
+
+    /// Two markets on BTC, YES up on the first and YES down on the second,
+    /// plus one on ETH.
+    fn settings() -> Settings {
+        let yaml = r#"
+markets:
+  - market_id: "up"
+    symbol: "BTC/USDT"
+    yes_token_id: "up-yes"
+    no_token_id: "up-no"
+    threshold_pct: 0.005
+  - market_id: "down"
+    symbol: "btc/usdt"
+    yes_token_id: "down-yes"
+    no_token_id: "down-no"
+    yes_is_upside: false
+    threshold_pct: 0.02
+  - market_id: "eth"
+    symbol: "eth/usdt"
+    yes_token_id: "eth-yes"
+    no_token_id: "eth-no"
+    threshold_pct: 0.005
+risk:
+  max_notional_per_trade: 10.0
+  self_slippage_buffer_pct: 0.0
+"#;
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn books() -> BookStore {
+        let mut books = BookStore::new();
+        for token in ["up-yes", "up-no", "down-yes", "down-no", "eth-yes", "eth-no"] {
+            let book = BookEvent {
+                asset_id: token.to_string(),
+                market: token.split('-').next().unwrap().to_string(),
+                bids: vec![PriceLevel { price: 0.45, size: 1000.0 }],
+                asks: vec![PriceLevel { price: 0.5, size: 1000.0 }],
+                timestamp: 1,
+                hash: None,
+            };
+            books.apply_resync(&book, 1);
+        }
+        books
+    }
+
+    fn tick(symbol: &str, last: f64) -> SpotTick {
+        tick_at(symbol, last, 1_000)
+    }
+
+    fn tick_at(symbol: &str, last: f64, received_at_ms: i64) -> SpotTick {
+        SpotTick {
+            venue: "binance",
+            symbol: symbol.to_string(),
+            bid: None,
+            ask: None,
+            last,
+            exchange_ts_ms: None,
+            sequence: None,
+            received_at_ms,
+        }
+    }
+
+    /// Market and token of every signal `prices` raise on BTC, after a tick
+    /// on a symbol no market tracks.
+    fn signals(prices: &[f64]) -> Vec<(String, String)> {
+        let mut strategy = LatencyStrategy::new(&settings());
+        let (books, oms) = (books(), Oms::new());
+        assert!(strategy.on_spot_tick(&tick("sol/usdt", 1.0), &books, &oms).is_empty());
+        let mut signals = Vec::new();
+        for &price in prices {
+            for signal in strategy.on_spot_tick(&tick("btc/usdt", price), &books, &oms) {
+                signals.push((signal.market_id, signal.token_id));
+            }
+        }
+        signals
+    }
+
+    #[test]
+    fn selects_the_token_from_direction_and_upside() {
+        let markets = LatencyStrategy::new(&settings());
+        let state = |id: &str| markets.markets().find(|m| m.config.market_id == id).unwrap();
+        assert_eq!(state("up").select_token(Direction::Up), "up-yes");
+        assert_eq!(state("up").select_token(Direction::Down), "up-no");
+        assert_eq!(state("down").select_token(Direction::Up), "down-no");
+        assert_eq!(state("down").select_token(Direction::Down), "down-yes");
+    }
+
+    #[test]
+    fn keeps_one_state_per_market_and_routes_by_symbol() {
+        let strategy = LatencyStrategy::new(&settings());
+        let mut symbols: Vec<&str> = strategy.symbols().collect();
+        symbols.sort();
+        assert_eq!(symbols, ["btc/usdt", "eth/usdt"]);
+        assert_eq!(strategy.markets().count(), 3);
+
+        // A 3% drop reaches both BTC markets, each buying its downside token;
+        // ETH and unknown symbols are left alone.
+        let signals = signals(&[100.0, 97.0]);
+        assert_eq!(signals, [("up".to_string(), "up-no".to_string()), ("down".to_string(), "down-yes".to_string())]);
+    }
+
+    #[test]
+    fn applies_each_markets_own_threshold() {
+        // 1% clears the 0.5% threshold of `up` but not the 2% of `down`.
+        assert_eq!(signals(&[100.0, 101.0]), [("up".to_string(), "up-yes".to_string())]);
+    }
+
+    #[test]
+    fn sizes_orders_to_the_per_trade_notional_and_the_ask() {
+        let mut settings = settings();
+        // Position limits are the risk engine's, not the strategy's.
+        settings.markets[0].max_position = 5.0;
+        let mut strategy = LatencyStrategy::new(&settings);
+        let (mut books, oms) = (books(), Oms::new());
+        strategy.on_spot_tick(&tick("btc/usdt", 100.0), &books, &oms);
+        for price in [101.0, 102.0] {
+            let signals = strategy.on_spot_tick(&tick("btc/usdt", price), &books, &oms);
+            let up = signals.iter().find(|s| s.market_id == "up").unwrap();
+            assert_eq!((up.price, up.size), (0.5, 20.0));
+        }
+
+        let thin = BookEvent {
+            asset_id: "up-yes".to_string(),
+            market: "up".to_string(),
+            bids: vec![PriceLevel { price: 0.45, size: 1000.0 }],
+            asks: vec![PriceLevel { price: 0.5, size: 4.0 }],
+            timestamp: 2,
+            hash: None,
+        };
+        books.apply_resync(&thin, 2);
+        let signals = strategy.on_spot_tick(&tick("btc/usdt", 103.0), &books, &oms);
+        let up = signals.iter().find(|s| s.market_id == "up").unwrap();
+        assert_eq!((up.size, up.notional), (4.0, 2.0));
+    }
+
+    #[test]
+    fn re_anchors_a_reference_older_than_the_window() {
+        let mut settings = settings();
+        settings.markets[0].reference_window_ms = Some(500);
+        let mut strategy = LatencyStrategy::new(&settings);
+        let (books, oms) = (books(), Oms::new());
+        let up = |signals: Vec<TradeSignal>| signals.iter().any(|s| s.market_id == "up");
+        strategy.on_spot_tick(&tick_at("btc/usdt", 100.0, 1_000), &books, &oms);
+        // A 1% move within the window trades.
+        assert!(up(strategy.on_spot_tick(&tick_at("btc/usdt", 101.0, 1_500), &books, &oms)));
+        // The same move spread over more than the window only moves the reference.
+        assert!(!up(strategy.on_spot_tick(&tick_at("btc/usdt", 102.01, 2_001), &books, &oms)));
+        let reference = strategy.markets().find(|m| m.config.market_id == "up").unwrap();
+        assert_eq!((reference.reference_price, reference.reference_at_ms), (Some(102.01), 2_001));
+        assert!(up(strategy.on_spot_tick(&tick_at("btc/usdt", 103.1, 2_400), &books, &oms)));
+    }
+}
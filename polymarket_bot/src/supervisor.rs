@@ -0,0 +1,461 @@
+//! Websocket connection supervision.
+//!
+//! A supervised connection runs in its own task: it connects (which also sends
+//! the subscriptions), forwards every text frame, and on close, error or EOF
+//! waits out a capped, jittered exponential backoff before connecting again.
+//! The socket is pinged on a timer, and one that delivers nothing at all, not
+//! even a pong, for the read timeout counts as dropped: a half-open connection
+//! never closes on its own.
+//! The backoff only starts over once a connection has delivered a frame, so a
+//! server that accepts and at once drops connections is not hammered.
+//! Connection state changes are delivered on the same channel as the frames,
+//! so consumers see exactly where gaps in the data are. A connection spawned
+//! with a [`Recorder`] also writes each frame and state change to the capture.
+
+use futures_util::future::BoxFuture;
+use futures_util::{SinkExt, StreamExt};
+use rand::Rng;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+use crate::feeds::{FeedError, WsStream};
+use crate::recorder::{CapturedEvent, Recorder};
+
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// Reconnect delay policy, defaults mirror `KrakenTickerClient` (1s doubling to 8s).
+#[derive(Debug, Clone, Copy)]
+pub struct BackoffPolicy {
+    pub initial: Duration,
+    pub max: Duration,
+    /// Fraction of each delay that is randomized away, in `[0, 1]`.
+    pub jitter: f64,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self { initial: Duration::from_secs(1), max: Duration::from_secs(8), jitter: 0.2 }
+    }
+}
+
+/// Liveness checks of a connection.
+#[derive(Debug, Clone, Copy)]
+pub struct Keepalive {
+    /// How often a ping is sent.
+    pub interval: Duration,
+    /// Text frame sent as the ping, for servers that want an application-level
+    /// one; a websocket ping when unset.
+    pub text: Option<&'static str>,
+    /// Silence after which the connection is dropped and reopened.
+    pub read_timeout: Duration,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Self { interval: Duration::from_secs(10), text: None, read_timeout: Duration::from_secs(30) }
+    }
+}
+
+/// Stateful backoff iterator for one connection.
+#[derive(Debug)]
+pub struct Backoff {
+    policy: BackoffPolicy,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(policy: BackoffPolicy) -> Self {
+        Self { policy, current: policy.initial }
+    }
+
+    /// Delay before the next attempt; doubles up to `max` on each call.
+    pub fn next_delay(&mut self) -> Duration {
+        let base = self.current.min(self.policy.max);
+        self.current = (self.current * 2).min(self.policy.max);
+        let jitter = self.policy.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return base;
+        }
+        base.mul_f64(1.0 - rand::thread_rng().gen_range(0.0..jitter))
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.policy.initial;
+    }
+}
+
+/// What a supervised connection reports.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// Connected and subscribed. `reconnects` counts previous successful connections.
+    Connected { reconnects: u32 },
+    Frame(String),
+    /// The connection closed, errored or hit EOF.
+    Disconnected { reason: String },
+    /// About to sleep before connection attempt number `attempt`.
+    Retrying { attempt: u32, delay: Duration, error: Option<String> },
+}
+
+/// Opens (and subscribes) a websocket; called again for every reconnect.
+pub trait Connect: Fn() -> BoxFuture<'static, Result<WsStream, FeedError>> + Send + Sync + 'static {}
+
+impl<F> Connect for F where F: Fn() -> BoxFuture<'static, Result<WsStream, FeedError>> + Send + Sync + 'static {}
+
+/// Spawn a task that keeps `connector` connected and streams its events.
+/// The task exits once the receiver is dropped.
+pub fn spawn<C: Connect>(
+    name: impl Into<String>,
+    policy: BackoffPolicy,
+    keepalive: Keepalive,
+    connector: C,
+) -> (mpsc::Receiver<StreamEvent>, JoinHandle<()>) {
+    spawn_recorded(name, policy, keepalive, connector, None)
+}
+
+/// [`spawn`], also capturing every frame under `name` when `recorder` is set.
+pub fn spawn_recorded<C: Connect>(
+    name: impl Into<String>,
+    policy: BackoffPolicy,
+    keepalive: Keepalive,
+    connector: C,
+    recorder: Option<Recorder>,
+) -> (mpsc::Receiver<StreamEvent>, JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let name = name.into();
+    let handle = tokio::spawn(async move { supervise(&name, policy, keepalive, connector, recorder, tx).await });
+    (rx, handle)
+}
+
+async fn supervise<C: Connect>(
+    name: &str,
+    policy: BackoffPolicy,
+    keepalive: Keepalive,
+    connector: C,
+    recorder: Option<Recorder>,
+    tx: mpsc::Sender<StreamEvent>,
+) {
+    let record = |event: CapturedEvent| {
+        if let Some(recorder) = &recorder {
+            recorder.record(name, event);
+        }
+    };
+    let mut backoff = Backoff::new(policy);
+    let mut reconnects = 0;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let mut ws_stream = match connector().await {
+            Ok(ws_stream) => ws_stream,
+            Err(e) => {
+                let delay = backoff.next_delay();
+                eprintln!("{}: connect attempt {} failed: {}; retrying in {:?}", name, attempt, e, delay);
+                let event = StreamEvent::Retrying { attempt: attempt + 1, delay, error: Some(e.to_string()) };
+                if tx.send(event).await.is_err() {
+                    return;
+                }
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+        };
+        attempt = 0;
+        record(CapturedEvent::Connected);
+        if tx.send(StreamEvent::Connected { reconnects }).await.is_err() {
+            return;
+        }
+        reconnects += 1;
+
+        let mut ping = tokio::time::interval_at(Instant::now() + keepalive.interval, keepalive.interval);
+        ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last_read = Instant::now();
+        let reason = loop {
+            let message = tokio::select! {
+                message = ws_stream.next() => message,
+                _ = ping.tick() => {
+                    let ping = match keepalive.text {
+                        Some(text) => Message::Text(text.to_string()),
+                        None => Message::Ping(Vec::new()),
+                    };
+                    if let Err(e) = ws_stream.send(ping).await {
+                        break format!("error: {}", e);
+                    }
+                    continue;
+                }
+                _ = tokio::time::sleep_until(last_read + keepalive.read_timeout) => {
+                    break format!("no data for {:?}", keepalive.read_timeout);
+                }
+            };
+            last_read = Instant::now();
+            match message {
+                Some(Ok(Message::Text(text))) => {
+                    backoff.reset();
+                    record(CapturedEvent::Frame { text: text.clone() });
+                    if tx.send(StreamEvent::Frame(text)).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(Message::Close(frame))) => {
+                    break match frame {
+                        Some(frame) => format!("closed by peer ({}: {})", frame.code, frame.reason),
+                        None => "closed by peer".to_string(),
+                    };
+                }
+                // Pings are answered by tungstenite itself; pongs only count as liveness.
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break format!("error: {}", e),
+                None => break "end of stream".to_string(),
+            }
+        };
+        eprintln!("{}: disconnected: {}", name, reason);
+        record(CapturedEvent::Disconnected { reason: reason.clone() });
+        if tx.send(StreamEvent::Disconnected { reason }).await.is_err() {
+            return;
+        }
+        let delay = backoff.next_delay();
+        if tx.send(StreamEvent::Retrying { attempt: 1, delay, error: None }).await.is_err() {
+            return;
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::{accept_async, connect_async};
+
+    fn fast_policy() -> BackoffPolicy {
+        BackoffPolicy { initial: Duration::from_millis(10), max: Duration::from_millis(40), jitter: 0.0 }
+    }
+
+    fn fast_keepalive() -> Keepalive {
+        Keepalive { interval: Duration::from_millis(20), text: None, read_timeout: Duration::from_millis(100) }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_cap_and_resets() {
+        let mut backoff = Backoff::new(fast_policy());
+        let delays: Vec<u64> = (0..5).map(|_| backoff.next_delay().as_millis() as u64).collect();
+        assert_eq!(delays, vec![10, 20, 40, 40, 40]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn jitter_only_shortens_delays() {
+        let policy = BackoffPolicy { jitter: 0.5, ..fast_policy() };
+        for _ in 0..100 {
+            let delay = Backoff::new(policy).next_delay();
+            assert!(delay > Duration::from_millis(5) && delay <= Duration::from_millis(10), "{:?}", delay);
+        }
+    }
+
+    async fn next_event(rx: &mut mpsc::Receiver<StreamEvent>) -> StreamEvent {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.expect("timed out").expect("channel closed")
+    }
+
+    #[tokio::test]
+    async fn reconnects_and_resubscribes_after_server_drops() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // First connection: answer the subscription, then drop without a close
+        // frame. Second: answer, then close politely. Third: stay up.
+        let server = tokio::spawn(async move {
+            let mut subscriptions = Vec::new();
+            for round in 0..3 {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = accept_async(tcp).await.unwrap();
+                let Some(Ok(Message::Text(sub))) = ws.next().await else { panic!("no subscription") };
+                subscriptions.push(sub);
+                ws.send(Message::Text(format!("frame-{}", round))).await.unwrap();
+                match round {
+                    0 => drop(ws),
+                    1 => ws.close(None).await.unwrap(),
+                    _ => {
+                        let _ = ws.next().await;
+                    }
+                }
+            }
+            subscriptions
+        });
+
+        let connector = move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+            let url = url.clone();
+            Box::pin(async move {
+                let (mut ws, _) = connect_async(url).await?;
+                ws.send(Message::Text("subscribe".to_string())).await?;
+                Ok(ws)
+            })
+        };
+        let (mut rx, handle) = spawn("test", fast_policy(), Keepalive::default(), connector);
+
+        for round in 0..3u32 {
+            assert_eq!(next_event(&mut rx).await, StreamEvent::Connected { reconnects: round });
+            assert_eq!(next_event(&mut rx).await, StreamEvent::Frame(format!("frame-{}", round)));
+            if round < 2 {
+                assert!(matches!(next_event(&mut rx).await, StreamEvent::Disconnected { .. }));
+                assert!(matches!(next_event(&mut rx).await, StreamEvent::Retrying { attempt: 1, .. }));
+            }
+        }
+        handle.abort();
+        let subscriptions = server.await.unwrap();
+        assert_eq!(subscriptions, vec!["subscribe"; 3]);
+    }
+
+    #[tokio::test]
+    async fn backs_off_when_the_server_drops_every_connection_at_once() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = accept_async(tcp).await.unwrap();
+                ws.close(None).await.unwrap();
+            }
+        });
+
+        let connector = move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+            let url = url.clone();
+            Box::pin(async move { Ok(connect_async(url).await?.0) })
+        };
+        let (mut rx, handle) = spawn("test", fast_policy(), Keepalive::default(), connector);
+
+        let mut delays = Vec::new();
+        while delays.len() < 4 {
+            match next_event(&mut rx).await {
+                StreamEvent::Retrying { delay, .. } => delays.push(delay.as_millis()),
+                StreamEvent::Connected { .. } | StreamEvent::Disconnected { .. } => {}
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(delays, vec![10, 20, 40, 40]);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn reconnects_a_connection_that_goes_silent() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // Accept and never read or write again, like the far end of a half-open
+        // connection: pings go unanswered.
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            loop {
+                let (tcp, _) = listener.accept().await.unwrap();
+                sockets.push(accept_async(tcp).await.unwrap());
+            }
+        });
+
+        let connector = move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+            let url = url.clone();
+            Box::pin(async move { Ok(connect_async(url).await?.0) })
+        };
+        let (mut rx, handle) = spawn("test", fast_policy(), fast_keepalive(), connector);
+
+        assert_eq!(next_event(&mut rx).await, StreamEvent::Connected { reconnects: 0 });
+        let StreamEvent::Disconnected { reason } = next_event(&mut rx).await else { panic!("still connected") };
+        assert_eq!(reason, "no data for 100ms");
+        assert!(matches!(next_event(&mut rx).await, StreamEvent::Retrying { attempt: 1, .. }));
+        assert_eq!(next_event(&mut rx).await, StreamEvent::Connected { reconnects: 1 });
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn keeps_an_idle_connection_that_answers_pings() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // Reading is what makes tungstenite answer the pings; nothing is sent.
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(tcp).await.unwrap();
+            while let Some(Ok(_)) = ws.next().await {}
+        });
+
+        let connector = move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+            let url = url.clone();
+            Box::pin(async move { Ok(connect_async(url).await?.0) })
+        };
+        let (mut rx, handle) = spawn("test", fast_policy(), fast_keepalive(), connector);
+
+        assert_eq!(next_event(&mut rx).await, StreamEvent::Connected { reconnects: 0 });
+        let quiet = tokio::time::timeout(Duration::from_millis(500), rx.recv()).await;
+        assert!(quiet.is_err(), "{:?}", quiet);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn sends_text_pings_and_forwards_the_replies() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(tcp).await.unwrap();
+            while let Some(Ok(message)) = ws.next().await {
+                if message == Message::Text("PING".to_string()) {
+                    ws.send(Message::Text("PONG".to_string())).await.unwrap();
+                }
+            }
+        });
+
+        let connector = move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+            let url = url.clone();
+            Box::pin(async move { Ok(connect_async(url).await?.0) })
+        };
+        let keepalive = Keepalive { text: Some("PING"), ..fast_keepalive() };
+        let (mut rx, handle) = spawn("test", fast_policy(), keepalive, connector);
+
+        assert_eq!(next_event(&mut rx).await, StreamEvent::Connected { reconnects: 0 });
+        assert_eq!(next_event(&mut rx).await, StreamEvent::Frame("PONG".to_string()));
+        assert_eq!(next_event(&mut rx).await, StreamEvent::Frame("PONG".to_string()));
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn retries_with_backoff_while_server_is_down() {
+        // Reserve a port, then close it so connects are refused.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let connector = move || -> BoxFuture<'static, Result<WsStream, FeedError>> {
+            Box::pin(async move { Ok(connect_async(format!("ws://{}", addr)).await?.0) })
+        };
+        let (mut rx, handle) = spawn("test", fast_policy(), Keepalive::default(), connector);
+
+        let mut delays = Vec::new();
+        for expected_attempt in 2..=4 {
+            match next_event(&mut rx).await {
+                StreamEvent::Retrying { attempt, delay, error } => {
+                    assert_eq!(attempt, expected_attempt);
+                    assert!(error.is_some());
+                    delays.push(delay.as_millis());
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(delays, vec![10, 20, 40]);
+
+        // Bring the server up on the same port; the supervisor should find it.
+        let listener = TcpListener::bind(addr).await.unwrap();
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut ws = accept_async(tcp).await.unwrap();
+            let _ = ws.next().await;
+        });
+        loop {
+            match next_event(&mut rx).await {
+                StreamEvent::Connected { reconnects } => {
+                    assert_eq!(reconnects, 0);
+                    break;
+                }
+                StreamEvent::Retrying { .. } => {}
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        handle.abort();
+    }
+}
@@ -0,0 +1,381 @@
+//! Parameter sweeps: which strategy parameters would have done best.
+//!
+//! The `sweep` section of the settings lists values for some strategy
+//! parameters; every combination of them, or a seeded random sample of the
+//! combinations, is backtested on each capture session. Backtests are CPU
+//! bound, so each parameter set runs on the blocking pool, `jobs` at a time,
+//! over the same shared sessions; the parameter sets come back ranked by
+//! total PnL.
+//!
+//! Sharpe is the mean over the standard deviation of the per-minute PnL
+//! changes of every session, not annualized.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::task::{JoinError, JoinSet};
+
+use crate::backtest;
+use crate::config::{Settings, SweepConfig};
+use crate::polymarket::order::OrderError;
+use crate::recorder::CapturedFrame;
+
+/// A strategy parameter that can be swept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    ThresholdPct,
+    MaxPosition,
+    ReferenceWindowMs,
+    MaxNotionalPerTrade,
+    MaxTradesPerMinute,
+    TokenCooldownMs,
+}
+
+impl Param {
+    /// Settings key of the parameter.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Param::ThresholdPct => "threshold_pct",
+            Param::MaxPosition => "max_position",
+            Param::ReferenceWindowMs => "reference_window_ms",
+            Param::MaxNotionalPerTrade => "max_notional_per_trade",
+            Param::MaxTradesPerMinute => "max_trades_per_minute",
+            Param::TokenCooldownMs => "token_cooldown_ms",
+        }
+    }
+
+    fn apply(&self, value: f64, settings: &mut Settings) {
+        match self {
+            Param::ThresholdPct => settings.markets.iter_mut().for_each(|m| m.threshold_pct = value),
+            Param::MaxPosition => settings.markets.iter_mut().for_each(|m| m.max_position = value),
+            Param::ReferenceWindowMs => {
+                settings.markets.iter_mut().for_each(|m| m.reference_window_ms = Some(value as u64))
+            }
+            Param::MaxNotionalPerTrade => settings.risk.max_notional_per_trade = value,
+            Param::MaxTradesPerMinute => settings.risk.max_trades_per_minute = value as u32,
+            Param::TokenCooldownMs => settings.risk.token_cooldown_ms = value as i64,
+        }
+    }
+
+    fn json(&self, value: f64) -> Value {
+        match self {
+            Param::ReferenceWindowMs | Param::MaxTradesPerMinute | Param::TokenCooldownMs => json!(value as i64),
+            _ => json!(value),
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One value for every swept parameter.
+pub type ParamSet = Vec<(Param, f64)>;
+
+/// The swept parameters and the values each one takes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    axes: Vec<(Param, Vec<f64>)>,
+}
+
+impl Grid {
+    pub fn new(config: &SweepConfig) -> Self {
+        let axes = [
+            (Param::ThresholdPct, config.threshold_pct.clone()),
+            (Param::MaxPosition, config.max_position.clone()),
+            (Param::ReferenceWindowMs, config.reference_window_ms.iter().map(|&ms| ms as f64).collect()),
+            (Param::MaxNotionalPerTrade, config.max_notional_per_trade.clone()),
+            (Param::MaxTradesPerMinute, config.max_trades_per_minute.iter().map(|&n| n as f64).collect()),
+            (Param::TokenCooldownMs, config.token_cooldown_ms.iter().map(|&ms| ms as f64).collect()),
+        ];
+        Self { axes: axes.into_iter().filter(|(_, values)| !values.is_empty()).collect() }
+    }
+
+    /// Swept parameters, in column order.
+    pub fn params(&self) -> impl Iterator<Item = Param> + '_ {
+        self.axes.iter().map(|(param, _)| *param)
+    }
+
+    /// Number of combinations; an empty grid has one, the configured values.
+    pub fn combinations(&self) -> usize {
+        self.axes.iter().fold(1, |n, (_, values)| n.saturating_mul(values.len()))
+    }
+
+    /// Combination `index`, with the last parameter varying fastest.
+    fn get(&self, mut index: usize) -> ParamSet {
+        let mut set: ParamSet = Vec::with_capacity(self.axes.len());
+        for (param, values) in self.axes.iter().rev() {
+            set.push((*param, values[index % values.len()]));
+            index /= values.len();
+        }
+        set.reverse();
+        set
+    }
+
+    /// Every combination in order, or `samples` distinct ones drawn with
+    /// `seed`, still in grid order.
+    pub fn param_sets(&self, samples: Option<usize>, seed: u64) -> Vec<ParamSet> {
+        let len = self.combinations();
+        let mut indices: Vec<usize> = match samples {
+            Some(samples) if samples < len => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                rand::seq::index::sample(&mut rng, len, samples).into_vec()
+            }
+            _ => (0..len).collect(),
+        };
+        indices.sort_unstable();
+        indices.into_iter().map(|index| self.get(index)).collect()
+    }
+}
+
+/// How one parameter set did across every session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepResult {
+    pub params: ParamSet,
+    /// Total PnL, summed over the sessions.
+    pub pnl: f64,
+    /// `None` with fewer than two minutes of PnL or no variation in it.
+    pub sharpe: Option<f64>,
+    /// Orders that filled.
+    pub trades: u64,
+    /// Deepest drawdown of any one session.
+    pub max_drawdown: f64,
+}
+
+impl SweepResult {
+    fn new(params: ParamSet, reports: &[backtest::BacktestReport]) -> Self {
+        let minute_pnl: Vec<f64> = reports.iter().flat_map(|r| r.minute_pnl.iter().copied()).collect();
+        Self {
+            params,
+            pnl: reports.iter().map(|r| r.total.pnl.total()).sum(),
+            sharpe: sharpe(&minute_pnl),
+            trades: reports.iter().map(|r| r.total.filled).sum(),
+            max_drawdown: reports.iter().map(|r| r.total.max_drawdown).fold(0.0, f64::max),
+        }
+    }
+}
+
+/// Mean over sample standard deviation of `changes`.
+fn sharpe(changes: &[f64]) -> Option<f64> {
+    if changes.len() < 2 {
+        return None;
+    }
+    let n = changes.len() as f64;
+    let mean = changes.iter().sum::<f64>() / n;
+    let variance = changes.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (variance > 0.0).then(|| mean / variance.sqrt())
+}
+
+/// Index of a parameter set and how it did.
+type Run = (usize, Result<SweepResult, OrderError>);
+
+/// Backtest every parameter set in `settings.sweep` on every session, at
+/// most `jobs` at a time, and rank the results by PnL, then Sharpe.
+pub async fn run(
+    settings: &Settings,
+    sessions: Vec<Vec<CapturedFrame>>,
+    jobs: usize,
+) -> Result<Vec<SweepResult>, OrderError> {
+    let grid = Grid::new(&settings.sweep);
+    let param_sets = grid.param_sets(settings.sweep.samples, settings.sweep.seed);
+    let sessions = Arc::new(sessions);
+    let runtime = Handle::current();
+    let mut results: Vec<Option<SweepResult>> = vec![None; param_sets.len()];
+    let mut running: JoinSet<Run> = JoinSet::new();
+    for (index, params) in param_sets.into_iter().enumerate() {
+        if running.len() >= jobs.max(1) {
+            if let Some(joined) = running.join_next().await {
+                store(&mut results, joined)?;
+            }
+        }
+        let mut settings = settings.clone();
+        for (param, value) in &params {
+            param.apply(*value, &mut settings);
+        }
+        let (sessions, runtime) = (Arc::clone(&sessions), runtime.clone());
+        running.spawn_blocking(move || {
+            let mut reports = Vec::with_capacity(sessions.len());
+            for frames in sessions.iter() {
+                match runtime.block_on(backtest::run(&settings, frames)) {
+                    Ok(report) => reports.push(report),
+                    Err(e) => return (index, Err(e)),
+                }
+            }
+            (index, Ok(SweepResult::new(params, &reports)))
+        });
+    }
+    while let Some(joined) = running.join_next().await {
+        store(&mut results, joined)?;
+    }
+
+    let mut ranked: Vec<SweepResult> = results.into_iter().flatten().collect();
+    ranked.sort_by(|a, b| {
+        let sharpe = |r: &SweepResult| r.sharpe.unwrap_or(f64::NEG_INFINITY);
+        b.pnl.total_cmp(&a.pnl).then(sharpe(b).total_cmp(&sharpe(a)))
+    });
+    Ok(ranked)
+}
+
+fn store(results: &mut [Option<SweepResult>], joined: Result<Run, JoinError>) -> Result<(), OrderError> {
+    match joined {
+        Ok((index, result)) => results[index] = Some(result?),
+        // A backtest only panics on a bug; let it surface.
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+    Ok(())
+}
+
+/// Write ranked `results` as CSV, one row per parameter set.
+pub fn write_csv(results: &[SweepResult], mut out: impl Write) -> io::Result<()> {
+    let mut header = vec!["rank".to_string()];
+    if let Some(first) = results.first() {
+        header.extend(first.params.iter().map(|(param, _)| param.to_string()));
+    }
+    header.extend(["pnl", "sharpe", "trades", "max_drawdown"].map(String::from));
+    writeln!(out, "{}", header.join(","))?;
+    for (rank, result) in results.iter().enumerate() {
+        let mut row = vec![(rank + 1).to_string()];
+        row.extend(result.params.iter().map(|(_, value)| value.to_string()));
+        row.push(result.pnl.to_string());
+        row.push(result.sharpe.map(|s| s.to_string()).unwrap_or_default());
+        row.push(result.trades.to_string());
+        row.push(result.max_drawdown.to_string());
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+/// Write ranked `results` as a JSON array, best first.
+pub fn write_json(results: &[SweepResult], mut out: impl Write) -> io::Result<()> {
+    let rows: Vec<Value> = results
+        .iter()
+        .enumerate()
+        .map(|(rank, result)| {
+            let params: Map<String, Value> =
+                result.params.iter().map(|(param, value)| (param.to_string(), param.json(*value))).collect();
+            json!({
+                "rank": rank + 1,
+                "params": params,
+                "pnl": result.pnl,
+                "sharpe": result.sharpe,
+                "trades": result.trades,
+                "max_drawdown": result.max_drawdown,
+            })
+        })
+        .collect();
+    serde_json::to_writer_pretty(&mut out, &rows)?;
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::read_capture;
+    use std::path::Path;
+
+    fn settings(sweep: &str) -> Settings {
+        let yaml = format!(
+            r#"
+spot_venue: binance
+markets:
+  - market_id: "0xabc"
+    symbol: "btc/usdt"
+    yes_token_id: "1111"
+    no_token_id: "2222"
+    threshold_pct: 0.005
+    order_type: fok
+risk:
+  max_notional_per_trade: 10.0
+  self_slippage_buffer_pct: 0.0
+sweep:
+{}
+"#,
+            sweep
+        );
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    fn session() -> Vec<CapturedFrame> {
+        read_capture(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay/capture.jsonl")).unwrap()
+    }
+
+    #[test]
+    fn walks_the_grid_with_the_last_parameter_fastest() {
+        let grid = Grid::new(&settings("  threshold_pct: [0.01, 0.02]\n  token_cooldown_ms: [0, 500, 1000]").sweep);
+        assert_eq!(grid.params().collect::<Vec<_>>(), [Param::ThresholdPct, Param::TokenCooldownMs]);
+        assert_eq!(grid.combinations(), 6);
+        let sets = grid.param_sets(None, 0);
+        assert_eq!(sets.len(), 6);
+        assert_eq!(sets[0], [(Param::ThresholdPct, 0.01), (Param::TokenCooldownMs, 0.0)]);
+        assert_eq!(sets[1], [(Param::ThresholdPct, 0.01), (Param::TokenCooldownMs, 500.0)]);
+        assert_eq!(sets[5], [(Param::ThresholdPct, 0.02), (Param::TokenCooldownMs, 1000.0)]);
+
+        let sample = grid.param_sets(Some(3), 7);
+        assert_eq!(sample.len(), 3);
+        assert_eq!(sample, grid.param_sets(Some(3), 7));
+        assert!(sample.iter().all(|set| sets.contains(set)));
+        assert!(sample.windows(2).all(|w| w[0] != w[1]));
+        // An empty grid is the configured parameters, once.
+        assert_eq!(Grid::new(&SweepConfig::default()).param_sets(None, 0), [Vec::new()]);
+    }
+
+    #[test]
+    fn sets_the_reference_window_of_every_market() {
+        let mut settings = settings("  reference_window_ms: [250, 5000]");
+        let grid = Grid::new(&settings.sweep);
+        let sets = grid.param_sets(None, 0);
+        assert_eq!(sets, [[(Param::ReferenceWindowMs, 250.0)], [(Param::ReferenceWindowMs, 5000.0)]]);
+        Param::ReferenceWindowMs.apply(5000.0, &mut settings);
+        assert_eq!(settings.markets[0].reference_window_ms, Some(5000));
+        assert_eq!(Param::ReferenceWindowMs.json(5000.0), json!(5000));
+    }
+
+    #[test]
+    fn sharpe_needs_variation() {
+        assert_eq!(sharpe(&[1.0]), None);
+        assert_eq!(sharpe(&[1.0, 1.0]), None);
+        // Mean 2, sample standard deviation 2.
+        assert_eq!(sharpe(&[0.0, 4.0, 2.0]), Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn ranks_parameter_sets_by_pnl() {
+        // Neither spot move reaches 2%; at 0.5% both trade and net 1.5.
+        let thresholds = settings("  threshold_pct: [0.02, 0.005]\n  max_notional_per_trade: [10.0]");
+        let results = run(&thresholds, vec![session(), session()], 2).await.unwrap();
+        assert_eq!(results.len(), 2);
+        let best = &results[0];
+        assert_eq!(best.params, [(Param::ThresholdPct, 0.005), (Param::MaxNotionalPerTrade, 10.0)]);
+        assert!((best.pnl - 3.0).abs() < 1e-9, "{}", best.pnl);
+        assert_eq!((best.trades, best.sharpe), (4, None));
+        assert!((best.max_drawdown - 1.0).abs() < 1e-9, "{}", best.max_drawdown);
+        assert_eq!((results[1].pnl, results[1].trades), (0.0, 0));
+
+        let mut csv = Vec::new();
+        write_csv(&results, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "rank,threshold_pct,max_notional_per_trade,pnl,sharpe,trades,max_drawdown");
+        assert!(lines[1].starts_with("1,0.005,10,"), "{}", lines[1]);
+        assert_eq!(lines[2], "2,0.02,10,0,,0,0");
+
+        let mut json = Vec::new();
+        write_json(&results, &mut json).unwrap();
+        let json: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json[0]["params"]["threshold_pct"], 0.005);
+        assert_eq!(json[1]["rank"], 2);
+        assert_eq!(json[1]["sharpe"], Value::Null);
+
+        let cooldown = settings("  token_cooldown_ms: [250]");
+        let mut json = Vec::new();
+        write_json(&run(&cooldown, vec![session()], 1).await.unwrap(), &mut json).unwrap();
+        let json: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json[0]["params"]["token_cooldown_ms"].as_i64(), Some(250));
+    }
+}